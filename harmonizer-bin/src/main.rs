@@ -1,11 +1,35 @@
 use std::{fs::read_to_string, path::PathBuf};
 
-use harmonizer::{harmonize, ServiceDefinition};
+use harmonizer::{Harmonizer, HarmonizerError, ServiceDefinition};
 
 fn main() {
-    let composed = harmonize(
-        std::env::args()
-            .skip(1)
+    let mut args = std::env::args().skip(1).peekable();
+
+    // The supergraph and query plans are printed to stdout, so anything the
+    // JavaScript prints has to go elsewhere.
+    let harmonizer = Harmonizer::with_console(|level, message| eprintln!("{}: {}", level, message));
+    let mut harmonizer = match harmonizer {
+        Ok(harmonizer) => harmonizer,
+        Err(err) => {
+            eprintln!("the composition runtime could not be started: {}", err);
+            return;
+        }
+    };
+
+    if args.peek().map(String::as_str) == Some("plan") {
+        args.next();
+        print_query_plan(&mut harmonizer, args.collect());
+    } else {
+        compose(&mut harmonizer, args.collect());
+    }
+}
+
+/// Compose the subgraph schema files into a supergraph, naming each subgraph
+/// after its file.
+fn compose(harmonizer: &mut Harmonizer, files: Vec<String>) {
+    let composed = harmonizer.compose(
+        files
+            .into_iter()
             .map(|file| {
                 let src = read_to_string(&file).expect("reading source file");
                 ServiceDefinition::new(
@@ -22,10 +46,18 @@ fn main() {
     );
 
     match composed {
-        Ok(schema) => {
-            println!("{}", schema);
+        Ok(output) => {
+            println!("{}", output.supergraph_sdl);
+
+            for hint in &output.hints {
+                eprintln!("hint: {}", hint);
+
+                for location in &hint.locations {
+                    eprintln!("       at {}", location);
+                }
+            }
         }
-        Err(errors) => {
+        Err(HarmonizerError::Composition(errors)) => {
             eprintln!(
                 "{count} {errors} occurred during composition:",
                 count = errors.len(),
@@ -43,7 +75,69 @@ fn main() {
                 } else {
                     eprintln!("  {index}. {code}", index = index + 1, code = err.code())
                 }
+
+                for location in &err.locations {
+                    eprintln!("       at {}", location);
+                }
             }
         }
+        Err(err) => {
+            eprintln!("composition could not be attempted: {}", err);
+        }
+    }
+}
+
+/// Print the query plan for the operation in a file, in the same format as
+/// `prettyFormatQueryPlan` from `query-planner-js`.
+fn print_query_plan(harmonizer: &mut Harmonizer, args: Vec<String>) {
+    let (supergraph_file, operation_file, operation_name) = match args.as_slice() {
+        [supergraph, operation] => (supergraph, operation, None),
+        [supergraph, operation, name] => (supergraph, operation, Some(name.as_str())),
+        _ => {
+            eprintln!("usage: harmonizer plan <supergraph file> <operation file> [operation name]");
+            return;
+        }
+    };
+
+    let supergraph_sdl = read_to_string(supergraph_file).expect("reading supergraph file");
+    let operation = read_to_string(operation_file).expect("reading operation file");
+
+    match harmonizer.plan(&supergraph_sdl, &operation, operation_name) {
+        Ok(query_plan) => {
+            println!("{}", query_plan);
+        }
+        Err(HarmonizerError::Planning(errors)) => {
+            eprintln!(
+                "{count} {errors} occurred during query planning:",
+                count = errors.len(),
+                errors = if errors.len() == 1 { "error" } else { "errors" }
+            );
+
+            for (index, err) in errors.iter().enumerate() {
+                eprintln!("  {index}. {message}", index = index + 1, message = err);
+
+                for location in &err.locations {
+                    eprintln!("       at {}:{}", operation_file, location);
+                }
+            }
+        }
+        Err(HarmonizerError::Supergraph(errors)) => {
+            eprintln!(
+                "{count} {errors} found in the supergraph:",
+                count = errors.len(),
+                errors = if errors.len() == 1 { "error" } else { "errors" }
+            );
+
+            for (index, err) in errors.iter().enumerate() {
+                eprintln!("  {index}. {message}", index = index + 1, message = err);
+
+                for location in &err.locations {
+                    eprintln!("       at {}:{}", supergraph_file, location);
+                }
+            }
+        }
+        Err(err) => {
+            eprintln!("query planning could not be attempted: {}", err);
+        }
     }
 }
@@ -0,0 +1,37 @@
+/*!
+# Harmonizer snapshots
+
+Creates the V8 startup snapshot which the `harmonizer` crate boots its
+composition runtimes from when built with its `snapshot` feature.  This is a
+separate crate, used only by the harmonizer's build script, so that only
+builds with that feature compile V8 for the build script, too; Cargo doesn't
+allow the harmonizer to depend on `deno_core` under a second name instead.
+*/
+
+#![forbid(unsafe_code)]
+#![deny(missing_debug_implementations, nonstandard_style)]
+#![warn(missing_docs, future_incompatible, unreachable_pub, rust_2018_idioms)]
+
+use deno_core::{JsRuntime, RuntimeOptions};
+
+/// Evaluate each of the given `(name, source)` scripts in turn within a new
+/// runtime and return a V8 startup snapshot of the result.
+///
+/// # Panics
+///
+/// Panics when any of the scripts fails to evaluate, since a build can't go
+/// on without its snapshot.
+pub fn create_snapshot(scripts: &[(&str, &str)]) -> Box<[u8]> {
+    let mut runtime = JsRuntime::new(RuntimeOptions {
+        will_snapshot: true,
+        ..Default::default()
+    });
+
+    for (name, source) in scripts {
+        runtime
+            .execute(name, source)
+            .unwrap_or_else(|error| panic!("unable to evaluate {}: {}", name, error));
+    }
+
+    runtime.snapshot().to_vec().into_boxed_slice()
+}
@@ -1,13 +1,97 @@
 use std::fs::metadata;
+use std::path::Path;
 use std::process::Command;
 
+/// What `dist/composition.js` is bundled from by `rollup.config.js`, besides
+/// the libraries it bundles.
+const BUNDLE_INPUTS: &[&str] = &["js/index.mjs", "rollup.config.js"];
+
 fn main() {
-    if metadata("dist/composition.js").is_err() {
+    if cfg!(feature = "js") && bundle_is_missing_or_stale() {
         assert!(Command::new("npm")
             .current_dir("../")
-            .args(&["run", "compile:for-harmonizer-build-rs"])
+            .args(["run", "compile:for-harmonizer-build-rs"])
             .status()
             .unwrap()
             .success());
     }
+
+    emit_federation_version();
+
+    #[cfg(feature = "snapshot")]
+    create_snapshot();
+}
+
+/// Whether `dist/composition.js` needs to be bundled, because it doesn't
+/// exist or (within this repository, where it can be rebundled) because it
+/// is older than what it is bundled from.
+fn bundle_is_missing_or_stale() -> bool {
+    let bundled = match metadata("dist/composition.js").and_then(|bundle| bundle.modified()) {
+        Ok(bundled) => bundled,
+        Err(_) => return true,
+    };
+    Path::new("../package.json").exists()
+        && BUNDLE_INPUTS.iter().any(|input| {
+            metadata(input)
+                .and_then(|input| input.modified())
+                .map(|modified| modified > bundled)
+                .unwrap_or(false)
+        })
+}
+
+/// Watch the bundle and what it is bundled from.  Watching anything at all
+/// switches off Cargo's default of rerunning the build script whenever
+/// anything within the package changes, so everything which watches anything
+/// has to watch these, too.
+fn watch_bundle() {
+    for path in BUNDLE_INPUTS.iter().chain(&["dist/composition.js"]) {
+        if metadata(path).is_ok() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
+
+/// Make the version of `@apollo/federation` which is bundled into
+/// `dist/composition.js` available to `src/cache.rs` as
+/// `HARMONIZER_FEDERATION_VERSION`, since it determines what composition
+/// returns.  Outside of this repository (e.g., when building the published
+/// crate), the bundle is fixed for each version of this crate, so the version
+/// is reported as `bundled`.
+fn emit_federation_version() {
+    let package_json = "../federation-js/package.json";
+    // Cargo reruns the build script on every build when asked to watch a
+    // file which doesn't exist, as is the case outside of this repository.
+    if metadata(package_json).is_ok() {
+        println!("cargo:rerun-if-changed={}", package_json);
+        watch_bundle();
+    }
+
+    let version = std::fs::read_to_string(package_json)
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .and_then(|package| package["version"].as_str().map(str::to_string))
+        .unwrap_or_else(|| "bundled".to_string());
+    println!("cargo:rustc-env=HARMONIZER_FEDERATION_VERSION={}", version);
+}
+
+/// Evaluate `js/init.js`, the composition bundle and `js/diagnostics.js` within
+/// a runtime and write a V8 startup snapshot of the result to
+/// `$OUT_DIR/composition.snap`, from which `src/runtime.rs` will boot its
+/// runtimes.
+#[cfg(feature = "snapshot")]
+fn create_snapshot() {
+    use std::{env, fs, path::PathBuf};
+
+    println!("cargo:rerun-if-changed=js/init.js");
+    println!("cargo:rerun-if-changed=js/diagnostics.js");
+    watch_bundle();
+
+    let bundle = fs::read_to_string("dist/composition.js").expect("reading dist/composition.js");
+    let snapshot = harmonizer_snapshot::create_snapshot(&[
+        ("<init>", include_str!("js/init.js")),
+        ("composition.js", &bundle),
+        ("diagnostics.js", include_str!("js/diagnostics.js")),
+    ]);
+    let snapshot_path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("composition.snap");
+    fs::write(&snapshot_path, &*snapshot).expect("writing composition snapshot");
 }
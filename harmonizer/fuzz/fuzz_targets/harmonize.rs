@@ -0,0 +1,9 @@
+//! Compose arbitrary text, split into subgraphs at every `# subgraph: <name>`
+//! line, both natively and within V8.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    harmonizer_fuzz::check_composition(harmonizer_fuzz::service_list(data));
+});
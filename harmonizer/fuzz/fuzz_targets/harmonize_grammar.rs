@@ -0,0 +1,11 @@
+//! Compose service lists of (mostly) well-formed SDL, generated from arbitrary
+//! syntax trees which use federation's directives and share their names, both
+//! natively and within V8.
+
+#![no_main]
+use harmonizer_fuzz::ArbitraryServiceList;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|service_list: ArbitraryServiceList| {
+    harmonizer_fuzz::check_composition(service_list.service_list());
+});
@@ -0,0 +1,14 @@
+//! Parse and validate arbitrary text as the SDL of a single subgraph, within
+//! Rust.
+
+#![no_main]
+use harmonizer::ServiceDefinition;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    harmonizer_fuzz::check_subgraph(ServiceDefinition::new(
+        "subgraph",
+        "undefined",
+        String::from_utf8_lossy(data),
+    ));
+});
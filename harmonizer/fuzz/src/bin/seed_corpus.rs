@@ -0,0 +1,72 @@
+//! Seed the corpora of the fuzz targets with the service lists within
+//! `seeds`: the subgraphs of the snapshot test of the harmonizer and of the
+//! fixtures of the integration test suite, copied from where they're written.
+//!
+//! Run `cargo run --bin seed_corpus` within `harmonizer/fuzz` before fuzzing
+//! for the first time.  `harmonize_grammar` doesn't take SDL as its input, so
+//! it starts from an empty corpus instead.
+
+use harmonizer_fuzz::SUBGRAPH_MARKER;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+fn main() -> io::Result<()> {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let corpus = manifest_dir.join("corpus");
+    let harmonize = corpus.join("harmonize");
+    let parse_subgraph = corpus.join("parse_subgraph");
+    fs::create_dir_all(&harmonize)?;
+    fs::create_dir_all(&parse_subgraph)?;
+
+    // Every directory within `seeds` is a service list, of which every
+    // `.graphql` file is a subgraph named after the file.
+    for seed in sorted_entries(&manifest_dir.join("seeds"))? {
+        let subgraphs = sorted_entries(&seed)?
+            .into_iter()
+            .filter(|path| path.extension() == Some(OsStr::new("graphql")))
+            .map(|path| {
+                let name = file_stem(&path);
+                fs::read_to_string(&path).map(|type_defs| (name, type_defs))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        fs::write(harmonize.join(file_stem(&seed)), service_list(&subgraphs))?;
+        for subgraph in &subgraphs {
+            let (name, type_defs) = subgraph;
+            fs::write(
+                harmonize.join(name),
+                service_list(std::slice::from_ref(subgraph)),
+            )?;
+            fs::write(parse_subgraph.join(name), type_defs)?;
+        }
+    }
+
+    println!("Seeded the corpora within {}", corpus.display());
+    Ok(())
+}
+
+/// The paths within a directory, in order, so that seeding is reproducible.
+fn sorted_entries(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.sort();
+    Ok(paths)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .expect("seeds are named")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The input of `harmonize` which it splits into the given subgraphs.
+fn service_list(subgraphs: &[(String, String)]) -> String {
+    subgraphs
+        .iter()
+        .map(|(name, type_defs)| format!("{} {}\n{}\n", SUBGRAPH_MARKER, name, type_defs))
+        .collect()
+}
@@ -0,0 +1,537 @@
+//! What the fuzz targets of the harmonizer share: turning their input into
+//! service lists, either by splitting text into subgraphs or by printing the
+//! SDL of an [`ArbitraryServiceList`], and checking that composing those never
+//! fails for any reason other than the subgraphs themselves.
+//!
+//! Panics (including those of the checks) and hangs are what libFuzzer
+//! reports as crashes, so the checks turn every other failure into a panic.
+
+use arbitrary::Arbitrary;
+use harmonizer::{ComposeOptions, Harmonizer, HarmonizerError, ServiceDefinition, ServiceList};
+use std::cell::RefCell;
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// The prefix of the lines which start the SDL of each subgraph within the
+/// text that [`service_list`] splits, e.g., `# subgraph: users`.  Being a
+/// comment, it leaves the SDL of the seed corpus intact.
+pub const SUBGRAPH_MARKER: &str = "# subgraph:";
+
+/// How long composition within V8 may run before it is considered to hang.
+/// Native composition can't be interrupted, so hangs of it are left for
+/// libFuzzer's own `-timeout` to catch.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+thread_local! {
+    /// Starting a runtime for every input would leave little time for
+    /// anything else, so every input is composed by the same one.
+    static HARMONIZER: RefCell<Harmonizer> = RefCell::new(
+        Harmonizer::with_options(ComposeOptions {
+            timeout: Some(TIMEOUT),
+            max_heap_size: None,
+        })
+        .expect("a composition runtime can be created"),
+    );
+}
+
+/// Split text into the subgraphs of a service list, at every line which
+/// starts with [`SUBGRAPH_MARKER`].  Text before the first of those lines is a
+/// subgraph of its own, unless it is blank and there are others.
+pub fn service_list(data: &[u8]) -> ServiceList {
+    let text = String::from_utf8_lossy(data);
+    let mut subgraphs = vec![(String::new(), String::new())];
+    for line in text.split_inclusive('\n') {
+        match line.strip_prefix(SUBGRAPH_MARKER) {
+            Some(name) => subgraphs.push((name.trim().to_string(), String::new())),
+            None => subgraphs.last_mut().unwrap().1.push_str(line),
+        }
+    }
+    if subgraphs.len() > 1 && subgraphs[0].1.trim().is_empty() {
+        subgraphs.remove(0);
+    }
+
+    subgraphs
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, type_defs))| {
+            // A service without a name is a bug of the caller, which the
+            // harmonizer rightly reports as one.
+            let name = if name.is_empty() {
+                format!("subgraph{}", index)
+            } else {
+                name
+            };
+            ServiceDefinition::new(name, "undefined", type_defs)
+        })
+        .collect()
+}
+
+/// Compose a service list both natively and within V8, panicking if either
+/// fails other than by reporting composition errors.
+pub fn check_composition(service_list: ServiceList) {
+    check(
+        "native composition",
+        harmonizer::native::harmonize(service_list.clone()),
+    );
+    check(
+        "composition within V8",
+        HARMONIZER.with(|harmonizer| harmonizer.borrow_mut().compose(service_list)),
+    );
+}
+
+/// Parse and validate a single subgraph within Rust, panicking if that fails
+/// other than by reporting what is wrong with the subgraph.
+pub fn check_subgraph(service: ServiceDefinition) {
+    check(
+        "validating a subgraph",
+        harmonizer::native::validate_subgraph(service),
+    );
+}
+
+fn check<T>(what: &str, result: Result<T, HarmonizerError>) {
+    match result {
+        Err(HarmonizerError::Timeout(timeout)) => {
+            panic!("{} hung for longer than {:?}", what, timeout)
+        }
+        Err(err) if err.is_internal() => panic!("{} failed: {}", what, err),
+        _ => {}
+    }
+}
+
+/// The names of every type, field, argument, enum value and directive of an
+/// [`ArbitraryServiceList`].  Drawing them all from so few makes subgraphs
+/// define, extend and refer to one another's types as often as not, and
+/// includes the names which federation treats specially.
+const NAMES: &[&str] = &[
+    "Query",
+    "Mutation",
+    "Subscription",
+    "User",
+    "Product",
+    "Review",
+    "Node",
+    "Color",
+    "id",
+    "upc",
+    "name",
+    "author",
+    "reviews",
+    "RED",
+    "String",
+    "Int",
+    "ID",
+    "Boolean",
+    "_Any",
+    "_Entity",
+    "_service",
+    "_entities",
+    "__typename",
+    "key",
+];
+
+/// The names of the subgraphs of an [`ArbitraryServiceList`], which may well
+/// have more than one subgraph of the same name.
+const SUBGRAPH_NAMES: &[&str] = &["accounts", "products", "reviews", "inventory"];
+
+/// A service list whose subgraphs are syntax trees of SDL, which libFuzzer
+/// mutates far more meaningfully than it would mutate the SDL itself.
+#[derive(Arbitrary, Debug)]
+pub struct ArbitraryServiceList(Vec<Subgraph>);
+
+impl ArbitraryServiceList {
+    /// The service list with the printed SDL of every subgraph.
+    pub fn service_list(&self) -> ServiceList {
+        self.0
+            .iter()
+            .map(|subgraph| {
+                ServiceDefinition::new(
+                    SUBGRAPH_NAMES[subgraph.name as usize % SUBGRAPH_NAMES.len()],
+                    "undefined",
+                    subgraph.to_string(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct Subgraph {
+    name: u8,
+    definitions: Vec<Definition>,
+}
+
+impl Display for Subgraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for definition in &self.definitions {
+            writeln!(f, "{}", definition)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct Name(u8);
+
+impl Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(NAMES[self.0 as usize % NAMES.len()])
+    }
+}
+
+/// Writes `items` separated by `separator`.
+fn join<T: Display>(f: &mut fmt::Formatter<'_>, items: &[T], separator: &str) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.write_str(separator)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+#[derive(Arbitrary, Debug)]
+enum Definition {
+    Schema {
+        query: Option<Name>,
+        mutation: Option<Name>,
+    },
+    Object(ObjectType),
+    Interface {
+        extend: bool,
+        name: Name,
+        directives: Vec<TypeDirective>,
+        fields: Vec<FieldDefinition>,
+    },
+    Union {
+        extend: bool,
+        name: Name,
+        members: Vec<Name>,
+    },
+    Enum {
+        extend: bool,
+        name: Name,
+        values: Vec<Name>,
+    },
+    Scalar(Name),
+    InputObject {
+        extend: bool,
+        name: Name,
+        fields: Vec<InputValue>,
+    },
+    Directive {
+        name: Name,
+        arguments: Vec<InputValue>,
+        repeatable: bool,
+        locations: Vec<DirectiveLocation>,
+    },
+}
+
+impl Display for Definition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let extend = |f: &mut fmt::Formatter<'_>, extend: bool| {
+            if extend {
+                f.write_str("extend ")
+            } else {
+                Ok(())
+            }
+        };
+        match self {
+            Definition::Schema { query, mutation } => {
+                f.write_str("schema {")?;
+                if let Some(query) = query {
+                    write!(f, " query: {}", query)?;
+                }
+                if let Some(mutation) = mutation {
+                    write!(f, " mutation: {}", mutation)?;
+                }
+                f.write_str(" }")
+            }
+            Definition::Object(object) => write!(f, "{}", object),
+            Definition::Interface {
+                extend: extends,
+                name,
+                directives,
+                fields,
+            } => {
+                extend(f, *extends)?;
+                write!(f, "interface {}", name)?;
+                for directive in directives {
+                    write!(f, " {}", directive)?;
+                }
+                write_fields(f, fields)
+            }
+            Definition::Union {
+                extend: extends,
+                name,
+                members,
+            } => {
+                extend(f, *extends)?;
+                write!(f, "union {} = ", name)?;
+                join(f, members, " | ")
+            }
+            Definition::Enum {
+                extend: extends,
+                name,
+                values,
+            } => {
+                extend(f, *extends)?;
+                write!(f, "enum {} {{ ", name)?;
+                join(f, values, " ")?;
+                f.write_str(" }")
+            }
+            Definition::Scalar(name) => write!(f, "scalar {}", name),
+            Definition::InputObject {
+                extend: extends,
+                name,
+                fields,
+            } => {
+                extend(f, *extends)?;
+                write!(f, "input {} {{ ", name)?;
+                join(f, fields, " ")?;
+                f.write_str(" }")
+            }
+            Definition::Directive {
+                name,
+                arguments,
+                repeatable,
+                locations,
+            } => {
+                write!(f, "directive @{}", name)?;
+                if !arguments.is_empty() {
+                    f.write_str("(")?;
+                    join(f, arguments, ", ")?;
+                    f.write_str(")")?;
+                }
+                if *repeatable {
+                    f.write_str(" repeatable")?;
+                }
+                f.write_str(" on ")?;
+                join(f, locations, " | ")
+            }
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct ObjectType {
+    extend: bool,
+    name: Name,
+    implements: Vec<Name>,
+    directives: Vec<TypeDirective>,
+    fields: Vec<FieldDefinition>,
+}
+
+impl Display for ObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.extend {
+            f.write_str("extend ")?;
+        }
+        write!(f, "type {}", self.name)?;
+        if !self.implements.is_empty() {
+            f.write_str(" implements ")?;
+            join(f, &self.implements, " & ")?;
+        }
+        for directive in &self.directives {
+            write!(f, " {}", directive)?;
+        }
+        write_fields(f, &self.fields)
+    }
+}
+
+fn write_fields(f: &mut fmt::Formatter<'_>, fields: &[FieldDefinition]) -> fmt::Result {
+    if fields.is_empty() {
+        return Ok(());
+    }
+    f.write_str(" {\n")?;
+    for field in fields {
+        writeln!(f, "  {}", field)?;
+    }
+    f.write_str("}")
+}
+
+#[derive(Arbitrary, Debug)]
+enum TypeDirective {
+    Key(FieldSet),
+    Extends,
+    Custom(Name),
+}
+
+impl Display for TypeDirective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeDirective::Key(field_set) => write!(f, "@key(fields: \"{}\")", field_set),
+            TypeDirective::Extends => f.write_str("@extends"),
+            TypeDirective::Custom(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct FieldDefinition {
+    name: Name,
+    arguments: Vec<InputValue>,
+    field_type: Type,
+    directives: Vec<FieldDirective>,
+}
+
+impl Display for FieldDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.arguments.is_empty() {
+            f.write_str("(")?;
+            join(f, &self.arguments, ", ")?;
+            f.write_str(")")?;
+        }
+        write!(f, ": {}", self.field_type)?;
+        for directive in &self.directives {
+            write!(f, " {}", directive)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum FieldDirective {
+    External,
+    Requires(FieldSet),
+    Provides(FieldSet),
+    Deprecated(Option<Name>),
+    Custom(Name),
+}
+
+impl Display for FieldDirective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldDirective::External => f.write_str("@external"),
+            FieldDirective::Requires(field_set) => {
+                write!(f, "@requires(fields: \"{}\")", field_set)
+            }
+            FieldDirective::Provides(field_set) => {
+                write!(f, "@provides(fields: \"{}\")", field_set)
+            }
+            FieldDirective::Deprecated(Some(reason)) => {
+                write!(f, "@deprecated(reason: \"{}\")", reason)
+            }
+            FieldDirective::Deprecated(None) => f.write_str("@deprecated"),
+            FieldDirective::Custom(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+/// The field set of a `@key`, `@requires` or `@provides`.
+#[derive(Arbitrary, Debug)]
+struct FieldSet(Vec<Selection>);
+
+impl Display for FieldSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        join(f, &self.0, " ")
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum Selection {
+    Field(Name, FieldSet),
+    InlineFragment(Name, FieldSet),
+}
+
+impl Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selection::Field(name, selections) if selections.0.is_empty() => {
+                write!(f, "{}", name)
+            }
+            Selection::Field(name, selections) => write!(f, "{} {{ {} }}", name, selections),
+            Selection::InlineFragment(type_condition, selections) => {
+                write!(f, "... on {} {{ {} }}", type_condition, selections)
+            }
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct InputValue {
+    name: Name,
+    value_type: Type,
+    default_value: Option<Value>,
+}
+
+impl Display for InputValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.value_type)?;
+        if let Some(default_value) = &self.default_value {
+            write!(f, " = {}", default_value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum Type {
+    Named(Name),
+    List(Box<Type>),
+    NonNull(Box<Type>),
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Named(name) => write!(f, "{}", name),
+            Type::List(ty) => write!(f, "[{}]", ty),
+            // A type can only be non-null once.
+            Type::NonNull(ty) if matches!(**ty, Type::NonNull(_)) => write!(f, "{}", ty),
+            Type::NonNull(ty) => write!(f, "{}!", ty),
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum Value {
+    Int(i32),
+    String(Name),
+    Boolean(bool),
+    Null,
+    Enum(Name),
+    List(Vec<Value>),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "\"{}\"", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+            Value::Null => f.write_str("null"),
+            Value::Enum(value) => write!(f, "{}", value),
+            Value::List(values) => {
+                f.write_str("[")?;
+                join(f, values, ", ")?;
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum DirectiveLocation {
+    Query,
+    Mutation,
+    Field,
+    FragmentSpread,
+    InlineFragment,
+    FieldDefinition,
+    Object,
+}
+
+impl Display for DirectiveLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DirectiveLocation::Query => "QUERY",
+            DirectiveLocation::Mutation => "MUTATION",
+            DirectiveLocation::Field => "FIELD",
+            DirectiveLocation::FragmentSpread => "FRAGMENT_SPREAD",
+            DirectiveLocation::InlineFragment => "INLINE_FRAGMENT",
+            DirectiveLocation::FieldDefinition => "FIELD_DEFINITION",
+            DirectiveLocation::Object => "OBJECT",
+        })
+    }
+}
@@ -0,0 +1,535 @@
+// This is evaluated once, after the composition bundle, whenever a runtime is
+// created (or, with the `snapshot` feature, at build time).  It defines the
+// functions shared by the scripts which the runtime evaluates for each call,
+// like `do_compose.js` and `do_plan.js`.
+
+/** @typedef {{typeDefs: string, name: string, url?: string;}} ServiceDefinition */
+
+/**
+ * This `composition` is defined as a global by the runtime we define in Rust.
+ * We declare this as a `var` here only to allow the TSDoc type annotation to be
+ * applied to it. Running `var` multiple times has no effect.
+ * @type {{
+ *   composeAndValidate: import('../../federation-js').composeAndValidate,
+ *   composeServices: import('../../federation-js').composeServices,
+ *   normalizeTypeDefs: import('../../federation-js').normalizeTypeDefs,
+ *   validateServicesBeforeNormalization: import('../../federation-js/src/composition/validate').validateServicesBeforeNormalization,
+ *   validateServicesBeforeComposition: import('../../federation-js/src/composition/validate').validateServicesBeforeComposition,
+ *   validateComposedSchema: import('../../federation-js/src/composition/validate').validateComposedSchema,
+ *   QueryPlanner: typeof import('../../query-planner-js').QueryPlanner,
+ *   buildComposedSchema: import('../../query-planner-js').buildComposedSchema,
+ *   buildOperationContext: import('../../query-planner-js').buildOperationContext,
+ *   printSupergraphSdl: import('../../federation-js/src/service/printSupergraphSdl').printSupergraphSdl,
+ *   parseGraphqlDocument: import('graphql').parse,
+ *   printGraphqlDocument: import('graphql').print,
+ *   visitGraphqlDocument: import('graphql').visit,
+ *   isAbstractType: import('graphql').isAbstractType,
+ *   validateGraphqlDocument: import('graphql').validate,
+ *   GraphQLError: typeof import('graphql').GraphQLError,
+ * }} */
+var composition;
+
+/**
+ * Every parsed document keeps a reference to its `Source` in the `loc` of each
+ * of its nodes, which lets us work out which service an error points into.
+ * This is reset by every call to `parseServiceList`.
+ * @type {Map<unknown, string>}
+ */
+var serviceNamesBySource = new Map();
+
+/**
+ * Parse the `typeDefs` of each of the services of a service list, which
+ * {@link checkServiceList} has found to be well-formed.
+ *
+ * The runtime parses every service within Rust before handing the service
+ * list to us, but leaves the services which use syntax it doesn't support for
+ * us to parse.  If any of them can't be parsed after all, this throws an error
+ * carrying the syntax errors of all of them (see {@link serializeErrors}).
+ *
+ * @param {ServiceDefinition[]} serviceList
+ */
+function parseServiceList(serviceList) {
+  serviceNamesBySource = new Map();
+
+  const syntaxErrors = [];
+  const parsedServiceList = serviceList.map(({ typeDefs, ...rest }) => ({
+    typeDefs: parseTypedefs(typeDefs, rest.name, syntaxErrors),
+    ...rest,
+  }));
+  throwSyntaxErrors(syntaxErrors);
+  return parsedServiceList;
+}
+
+/**
+ * Check that the service list handed to us by the runtime is well-formed,
+ * which is a bug within the harmonizer (rather than a problem with the
+ * services) if it isn't.
+ *
+ * @param {ServiceDefinition[]} serviceList
+ */
+function checkServiceList(serviceList) {
+  if (!serviceList || !Array.isArray(serviceList)) {
+    throw new Error("Error in JS-Rust-land: serviceList missing or incorrect.");
+  }
+
+  serviceList.some((service) => {
+    if (
+      typeof service.name !== "string" || !service.name ||
+      typeof service.url !== "string" && service.url ||
+      typeof service.typeDefs !== "string" && service.typeDefs
+    ) {
+      throw new Error("Missing required data structure on service.");
+    }
+  });
+}
+
+/**
+ * What incremental composition knows about each service of the service list
+ * it last composed, in the same order: the service as it was given to us,
+ * along with its parsed `typeDefs` and the results of the validation and
+ * normalization which composition applies to each service on its own.  All
+ * but the parsed `typeDefs` are filled in as they are first needed.
+ *
+ * This outlives the call which composed the service list, so that the next
+ * incremental composition only has to repeat the work for services which
+ * have changed since.
+ *
+ * @type {{
+ *   name: string,
+ *   url?: string,
+ *   source: string,
+ *   typeDefs?: import('graphql').DocumentNode,
+ *   errorsBeforeNormalization?: import('graphql').GraphQLError[],
+ *   normalizedTypeDefs?: import('graphql').DocumentNode,
+ *   errorsBeforeComposition?: import('graphql').GraphQLError[],
+ * }[]}
+ */
+var incrementalServices = [];
+
+/**
+ * Like {@link parseServiceList}, but reuses the parsed `typeDefs` of every
+ * service which is the same (by name, url and SDL) as one in the service list
+ * that was last composed incrementally.
+ *
+ * @param {ServiceDefinition[]} serviceList
+ */
+function parseServiceListIncrementally(serviceList) {
+  serviceNamesBySource = new Map();
+
+  const previousServices = new Map(
+    incrementalServices.map((service) => [service.name, service]),
+  );
+  const syntaxErrors = [];
+  incrementalServices = serviceList.map(({ typeDefs, name, url }) => {
+    const previous = previousServices.get(name);
+    if (
+      previous &&
+      previous.typeDefs &&
+      previous.source === typeDefs &&
+      previous.url === url
+    ) {
+      if (previous.typeDefs.loc) {
+        serviceNamesBySource.set(previous.typeDefs.loc.source, name);
+      }
+      return previous;
+    }
+    return {
+      name,
+      url,
+      source: typeDefs,
+      typeDefs: parseTypedefs(typeDefs, name, syntaxErrors),
+    };
+  });
+  throwSyntaxErrors(syntaxErrors);
+
+  return serviceList.map(({ typeDefs, ...rest }, index) => ({
+    typeDefs: incrementalServices[index].typeDefs,
+    ...rest,
+  }));
+}
+
+/**
+ * The same as `composeAndValidate`, for a service list which was parsed by
+ * {@link parseServiceListIncrementally}, except that the validation and
+ * normalization of each service on its own is only done for services which
+ * have changed since the previous incremental composition.  The errors are
+ * reported in the same order, too.
+ *
+ * @param {{ name: string, url?: string, typeDefs: import('graphql').DocumentNode }[]} serviceList
+ */
+function composeIncrementally(serviceList) {
+  const errors = [];
+
+  serviceList.forEach((service, index) => {
+    const known = incrementalServices[index];
+    if (!known.errorsBeforeNormalization) {
+      known.errorsBeforeNormalization =
+        composition.validateServicesBeforeNormalization([service]);
+    }
+    errors.push(...known.errorsBeforeNormalization);
+  });
+
+  const normalizedServiceList = serviceList.map(({ typeDefs, ...rest }, index) => {
+    const known = incrementalServices[index];
+    if (!known.normalizedTypeDefs) {
+      known.normalizedTypeDefs = composition.normalizeTypeDefs(typeDefs);
+    }
+    return { typeDefs: known.normalizedTypeDefs, ...rest };
+  });
+
+  normalizedServiceList.forEach((service, index) => {
+    const known = incrementalServices[index];
+    if (!known.errorsBeforeComposition) {
+      known.errorsBeforeComposition =
+        composition.validateServicesBeforeComposition([service]);
+    }
+    errors.push(...known.errorsBeforeComposition);
+  });
+
+  const compositionResult = composition.composeServices(normalizedServiceList);
+  if (compositionResult.errors) {
+    errors.push(...compositionResult.errors);
+  }
+
+  errors.push(
+    ...composition.validateComposedSchema({
+      schema: compositionResult.schema,
+      serviceList,
+    }),
+  );
+
+  return errors.length > 0
+    ? { schema: compositionResult.schema, errors }
+    : compositionResult;
+}
+
+/**
+ * Parse the `typeDefs` of a service, or collect its syntax error into
+ * `syntaxErrors` (serialized, since it belongs to the service rather than to
+ * any node) and return `undefined`.
+ *
+ * @param {string} source
+ * @param {string} serviceName
+ * @param {object[]} syntaxErrors
+ */
+function parseTypedefs(source, serviceName, syntaxErrors) {
+  try {
+    const document = composition.parseGraphqlDocument(source);
+    if (document.loc) {
+      serviceNamesBySource.set(document.loc.source, serviceName);
+    }
+    return document;
+  } catch (err) {
+    syntaxErrors.push(serializeError(err, serviceName));
+    return undefined;
+  }
+}
+
+/**
+ * Stop the script which is parsing a service list once any of its services
+ * can't be parsed, since there is nothing left to do with it.
+ *
+ * @param {object[]} syntaxErrors
+ */
+function throwSyntaxErrors(syntaxErrors) {
+  if (syntaxErrors.length) {
+    const err = new Error("The service list contains syntax errors.");
+    err.serializedErrors = syntaxErrors;
+    throw err;
+  }
+}
+
+/**
+ * Serialize an error which stopped one of the `do_*.js` scripts, which is
+ * either a single error or the syntax errors thrown by
+ * {@link parseServiceList}.
+ *
+ * @param {any} err
+ */
+function serializeErrors(err) {
+  return err && err.serializedErrors
+    ? err.serializedErrors
+    : [serializeError(err)];
+}
+
+/**
+ * The properties of a `GraphQLError` which point at the offending parts of a
+ * schema (e.g., its AST `nodes`) aren't enumerable, so they wouldn't survive
+ * the trip to Rust.  This flattens everything we're interested in into a
+ * plain object matching the Rust `CompositionError`.
+ *
+ * @param {any} err
+ * @param {string} [defaultServiceName] The service an error without any nodes
+ *   of its own (e.g., a syntax error) belongs to.
+ */
+function serializeError(err, defaultServiceName) {
+  const subgraphs = new Set();
+  const locations = [];
+
+  // Composition prefixes most of its messages with the service and the type
+  // or field they are about, e.g., `[movies] User.favorites -> ...`.
+  const prefix =
+    typeof err.message === "string" &&
+    err.message.match(/^\[([^\]@][^\]]*)\] (@?[_A-Za-z][_0-9A-Za-z]*(?:\.[_A-Za-z][_0-9A-Za-z]*)?) ->/);
+  if (prefix) {
+    subgraphs.add(prefix[1]);
+  }
+
+  const nodes = Array.isArray(err.nodes) ? err.nodes : [];
+  for (const node of nodes) {
+    if (node && typeof node.serviceName === "string") {
+      subgraphs.add(node.serviceName);
+    }
+  }
+
+  // `locations` are derived from the nodes that have a `loc`, in order, or
+  // from the `source` of errors which don't have nodes (like syntax errors).
+  const sources = nodes.length
+    ? nodes.filter((node) => node && node.loc).map((node) => node.loc.source)
+    : (err.locations || []).map(() => err.source);
+  (err.locations || []).forEach(({ line, column }, index) => {
+    const subgraph =
+      serviceNamesBySource.get(sources[index]) || defaultServiceName || null;
+    if (subgraph) {
+      subgraphs.add(subgraph);
+    }
+    locations.push({ subgraph, line, column });
+  });
+
+  if (!subgraphs.size && defaultServiceName) {
+    subgraphs.add(defaultServiceName);
+  }
+
+  return {
+    message: err.message,
+    extensions: err.extensions,
+    locations,
+    subgraphs: Array.from(subgraphs),
+    coordinate: prefix ? prefix[2] : undefined,
+  };
+}
+
+/**
+ * Directives which are understood by composition itself, or which survive it,
+ * and therefore are never dropped from the supergraph.
+ */
+var retainedDirectiveNames = [
+  "key",
+  "extends",
+  "external",
+  "requires",
+  "provides",
+  "deprecated",
+  "specifiedBy",
+];
+
+/**
+ * Composition only reports the problems which prevent a supergraph from being
+ * produced.  These are the things which don't, but which the owners of the
+ * subgraphs would most likely still want to know about.
+ *
+ * @param {{ name: string, typeDefs: import('graphql').DocumentNode }[]} serviceList
+ * @param {import('graphql').GraphQLSchema} schema
+ */
+function collectHints(serviceList, schema) {
+  return [
+    ...inconsistentDescriptionHints(serviceList),
+    ...droppedDirectiveHints(serviceList),
+    ...unusedTypeHints(schema),
+  ].map((hint) => {
+    const { extensions, ...serialized } = serializeError(hint);
+    return {
+      code: extensions.code,
+      ...serialized,
+      coordinate: serialized.coordinate || hint.coordinate,
+    };
+  });
+}
+
+/**
+ * Hints are `GraphQLError`s, much like the errors created by composition, so
+ * that they get the same `locations` and attribution to subgraphs.
+ *
+ * @param {string} code
+ * @param {string} message
+ * @param {readonly import('graphql').ASTNode[]} nodes
+ * @param {string} [coordinate]
+ */
+function hintWithCode(code, message, nodes, coordinate) {
+  const hint = new composition.GraphQLError(
+    message,
+    nodes,
+    undefined,
+    undefined,
+    undefined,
+    undefined,
+    { code },
+  );
+  hint.coordinate = coordinate;
+  return hint;
+}
+
+/** @param {any} node */
+function descriptionOf(node) {
+  return node.description ? node.description.value : undefined;
+}
+
+/**
+ * Value types may be defined by more than one service, but only one of their
+ * descriptions makes it into the supergraph.
+ *
+ * @param {{ name: string, typeDefs: import('graphql').DocumentNode }[]} serviceList
+ */
+function inconsistentDescriptionHints(serviceList) {
+  /** @type {Map<string, { serviceName: string, node: any }[]>} */
+  const definitionsByName = new Map();
+  for (const { name: serviceName, typeDefs } of serviceList) {
+    for (const node of typeDefs.definitions) {
+      if (
+        !node.kind.endsWith("TypeDefinition") ||
+        ["Query", "Mutation", "Subscription"].includes(node.name.value) ||
+        (node.directives || []).some((d) => d.name.value === "extends")
+      ) {
+        continue;
+      }
+      const definitions = definitionsByName.get(node.name.value) || [];
+      definitions.push({ serviceName, node });
+      definitionsByName.set(node.name.value, definitions);
+    }
+  }
+
+  const hints = [];
+  for (const [typeName, [first, ...others]] of definitionsByName) {
+    for (const other of others) {
+      if (descriptionOf(first.node) !== descriptionOf(other.node)) {
+        hints.push(
+          hintWithCode(
+            "INCONSISTENT_DESCRIPTION",
+            `[${other.serviceName}] ${typeName} -> The description of \`${typeName}\` differs from its description in \`${first.serviceName}\`. Only one of them will be included in the supergraph.`,
+            [first.node, other.node],
+          ),
+        );
+      }
+
+      const firstMembers = first.node.fields || first.node.values || [];
+      for (const member of other.node.fields || other.node.values || []) {
+        const original = firstMembers.find(
+          (candidate) => candidate.name.value === member.name.value,
+        );
+        if (original && descriptionOf(original) !== descriptionOf(member)) {
+          const coordinate = `${typeName}.${member.name.value}`;
+          hints.push(
+            hintWithCode(
+              "INCONSISTENT_DESCRIPTION",
+              `[${other.serviceName}] ${coordinate} -> The description of \`${coordinate}\` differs from its description in \`${first.serviceName}\`. Only one of them will be included in the supergraph.`,
+              [original, member],
+            ),
+          );
+        }
+      }
+    }
+  }
+  return hints;
+}
+
+/**
+ * Only executable directives are carried over into the supergraph, so every
+ * usage of any other custom directive within a subgraph is dropped.
+ *
+ * @param {{ name: string, typeDefs: import('graphql').DocumentNode }[]} serviceList
+ */
+function droppedDirectiveHints(serviceList) {
+  const hints = [];
+  for (const { name: serviceName, typeDefs } of serviceList) {
+    /** @type {Map<string, import('graphql').DirectiveNode[]>} */
+    const usagesByName = new Map();
+    composition.visitGraphqlDocument(typeDefs, {
+      Directive(node) {
+        if (retainedDirectiveNames.includes(node.name.value)) {
+          return;
+        }
+        const usages = usagesByName.get(node.name.value) || [];
+        usages.push(node);
+        usagesByName.set(node.name.value, usages);
+      },
+    });
+
+    for (const [directiveName, usages] of usagesByName) {
+      hints.push(
+        hintWithCode(
+          "DIRECTIVE_DROPPED",
+          `[${serviceName}] @${directiveName} -> The supergraph only includes executable directives, so ${usages.length === 1 ? "the usage" : `all ${usages.length} usages`} of \`@${directiveName}\` in this service will be dropped.`,
+          usages,
+        ),
+      );
+    }
+  }
+  return hints;
+}
+
+/**
+ * Types which can't be reached from any of the root operation types can never
+ * be queried through the supergraph.
+ *
+ * @param {import('graphql').GraphQLSchema} schema
+ */
+function unusedTypeHints(schema) {
+  /** @type {Set<string>} */
+  const reachable = new Set();
+  /** @type {any[]} */
+  const queue = [
+    schema.getQueryType(),
+    schema.getMutationType(),
+    schema.getSubscriptionType(),
+  ].filter(Boolean);
+  for (const directive of schema.getDirectives()) {
+    queue.push(...directive.args.map((arg) => arg.type));
+  }
+
+  while (queue.length) {
+    let type = queue.pop();
+    while (type.ofType) {
+      type = type.ofType;
+    }
+    if (reachable.has(type.name)) {
+      continue;
+    }
+    reachable.add(type.name);
+
+    if (type.getFields) {
+      for (const field of Object.values(type.getFields())) {
+        queue.push(field.type, ...(field.args || []).map((arg) => arg.type));
+      }
+    }
+    if (type.getInterfaces) {
+      queue.push(...type.getInterfaces());
+    }
+    if (composition.isAbstractType(type)) {
+      queue.push(...schema.getPossibleTypes(type));
+    }
+  }
+
+  const hints = [];
+  for (const type of Object.values(schema.getTypeMap())) {
+    if (
+      reachable.has(type.name) ||
+      type.name.startsWith("__") ||
+      ["String", "Int", "Float", "Boolean", "ID"].includes(type.name)
+    ) {
+      continue;
+    }
+    const owner =
+      type.extensions && type.extensions.federation
+        ? type.extensions.federation.serviceName
+        : undefined;
+    hints.push(
+      hintWithCode(
+        "UNUSED_TYPE",
+        `${owner ? `[${owner}] ` : ""}${type.name} -> \`${type.name}\` is not reachable from any root operation type, so it can't be queried through the supergraph.`,
+        type.astNode ? [type.astNode] : [],
+        type.name,
+      ),
+    );
+  }
+  return hints;
+}
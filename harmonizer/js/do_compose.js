@@ -1,56 +1,90 @@
-/** @typedef {{typeDefs: string, name: string, url?: string;}} ServiceDefinition */
+/**
+ * The service list to compose, which is set as a global by the runtime before
+ * this script is evaluated.
+ * @type {ServiceDefinition[]}
+ */
+var serviceList = serviceList;
 
 /**
- * This `composition` is defined as a global by the runtime we define in Rust.
- * We declare this as a `var` here only to allow the TSDoc type annotation to be
- * applied to it. Running `var` multiple times has no effect.
- * @type {{
- *   composeAndValidate: import('../../federation-js').composeAndValidate,
- *   parseGraphqlDocument: import('graphql').parse
- * }} */
-var composition;
+ * Whether the schema composed from a failing service list should be printed
+ * and reported alongside its errors.  Like the `serviceList`, this is set by
+ * the runtime before this script is evaluated.
+ * @type {boolean | undefined}
+ */
+var bestEffort = bestEffort;
 
 /**
- * @type {ServiceDefinition[]}
+ * Whether to reuse what is known about the services which haven't changed
+ * since the previous incremental composition.  This is set by the runtime,
+ * too.
+ * @type {boolean | undefined}
  */
-var serviceList = serviceList;
+var incremental = incremental;
 
-if (!serviceList || !Array.isArray(serviceList)) {
-  throw new Error("Error in JS-Rust-land: serviceList missing or incorrect.");
+/**
+ * Print whatever composition managed to put together from a failing service
+ * list.  The federation metadata which printing the supergraph relies upon is
+ * attached to the schema even when composition fails, but there's no telling
+ * how complete it is, so this gives up rather than fail the composition.
+ *
+ * @param {import('graphql').GraphQLSchema | undefined} schema
+ */
+function printBestEffortSupergraph(schema) {
+  if (!schema) {
+    return undefined;
+  }
+  try {
+    return composition.printSupergraphSdl(schema, serviceList);
+  } catch (err) {
+    return undefined;
+  }
 }
 
-serviceList.some((service) => {
-  if (
-    typeof service.name !== "string" || !service.name ||
-    typeof service.url !== "string" && service.url ||
-    typeof service.typeDefs !== "string" && service.typeDefs
-  ) {
-    throw new Error("Missing required data structure on service.");
+/**
+ * Collect the hints about a failing service list, for the same reasons (and
+ * in the same way) as {@link printBestEffortSupergraph}.
+ *
+ * @param {import('graphql').GraphQLSchema | undefined} schema
+ */
+function collectBestEffortHints(schema) {
+  if (!schema) {
+    return [];
   }
-});
-
-serviceList = serviceList.map(({ typeDefs, ...rest }) => ({
-  typeDefs: parseTypedefs(typeDefs),
-  ...rest,
-}));
-
-function parseTypedefs(source) {
   try {
-    return composition.parseGraphqlDocument(source)    
+    return collectHints(serviceList, schema);
   } catch (err) {
-    // Return the error in a way that we know how to handle it.
-    done({ Err: [err] });
+    return [];
   }
 }
 
+checkServiceList(serviceList);
+
 try {
+  serviceList = incremental
+    ? parseServiceListIncrementally(serviceList)
+    : parseServiceList(serviceList);
+
   /**
-   * @type {{ errors: Error[], supergraphSdl?: undefined } | { errors?: undefined, supergraphSdl: string; }}
+   * @type {{ errors: Error[], schema: import('graphql').GraphQLSchema, supergraphSdl?: undefined } | { errors?: undefined, schema: import('graphql').GraphQLSchema, supergraphSdl: string; }}
    */
-  const composed = composition.composeAndValidate(serviceList);
-  done(
-    composed.errors ? { Err: composed.errors } : { Ok: composed.supergraphSdl },
-  );
+  const composed = incremental
+    ? composeIncrementally(serviceList)
+    : composition.composeAndValidate(serviceList);
+  if (!composed.errors) {
+    done({
+      supergraphSdl: composed.supergraphSdl,
+      errors: [],
+      hints: collectHints(serviceList, composed.schema),
+    });
+  } else if (bestEffort) {
+    done({
+      supergraphSdl: printBestEffortSupergraph(composed.schema),
+      errors: composed.errors.map((err) => serializeError(err)),
+      hints: collectBestEffortHints(composed.schema),
+    });
+  } else {
+    done({ errors: composed.errors.map((err) => serializeError(err)) });
+  }
 } catch (err) {
-  done({ Err: [err] });
+  done({ errors: serializeErrors(err) });
 }
@@ -0,0 +1,22 @@
+/**
+ * The service list containing the single service to normalize, which is set
+ * as a global by the runtime before this script is evaluated.
+ * @type {ServiceDefinition[]}
+ */
+var serviceList = serviceList;
+
+checkServiceList(serviceList);
+
+try {
+  serviceList = parseServiceList(serviceList);
+
+  const [{ typeDefs }] = serviceList;
+  done({
+    typeDefs: composition.printGraphqlDocument(
+      composition.normalizeTypeDefs(typeDefs),
+    ),
+    errors: [],
+  });
+} catch (err) {
+  done({ errors: serializeErrors(err) });
+}
@@ -0,0 +1,78 @@
+/**
+ * The supergraph to plan against, the operation document to plan and, if that
+ * document contains more than one operation, the name of the one to plan.
+ * These are set as globals by the runtime before this script is evaluated.
+ * @type {string}
+ */
+var supergraphSdl = supergraphSdl;
+/** @type {string} */
+var operation = operation;
+/** @type {string | null} */
+var operationName = operationName;
+
+if (typeof supergraphSdl !== "string" || typeof operation !== "string") {
+  throw new Error("Error in JS-Rust-land: supergraphSdl or operation missing.");
+}
+
+/**
+ * Planning errors are about the operation (or the supergraph) rather than any
+ * subgraph, so all that's worth keeping of them is their message and where
+ * they point to.
+ *
+ * @param {any} err
+ */
+function serializePlanningError(err) {
+  return {
+    message: String(err && err.message ? err.message : err),
+    locations: (err && err.locations) || [],
+  };
+}
+
+/**
+ * Build the schema of the supergraph, or report why the supergraph SDL isn't
+ * a valid supergraph.  That is reported apart from the planning errors, since
+ * it's the supergraph rather than the operation which is at fault.
+ *
+ * @returns {import('graphql').GraphQLSchema | undefined}
+ */
+function buildSupergraphSchema() {
+  try {
+    return composition.buildComposedSchema(
+      composition.parseGraphqlDocument(supergraphSdl),
+    );
+  } catch (err) {
+    done({ supergraphErrors: [serializePlanningError(err)], errors: [] });
+    return undefined;
+  }
+}
+
+try {
+  const schema = buildSupergraphSchema();
+  if (schema) {
+    const document = composition.parseGraphqlDocument(operation);
+
+    // The query planner assumes that it's given a valid operation, which is
+    // something the gateway makes sure of before planning.
+    const validationErrors = composition.validateGraphqlDocument(
+      schema,
+      document,
+    );
+    if (validationErrors.length > 0) {
+      done({ errors: validationErrors.map(serializePlanningError) });
+    } else {
+      const operationContext = composition.buildOperationContext(
+        schema,
+        document,
+        operationName || undefined,
+      );
+      const queryPlan = new composition.QueryPlanner(schema).buildQueryPlan(
+        operationContext,
+        { autoFragmentization: false },
+      );
+      // Round-tripping through JSON drops the properties which are `undefined`.
+      done({ queryPlan: JSON.parse(JSON.stringify(queryPlan)), errors: [] });
+    }
+  }
+} catch (err) {
+  done({ errors: [serializePlanningError(err)] });
+}
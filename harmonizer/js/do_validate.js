@@ -0,0 +1,37 @@
+/**
+ * The service list containing the single service to validate, which is set as
+ * a global by the runtime before this script is evaluated.
+ * @type {ServiceDefinition[]}
+ */
+var serviceList = serviceList;
+
+checkServiceList(serviceList);
+
+try {
+  serviceList = parseServiceList(serviceList);
+
+  const errors = composition.validateServicesBeforeNormalization(serviceList);
+
+  const normalizedServiceList = serviceList.map(({ typeDefs, ...rest }) => ({
+    typeDefs: composition.normalizeTypeDefs(typeDefs),
+    ...rest,
+  }));
+
+  errors.push(
+    ...composition.validateServicesBeforeComposition(normalizedServiceList),
+  );
+
+  // Composing a single service is what runs the SDL validation rules against
+  // it.  Extending a type that is owned by another service is perfectly fine
+  // for a subgraph on its own, though, so we don't report that.
+  const composed = composition.composeServices(normalizedServiceList);
+  errors.push(
+    ...(composed.errors || []).filter(
+      (err) => !err.extensions || err.extensions.code !== "EXTENSION_WITH_NO_BASE",
+    ),
+  );
+
+  done({ errors: errors.map((err) => serializeError(err)) });
+} catch (err) {
+  done({ errors: serializeErrors(err) });
+}
@@ -1,6 +1,28 @@
 // This file itself gets its types automatically in VSCode.
 //
 // However, if you change the exports from this file, be sure to provide the
-// types in the do_compose.js which is alongside (and injected in the runtime!)
-export { composeAndValidate } from "@apollo/federation";
-export { parse as parseGraphqlDocument } from "graphql";
+// types in the diagnostics.js which is alongside (and injected in the runtime!)
+export {
+  composeAndValidate,
+  composeServices,
+  normalizeTypeDefs,
+} from "@apollo/federation";
+export {
+  validateServicesBeforeNormalization,
+  validateServicesBeforeComposition,
+  validateComposedSchema,
+} from "@apollo/federation/dist/composition/validate";
+export {
+  QueryPlanner,
+  buildComposedSchema,
+  buildOperationContext,
+} from "@apollo/query-planner";
+export { printSupergraphSdl } from "@apollo/federation/dist/service/printSupergraphSdl";
+export {
+  parse as parseGraphqlDocument,
+  print as printGraphqlDocument,
+  visit as visitGraphqlDocument,
+  isAbstractType,
+  validate as validateGraphqlDocument,
+  GraphQLError,
+} from "graphql";
@@ -0,0 +1,67 @@
+// This is evaluated once, before the composition bundle, whenever a runtime
+// is created from scratch.  When the `snapshot` feature is enabled, it is
+// instead evaluated at build time and captured in the V8 startup snapshot, so
+// nothing here may depend on state which only exists at runtime (e.g., the ops
+// which are registered from Rust).
+
+// We define a console whose methods pass what they're given, along with
+// their level, to our op_console op, which hands it to the console of the
+// Harmonizer.  Like Node's, it prints strings as they are and inspects
+// everything else (if only with JSON.stringify).
+function _formatConsoleArgument(value) {
+  if (typeof value === 'string') {
+    return value;
+  }
+  if (value instanceof Error) {
+    return value.stack || String(value);
+  }
+  try {
+    const json = JSON.stringify(value);
+    return json === undefined ? String(value) : json;
+  } catch (_) {
+    return String(value);
+  }
+}
+
+function _consoleMethod(level) {
+  return function (...args) {
+    Deno.core.opSync('op_console', [
+      level,
+      args.map(_formatConsoleArgument).join(' '),
+    ]);
+  };
+}
+
+console = {
+  trace: _consoleMethod('trace'),
+  debug: _consoleMethod('debug'),
+  log: _consoleMethod('log'),
+  info: _consoleMethod('info'),
+  warn: _consoleMethod('warn'),
+  error: _consoleMethod('error'),
+};
+
+// We define a print function, too, which is the same as console.log.
+function print(value) {
+  console.log(String(value));
+}
+
+function done(result) {
+  Deno.core.opSync('op_composition_result', result);
+}
+
+// We build some of the preliminary objects that our Rollup-built package is
+// expecting to be present in the environment.
+// node_fetch_1 is an unused external dependency we don't bundle.  See the
+// configuration in this package's 'rollup.config.js' for where this is marked
+// as an external dependency and thus not packaged into the bundle.
+node_fetch_1 = {};
+// 'process' is a Node.js ism.  We rely on process.env.NODE_ENV, in
+// particular, to determine whether or not we are running in a debug
+// mode.  For the purposes of harmonizer, we don't gain anything from
+// running in such a mode.
+process = { env: { "NODE_ENV": "production" }};
+// Some JS runtime implementation specific bits that we rely on that
+// need to be initialized as empty objects.
+global = {};
+exports = {};
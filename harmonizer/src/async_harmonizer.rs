@@ -0,0 +1,191 @@
+use crate::workers::Workers;
+use crate::{
+    ComposeOptions, CompositionError, CompositionOutput, Harmonizer, HarmonizerError,
+    PartialComposition, QueryPlan, ServiceDefinition, ServiceList,
+};
+use futures_channel::oneshot;
+
+/// An asynchronous front-end to composition, for use within async services.
+///
+/// A [`Harmonizer`] can't be moved between threads and blocks the thread it
+/// is called on, so an `AsyncHarmonizer` instead owns a number of them on
+/// dedicated threads of its own and hands each call to whichever one is free.
+/// Its methods only wait for the result, which makes them safe to call from
+/// any number of tasks concurrently, on any executor.
+///
+/// Calls are queued until a thread is free, so there are never more
+/// compositions running at once than there are threads.  A thread whose
+/// [`Harmonizer`] fails with an [internal] error replaces it before its next
+/// call.
+///
+/// This is only available with the `async` cargo feature.
+///
+/// ```no_run
+/// use harmonizer::{AsyncHarmonizer, ServiceDefinition};
+///
+/// # async fn compose() -> Result<(), harmonizer::HarmonizerError> {
+/// let harmonizer = AsyncHarmonizer::new(4)?;
+/// let output = harmonizer
+///     .compose(vec![ServiceDefinition::new(
+///         "users",
+///         "http://users",
+///         "type Query { me: String }",
+///     )])
+///     .await?;
+/// println!("{}", output.supergraph_sdl);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [internal]: HarmonizerError::is_internal
+pub struct AsyncHarmonizer {
+    workers: Workers,
+}
+
+impl AsyncHarmonizer {
+    /// Start an [`AsyncHarmonizer`] with the given number of threads (at least
+    /// one), returning once each of them has created its [`Harmonizer`].
+    pub fn new(threads: usize) -> Result<AsyncHarmonizer, HarmonizerError> {
+        AsyncHarmonizer::with_options(threads, ComposeOptions::default())
+    }
+
+    /// Start an [`AsyncHarmonizer`] like [`AsyncHarmonizer::new`] does, whose
+    /// calls are limited by the given [`ComposeOptions`].
+    pub fn with_options(
+        threads: usize,
+        options: ComposeOptions,
+    ) -> Result<AsyncHarmonizer, HarmonizerError> {
+        Ok(AsyncHarmonizer {
+            workers: Workers::start(threads, options)?,
+        })
+    }
+
+    /// See [`Harmonizer::compose`].
+    pub async fn compose(
+        &self,
+        service_list: ServiceList,
+    ) -> Result<CompositionOutput, HarmonizerError> {
+        self.call(move |harmonizer| harmonizer.compose(service_list))
+            .await
+    }
+
+    /// See [`Harmonizer::compose_best_effort`].
+    pub async fn compose_best_effort(
+        &self,
+        service_list: ServiceList,
+    ) -> Result<PartialComposition, HarmonizerError> {
+        self.call(move |harmonizer| harmonizer.compose_best_effort(service_list))
+            .await
+    }
+
+    /// See [`Harmonizer::validate_subgraph`].
+    pub async fn validate_subgraph(
+        &self,
+        service: ServiceDefinition,
+    ) -> Result<Vec<CompositionError>, HarmonizerError> {
+        self.call(move |harmonizer| harmonizer.validate_subgraph(service))
+            .await
+    }
+
+    /// See [`Harmonizer::normalize`].
+    pub async fn normalize(&self, service: ServiceDefinition) -> Result<String, HarmonizerError> {
+        self.call(move |harmonizer| harmonizer.normalize(service))
+            .await
+    }
+
+    /// See [`Harmonizer::plan`].
+    pub async fn plan(
+        &self,
+        supergraph_sdl: String,
+        operation: String,
+        operation_name: Option<String>,
+    ) -> Result<QueryPlan, HarmonizerError> {
+        self.call(move |harmonizer| {
+            harmonizer.plan(&supergraph_sdl, &operation, operation_name.as_deref())
+        })
+        .await
+    }
+
+    /// Run `call` with the [`Harmonizer`] of the next free thread and wait for
+    /// its result.
+    async fn call<T, F>(&self, call: F) -> Result<T, HarmonizerError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Harmonizer) -> Result<T, HarmonizerError> + Send + 'static,
+    {
+        let (result_tx, result_rx) = oneshot::channel();
+        let submitted = self.workers.submit(Box::new(move |harmonizer| {
+            let result = harmonizer.and_then(call);
+            let usable = !matches!(&result, Err(err) if err.is_internal());
+            // The caller may have stopped waiting, which is no reason for the
+            // worker to stop, too.
+            let _ = result_tx.send(result);
+            usable
+        }));
+        if submitted.is_err() {
+            return Err(HarmonizerError::WorkerStopped);
+        }
+
+        result_rx
+            .await
+            .map_err(|_| HarmonizerError::WorkerStopped)?
+    }
+}
+
+impl std::fmt::Debug for AsyncHarmonizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncHarmonizer").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AsyncHarmonizer, HarmonizerError, ServiceDefinition};
+    use futures::executor::block_on;
+    use futures::future::join_all;
+
+    #[test]
+    fn it_composes_concurrently() {
+        fn assert_send<T: Send>(_: &T) {}
+        fn assert_send_and_sync<T: Send + Sync>() {}
+        assert_send_and_sync::<AsyncHarmonizer>();
+
+        let harmonizer = AsyncHarmonizer::new(2).unwrap();
+        assert_send(&harmonizer.compose(Vec::new()));
+        let service_list = |name: &str| {
+            vec![ServiceDefinition::new(
+                name,
+                "undefined",
+                format!("type Query {{ {}: String }}", name),
+            )]
+        };
+
+        let results = block_on(join_all(vec![
+            harmonizer.compose(service_list("users")),
+            harmonizer.compose(service_list("movies")),
+            harmonizer.compose(vec![ServiceDefinition::new(
+                "broken",
+                "undefined",
+                "type {",
+            )]),
+            harmonizer.compose(service_list("books")),
+        ]));
+
+        assert!(results[0]
+            .as_ref()
+            .unwrap()
+            .supergraph_sdl
+            .contains("users: String"));
+        assert!(results[1]
+            .as_ref()
+            .unwrap()
+            .supergraph_sdl
+            .contains("movies: String"));
+        assert!(matches!(results[2], Err(HarmonizerError::Composition(_))));
+        assert!(results[3]
+            .as_ref()
+            .unwrap()
+            .supergraph_sdl
+            .contains("books: String"));
+    }
+}
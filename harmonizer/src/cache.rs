@@ -0,0 +1,491 @@
+#[cfg(feature = "js")]
+use crate::Harmonizer;
+use crate::{CompositionOutput, HarmonizerError, ServiceDefinition, ServiceList};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// The version of `@apollo/federation` within `dist/composition.js`, as
+/// determined by `build.rs`.
+#[cfg(feature = "js")]
+const FEDERATION_VERSION: &str = env!("HARMONIZER_FEDERATION_VERSION");
+
+/// What native composition is keyed by in place of the version of
+/// `@apollo/federation`, since the composer determines what composition
+/// returns just as much as the subgraphs do.
+#[cfg(feature = "native")]
+const NATIVE_COMPOSER: &str = "native";
+
+/// Bumped whenever the way keys are derived (or what is stored under them)
+/// changes, so that caches written by older versions of this crate are never
+/// read back.
+const KEY_FORMAT: &str = "harmonizer-composition-cache-v1";
+
+/// Identifies the result of composing a particular [`ServiceList`] with the
+/// bundled version of `@apollo/federation` (or natively, with the `native`
+/// cargo feature's `native::harmonize_cached`).
+///
+/// The key is a SHA-256 hash of the name, URL and SDL of every subgraph (in
+/// order), along with the version of this crate and the version of
+/// `@apollo/federation` or the fact that composition is native.
+/// It is stable across processes and platforms, which makes it suitable for
+/// caches which are persisted or shared.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// The key under which [`harmonize_cached`] caches the composition of
+    /// `service_list`.
+    #[cfg(feature = "js")]
+    pub fn for_service_list(service_list: &[ServiceDefinition]) -> CacheKey {
+        CacheKey::derive(FEDERATION_VERSION, service_list)
+    }
+
+    /// The key under which [`native::harmonize_cached`] caches the native
+    /// composition of `service_list`.
+    ///
+    /// [`native::harmonize_cached`]: crate::native::harmonize_cached
+    #[cfg(feature = "native")]
+    pub fn for_native_service_list(service_list: &[ServiceDefinition]) -> CacheKey {
+        CacheKey::derive(NATIVE_COMPOSER, service_list)
+    }
+
+    fn derive(composer: &str, service_list: &[ServiceDefinition]) -> CacheKey {
+        let mut hasher = Sha256::new();
+        let mut field = |value: &str| {
+            // Prefixing every field with its length keeps the fields from
+            // running into each other, so that different service lists never
+            // hash the same input.
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value.as_bytes());
+        };
+
+        field(KEY_FORMAT);
+        field(env!("CARGO_PKG_VERSION"));
+        field(composer);
+        for service in service_list {
+            field(&service.name);
+            field(&service.url);
+            field(&service.type_defs);
+        }
+
+        CacheKey(format!("{:x}", hasher.finalize()))
+    }
+
+    /// The key as a string of lowercase hexadecimal digits.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Somewhere to keep the results of composition, so that composing the same
+/// [`ServiceList`] again doesn't have to involve the JavaScript runtime at
+/// all.
+///
+/// Only successful compositions are cached.  A cache is not expected to be
+/// reliable, so failing to read or write an entry is not an error: it is
+/// simply a miss, or an entry which isn't kept.
+pub trait CompositionCache {
+    /// The composition cached under `key`, if there is one.
+    fn get(&self, key: &CacheKey) -> Option<CompositionOutput>;
+
+    /// Cache `output` under `key`, replacing whatever was cached there.
+    fn insert(&self, key: CacheKey, output: CompositionOutput);
+}
+
+/// A [`CompositionCache`] which keeps the most recently used compositions in
+/// memory, up to a fixed number of them.
+pub struct MemoryCache {
+    entries: Mutex<LruCache<CacheKey, CompositionOutput>>,
+}
+
+impl MemoryCache {
+    /// Create a cache which holds at most `capacity` compositions, evicting
+    /// the least recently used one to make room for another.
+    pub fn new(capacity: usize) -> MemoryCache {
+        MemoryCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// The number of compositions in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the cache holds no compositions at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CompositionCache for MemoryCache {
+    fn get(&self, key: &CacheKey) -> Option<CompositionOutput> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn insert(&self, key: CacheKey, output: CompositionOutput) {
+        self.entries.lock().unwrap().put(key, output);
+    }
+}
+
+impl fmt::Debug for MemoryCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries.lock().unwrap();
+        f.debug_struct("MemoryCache")
+            .field("len", &entries.len())
+            .field("capacity", &entries.cap())
+            .finish()
+    }
+}
+
+/// A [`CompositionCache`] which keeps every composition as a JSON file within
+/// a directory, named after its [`CacheKey`].
+///
+/// Entries are written to a temporary file first and then renamed into
+/// place, so any number of processes (and threads) can share the same
+/// directory.  Nothing
+/// is ever removed from it.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    directory: PathBuf,
+}
+
+impl DiskCache {
+    /// Create a cache within `directory`, which is created (along with its
+    /// parents) when the first composition is cached.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> DiskCache {
+        DiskCache {
+            directory: directory.into(),
+        }
+    }
+
+    /// The directory the cache is kept in.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn path(&self, key: &CacheKey) -> PathBuf {
+        self.directory.join(format!("{}.json", key))
+    }
+
+    fn write(&self, key: &CacheKey, output: &CompositionOutput) -> io::Result<()> {
+        // Every write within a process has a temporary file of its own, even
+        // when several threads write the same key at once.
+        static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+        fs::create_dir_all(&self.directory)?;
+        let temporary = self.directory.join(format!(
+            "{}.json.{}.{}.tmp",
+            key,
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written = serde_json::to_vec(output)
+            .map_err(io::Error::from)
+            .and_then(|json| fs::write(&temporary, json))
+            .and_then(|()| fs::rename(&temporary, self.path(key)));
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        written
+    }
+}
+
+impl CompositionCache for DiskCache {
+    fn get(&self, key: &CacheKey) -> Option<CompositionOutput> {
+        let path = self.path(key);
+        let json = match fs::read(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                log::warn!("could not read {}: {}", path.display(), err);
+                return None;
+            }
+        };
+        match serde_json::from_slice(&json) {
+            Ok(output) => Some(output),
+            Err(err) => {
+                log::warn!("ignoring malformed {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    fn insert(&self, key: CacheKey, output: CompositionOutput) {
+        if let Err(err) = self.write(&key, &output) {
+            log::warn!("could not write {}: {}", self.path(&key).display(), err);
+        }
+    }
+}
+
+#[cfg(feature = "js")]
+impl Harmonizer {
+    /// Like [`Harmonizer::compose`], but returns the composition from `cache`
+    /// if the same [`ServiceList`] has been composed before, and otherwise
+    /// adds it to `cache` once it has been composed.
+    ///
+    /// This is only available with the `cache` cargo feature.
+    pub fn compose_cached(
+        &mut self,
+        service_list: ServiceList,
+        cache: &dyn CompositionCache,
+    ) -> Result<CompositionOutput, HarmonizerError> {
+        let key = CacheKey::derive(FEDERATION_VERSION, &service_list);
+        cached(key, service_list, cache, |service_list| {
+            self.compose(service_list)
+        })
+    }
+}
+
+/// Like [`harmonize`], but returns the composition from `cache` if the same
+/// [`ServiceList`] has been composed before, without starting a JavaScript
+/// runtime at all.  Otherwise, the composition is added to `cache`.
+///
+/// This is only available with the `cache` cargo feature.
+///
+/// ```no_run
+/// use harmonizer::{harmonize_cached, DiskCache, ServiceDefinition};
+///
+/// let cache = DiskCache::new("target/compositions");
+/// let output = harmonize_cached(
+///     vec![ServiceDefinition::new(
+///         "users",
+///         "http://users",
+///         "type Query { me: String }",
+///     )],
+///     &cache,
+/// )?;
+/// println!("{}", output.supergraph_sdl);
+/// # Ok::<(), harmonizer::HarmonizerError>(())
+/// ```
+///
+/// [`harmonize`]: crate::harmonize
+#[cfg(feature = "js")]
+pub fn harmonize_cached(
+    service_list: ServiceList,
+    cache: &dyn CompositionCache,
+) -> Result<CompositionOutput, HarmonizerError> {
+    let key = CacheKey::for_service_list(&service_list);
+    cached(key, service_list, cache, crate::harmonize)
+}
+
+pub(crate) fn cached<F>(
+    key: CacheKey,
+    service_list: ServiceList,
+    cache: &dyn CompositionCache,
+    compose: F,
+) -> Result<CompositionOutput, HarmonizerError>
+where
+    F: FnOnce(ServiceList) -> Result<CompositionOutput, HarmonizerError>,
+{
+    if let Some(output) = cache.get(&key) {
+        return Ok(output);
+    }
+
+    let output = compose(service_list)?;
+    cache.insert(key, output.clone());
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cached;
+    use crate::{
+        CacheKey, CompositionCache, CompositionOutput, DiskCache, HarmonizerError, MemoryCache,
+        ServiceDefinition,
+    };
+    use std::cell::Cell;
+
+    fn key(service_list: &[ServiceDefinition]) -> CacheKey {
+        CacheKey::derive("composer", service_list)
+    }
+
+    fn service_list(name: &str) -> Vec<ServiceDefinition> {
+        vec![ServiceDefinition::new(
+            name,
+            "undefined",
+            format!("type Query {{ {}: String }}", name),
+        )]
+    }
+
+    fn output(supergraph_sdl: &str) -> CompositionOutput {
+        CompositionOutput {
+            supergraph_sdl: supergraph_sdl.to_string(),
+            hints: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_derives_keys_from_every_field_of_every_subgraph() {
+        let users = key(&service_list("users"));
+        assert_eq!(users, key(&service_list("users")));
+        assert_eq!(users.as_str().len(), 64);
+        assert!(users.as_str().chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(users, CacheKey::derive("other", &service_list("users")));
+
+        let others = [
+            service_list("movies"),
+            vec![ServiceDefinition::new(
+                "user",
+                "sundefined",
+                "type Query { users: String }",
+            )],
+            vec![ServiceDefinition::new(
+                "users",
+                "http://users",
+                "type Query { users: String }",
+            )],
+            service_list("users")
+                .into_iter()
+                .chain(service_list("movies"))
+                .collect(),
+            service_list("movies")
+                .into_iter()
+                .chain(service_list("users"))
+                .collect(),
+            Vec::new(),
+        ];
+        for other in &others {
+            assert_ne!(users, key(other));
+        }
+    }
+
+    #[test]
+    fn it_evicts_the_least_recently_used_composition() {
+        let cache = MemoryCache::new(2);
+        let users = key(&service_list("users"));
+        let movies = key(&service_list("movies"));
+        let books = key(&service_list("books"));
+
+        cache.insert(users.clone(), output("users"));
+        cache.insert(movies.clone(), output("movies"));
+        assert_eq!(cache.get(&users), Some(output("users")));
+        cache.insert(books.clone(), output("books"));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&users), Some(output("users")));
+        assert_eq!(cache.get(&movies), None);
+        assert_eq!(cache.get(&books), Some(output("books")));
+    }
+
+    #[test]
+    fn it_keeps_compositions_on_disk() {
+        let directory =
+            std::env::temp_dir().join(format!("harmonizer-cache-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let users = key(&service_list("users"));
+        let movies = key(&service_list("movies"));
+
+        let cache = DiskCache::new(&directory);
+        assert_eq!(cache.get(&users), None);
+        cache.insert(users.clone(), output("users"));
+        assert_eq!(
+            DiskCache::new(&directory).get(&users),
+            Some(output("users"))
+        );
+
+        std::fs::write(cache.path(&movies), "{").unwrap();
+        assert_eq!(cache.get(&movies), None);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn it_writes_the_same_key_from_many_threads() {
+        let directory = std::env::temp_dir().join(format!(
+            "harmonizer-cache-threads-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        let users = key(&service_list("users"));
+
+        let cache = DiskCache::new(&directory);
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let (cache, users) = (cache.clone(), users.clone());
+                std::thread::spawn(move || {
+                    for _ in 0..16 {
+                        cache.write(&users, &output("users")).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(cache.get(&users), Some(output("users")));
+        let files: Vec<_> = std::fs::read_dir(&directory).unwrap().collect();
+        assert_eq!(files.len(), 1);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn it_only_composes_on_a_miss() {
+        let cache = MemoryCache::new(8);
+        let compositions = Cell::new(0);
+        let compose = |service_list: Vec<ServiceDefinition>| {
+            compositions.set(compositions.get() + 1);
+            match service_list[0].name.as_str() {
+                "broken" => Err(HarmonizerError::NoResult),
+                name => Ok(output(name)),
+            }
+        };
+
+        let key = |name| key(&service_list(name));
+        for _ in 0..2 {
+            assert_eq!(
+                cached(key("users"), service_list("users"), &cache, compose).unwrap(),
+                output("users")
+            );
+        }
+        assert_eq!(compositions.get(), 1);
+
+        for _ in 0..2 {
+            assert!(cached(key("broken"), service_list("broken"), &cache, compose).is_err());
+        }
+        assert_eq!(compositions.get(), 3);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[cfg(feature = "js")]
+    #[test]
+    fn it_caches_harmonized_compositions() {
+        use crate::{harmonize, harmonize_cached};
+
+        let cache = MemoryCache::new(8);
+        let composed = harmonize_cached(service_list("users"), &cache).unwrap();
+        assert_eq!(composed, harmonize(service_list("users")).unwrap());
+        assert_eq!(
+            cache.get(&CacheKey::for_service_list(&service_list("users"))),
+            Some(composed)
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn it_caches_native_compositions() {
+        use crate::native::{harmonize, harmonize_cached};
+
+        let cache = MemoryCache::new(8);
+        let composed = harmonize_cached(service_list("users"), &cache).unwrap();
+        assert_eq!(composed, harmonize(service_list("users")).unwrap());
+        assert_eq!(
+            cache.get(&CacheKey::for_native_service_list(&service_list("users"))),
+            Some(composed)
+        );
+    }
+}
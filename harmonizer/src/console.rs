@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+/// The `console` method which the JavaScript within a [`Harmonizer`]'s runtime
+/// printed something with.
+///
+/// [`Harmonizer`]: crate::Harmonizer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleLevel {
+    /// `console.trace`
+    Trace,
+    /// `console.debug`
+    Debug,
+    /// `console.log`, which is also what the runtime's `print` uses.
+    Log,
+    /// `console.info`
+    Info,
+    /// `console.warn`
+    Warn,
+    /// `console.error`
+    Error,
+}
+
+impl ConsoleLevel {
+    /// The name of the `console` method.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsoleLevel::Trace => "trace",
+            ConsoleLevel::Debug => "debug",
+            ConsoleLevel::Log => "log",
+            ConsoleLevel::Info => "info",
+            ConsoleLevel::Warn => "warn",
+            ConsoleLevel::Error => "error",
+        }
+    }
+}
+
+impl Display for ConsoleLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<ConsoleLevel> for log::Level {
+    fn from(level: ConsoleLevel) -> Self {
+        match level {
+            ConsoleLevel::Trace => log::Level::Trace,
+            ConsoleLevel::Debug => log::Level::Debug,
+            ConsoleLevel::Log | ConsoleLevel::Info => log::Level::Info,
+            ConsoleLevel::Warn => log::Level::Warn,
+            ConsoleLevel::Error => log::Level::Error,
+        }
+    }
+}
+
+/// The console of a [`Harmonizer`] which isn't given one: everything is
+/// logged through the [`log`] facade with the `harmonizer::js` target.
+///
+/// [`Harmonizer`]: crate::Harmonizer
+/// [`log`]: https://crates.io/crates/log
+pub(crate) fn log(level: ConsoleLevel, message: &str) {
+    log::log!(target: "harmonizer::js", level.into(), "{}", message);
+}
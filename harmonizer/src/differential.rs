@@ -0,0 +1,245 @@
+//! Differential testing of native composition against JavaScript composition,
+//! for builds with both the `js` and the `native` cargo features.
+//!
+//! Native composition is meant to be a drop-in replacement for the
+//! `composeAndValidate` of `@apollo/federation`, so every service list should
+//! come out of both the same way: as the same supergraph SDL, or as errors
+//! with the same codes.  [`compare_composers`] composes a corpus of service
+//! lists both ways and reports every service list for which that isn't the
+//! case.
+
+use crate::{native, CompositionOutput, Harmonizer, HarmonizerError, ServiceList};
+use std::fmt::Display;
+
+/// What came of composing a service list, as far as comparing composers is
+/// concerned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompositionOutcome {
+    /// The service list was composed into this supergraph SDL.
+    Composed(String),
+    /// The service list couldn't be composed, because of errors with these
+    /// codes.  They're sorted before they're compared, since composers
+    /// needn't report errors in the same order.
+    Failed(Vec<String>),
+}
+
+impl CompositionOutcome {
+    /// The outcome of a composition, unless the composer itself failed (e.g.,
+    /// because its runtime couldn't be started) rather than the composition.
+    fn of(composed: Result<CompositionOutput, HarmonizerError>) -> Result<Self, HarmonizerError> {
+        match composed {
+            Ok(output) => Ok(CompositionOutcome::Composed(output.supergraph_sdl)),
+            Err(HarmonizerError::Composition(errors)) => {
+                let mut codes: Vec<String> =
+                    errors.iter().map(|err| err.code().to_string()).collect();
+                codes.sort();
+                Ok(CompositionOutcome::Failed(codes))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// A service list of the corpus which JavaScript and native composition
+/// didn't compose the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompositionDifference {
+    /// The name the service list was given within the corpus.
+    pub name: String,
+    /// What came of composing it with `@apollo/federation`.
+    pub js: CompositionOutcome,
+    /// What came of composing it natively.
+    pub native: CompositionOutcome,
+}
+
+impl Display for CompositionDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.js, &self.native) {
+            (CompositionOutcome::Composed(js), CompositionOutcome::Composed(native)) => {
+                // Both supergraphs are mostly the same, so the first line
+                // which isn't is the most helpful thing to point out.
+                let (line, (js_line, native_line)) = js
+                    .lines()
+                    .map(Some)
+                    .chain(std::iter::repeat(None))
+                    .zip(native.lines().map(Some).chain(std::iter::repeat(None)))
+                    .enumerate()
+                    .find(|(_, (js, native))| js != native)
+                    .unwrap_or((0, (None, None)));
+                writeln!(
+                    f,
+                    "{}: the supergraph SDL differs at line {}",
+                    self.name,
+                    line + 1
+                )?;
+                writeln!(f, "  js:     {}", js_line.unwrap_or("<end of SDL>"))?;
+                write!(f, "  native: {}", native_line.unwrap_or("<end of SDL>"))
+            }
+            (CompositionOutcome::Failed(js), CompositionOutcome::Failed(native)) => write!(
+                f,
+                "{}: the error codes differ (js: {}; native: {})",
+                self.name,
+                js.join(", "),
+                native.join(", ")
+            ),
+            (CompositionOutcome::Composed(_), CompositionOutcome::Failed(native)) => write!(
+                f,
+                "{}: only JavaScript composition succeeded (native: {})",
+                self.name,
+                native.join(", ")
+            ),
+            (CompositionOutcome::Failed(js), CompositionOutcome::Composed(_)) => write!(
+                f,
+                "{}: only native composition succeeded (js: {})",
+                self.name,
+                js.join(", ")
+            ),
+        }
+    }
+}
+
+/// Compose every named service list of the `corpus` with both JavaScript and
+/// native composition, returning the ones which they didn't compose the same
+/// way.
+///
+/// The JavaScript side of this runs on a single [`Harmonizer`], so a failure
+/// of its runtime fails the whole comparison.
+pub fn compare_composers<I, N>(corpus: I) -> Result<Vec<CompositionDifference>, HarmonizerError>
+where
+    I: IntoIterator<Item = (N, ServiceList)>,
+    N: Into<String>,
+{
+    let mut harmonizer = Harmonizer::new()?;
+    let mut differences = Vec::new();
+    for (name, service_list) in corpus {
+        let native = CompositionOutcome::of(native::compose(&service_list))?;
+        let js = CompositionOutcome::of(harmonizer.compose(service_list))?;
+        if js != native {
+            differences.push(CompositionDifference {
+                name: name.into(),
+                js,
+                native,
+            });
+        }
+    }
+    Ok(differences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_composers, CompositionDifference, CompositionOutcome};
+    use crate::native::fixtures;
+    use crate::{ServiceDefinition, ServiceList};
+
+    /// Service lists which both composers are expected to agree upon.  Syntax
+    /// errors are left out, since both composers report them from the same
+    /// `graphql-parser` before JavaScript is ever entered.
+    fn corpus() -> Vec<(&'static str, ServiceList)> {
+        let mut reversed_fixtures = fixtures();
+        reversed_fixtures.reverse();
+
+        vec![
+            ("fixtures", fixtures()),
+            ("reversed fixtures", reversed_fixtures),
+            (
+                "disjoint subgraphs",
+                vec![
+                    ServiceDefinition::new("users", "undefined", "type Query { users: [String!] }"),
+                    ServiceDefinition::new("movies", "undefined", "type Query { movies: [String!] }"),
+                ],
+            ),
+            (
+                "value types",
+                vec![
+                    ServiceDefinition::new(
+                        "users",
+                        "https://users",
+                        "type Query { me: User } type User { id: ID! name: Name } type Name { first: String last: String }",
+                    ),
+                    ServiceDefinition::new(
+                        "reviews",
+                        "https://reviews",
+                        "type Query { author: Name } type Name { first: String last: String }",
+                    ),
+                ],
+            ),
+            (
+                "renamed root operation types",
+                vec![ServiceDefinition::new(
+                    "users",
+                    "https://users",
+                    "schema { query: RootQuery mutation: RootMutation } type RootQuery { me: String } type RootMutation { login(name: String = \"guest\"): String }",
+                )],
+            ),
+            (
+                "unused external field",
+                vec![
+                    ServiceDefinition::new(
+                        "accounts",
+                        "https://accounts",
+                        r#"type Query { me: User } type User @key(fields: "id") { id: ID! name: String }"#,
+                    ),
+                    ServiceDefinition::new(
+                        "reviews",
+                        "https://reviews",
+                        r#"extend type User @key(fields: "id") { id: ID! @external name: String @external reviews: [String] }"#,
+                    ),
+                ],
+            ),
+            (
+                "mismatched enums",
+                vec![
+                    ServiceDefinition::new(
+                        "paint",
+                        "https://paint",
+                        "type Query { paint: Color } enum Color { RED GREEN }",
+                    ),
+                    ServiceDefinition::new(
+                        "ink",
+                        "https://ink",
+                        "extend type Query { ink: Color } enum Color { RED }",
+                    ),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn it_composes_the_corpus_like_javascript() {
+        let differences = compare_composers(corpus()).unwrap();
+        assert!(
+            differences.is_empty(),
+            "{}",
+            differences
+                .iter()
+                .map(CompositionDifference::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    #[test]
+    fn it_points_out_the_first_line_which_differs() {
+        let difference = CompositionDifference {
+            name: "fixtures".to_string(),
+            js: CompositionOutcome::Composed("schema\n{\n  query: Query\n}\n".to_string()),
+            native: CompositionOutcome::Composed(
+                "schema\n{\n  query: Query\n  mutation: Mutation\n}\n".to_string(),
+            ),
+        };
+        assert_eq!(
+            difference.to_string(),
+            "fixtures: the supergraph SDL differs at line 4\n  js:     }\n  native:   mutation: Mutation"
+        );
+
+        let difference = CompositionDifference {
+            name: "syntax error".to_string(),
+            js: CompositionOutcome::Failed(vec!["UNKNOWN".to_string()]),
+            native: CompositionOutcome::Composed(String::new()),
+        };
+        assert_eq!(
+            difference.to_string(),
+            "syntax error: only native composition succeeded (js: UNKNOWN)"
+        );
+    }
+}
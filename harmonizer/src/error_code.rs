@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+macro_rules! composition_error_codes {
+    ($($(#[doc = $doc:literal])+ $variant:ident => $code:literal,)+) => {
+        /// An Apollo Federation composition error code, as reported in the
+        /// `extensions.code` of the errors created with [`errorWithCode`] by
+        /// the validators in the `federation-js` composition library.
+        ///
+        /// Codes which this version of the harmonizer doesn't know about are
+        /// preserved as [`CompositionErrorCode::Unknown`].
+        ///
+        /// [`errorWithCode`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/utils.ts#L200-L216
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum CompositionErrorCode {
+            $(
+                $(#[doc = $doc])+
+                $variant,
+            )+
+            /// A code which isn't (yet) known to this version of the
+            /// harmonizer.
+            Unknown(String),
+        }
+
+        impl CompositionErrorCode {
+            /// Every code that is known to this version of the harmonizer.
+            pub const KNOWN: &'static [CompositionErrorCode] = &[
+                $(CompositionErrorCode::$variant,)+
+            ];
+
+            /// The code as it appears in an error's `extensions.code`.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(CompositionErrorCode::$variant => $code,)+
+                    CompositionErrorCode::Unknown(code) => code,
+                }
+            }
+        }
+
+        impl From<&str> for CompositionErrorCode {
+            fn from(code: &str) -> Self {
+                match code {
+                    $($code => CompositionErrorCode::$variant,)+
+                    _ => CompositionErrorCode::Unknown(code.to_string()),
+                }
+            }
+        }
+    };
+}
+
+composition_error_codes! {
+    /// A custom executable directive isn't defined identically (with the same
+    /// arguments and locations) in every service.
+    ExecutableDirectivesIdentical => "EXECUTABLE_DIRECTIVES_IDENTICAL",
+    /// A custom executable directive isn't implemented by every service.
+    ExecutableDirectivesInAllServices => "EXECUTABLE_DIRECTIVES_IN_ALL_SERVICES",
+    /// A field marked with `@external` doesn't exist on the base type.
+    ExternalMissingOnBase => "EXTERNAL_MISSING_ON_BASE",
+    /// A field marked with `@external` doesn't match the type of the field on
+    /// the base type.
+    ExternalTypeMismatch => "EXTERNAL_TYPE_MISMATCH",
+    /// A field marked with `@external` isn't used by a `@requires`, `@key` or
+    /// `@provides` directive.
+    ExternalUnused => "EXTERNAL_UNUSED",
+    /// The `fields` of a `@key` select a field which was overwritten by
+    /// another service.
+    KeyFieldsMissingOnBase => "KEY_FIELDS_MISSING_ON_BASE",
+    /// The `fields` of a `@key` select a field which returns a list, an
+    /// interface or a union.
+    KeyFieldsSelectInvalidType => "KEY_FIELDS_SELECT_INVALID_TYPE",
+    /// An originating type doesn't specify a `@key` directive.
+    KeyMissingOnBase => "KEY_MISSING_ON_BASE",
+    /// An extending service uses more than one `@key` directive.
+    MultipleKeysOnExtension => "MULTIPLE_KEYS_ON_EXTENSION",
+    /// An extending service uses a `@key` which isn't specified by the
+    /// originating type.
+    KeyNotSpecified => "KEY_NOT_SPECIFIED",
+    /// A field selected by a `@provides` has no matching `@external` field.
+    ProvidesFieldsMissingExternal => "PROVIDES_FIELDS_MISSING_EXTERNAL",
+    /// The `fields` of a `@provides` select a field which returns a list, an
+    /// interface or a union.
+    ProvidesFieldsSelectInvalidType => "PROVIDES_FIELDS_SELECT_INVALID_TYPE",
+    /// A `@provides` directive is used on a field whose type isn't an entity.
+    ProvidesNotOnEntity => "PROVIDES_NOT_ON_ENTITY",
+    /// A field selected by a `@requires` has no matching `@external` field.
+    RequiresFieldsMissingExternal => "REQUIRES_FIELDS_MISSING_EXTERNAL",
+    /// The `fields` of a `@requires` select a field which isn't on the base
+    /// type.
+    RequiresFieldsMissingOnBase => "REQUIRES_FIELDS_MISSING_ON_BASE",
+    /// An enum is defined more than once within the same service.
+    DuplicateEnumDefinition => "DUPLICATE_ENUM_DEFINITION",
+    /// A scalar is defined more than once within the same service.
+    DuplicateScalarDefinition => "DUPLICATE_SCALAR_DEFINITION",
+    /// An enum value is defined more than once within the same service.
+    DuplicateEnumValue => "DUPLICATE_ENUM_VALUE",
+    /// A field on a base type definition is marked with `@external`.
+    ExternalUsedOnBase => "EXTERNAL_USED_ON_BASE",
+    /// A field selected by a `@key` isn't found in the service, or has no
+    /// matching `@external` field.
+    KeyFieldsMissingExternal => "KEY_FIELDS_MISSING_EXTERNAL",
+    /// A field on a base type definition uses `@requires`.
+    RequiresUsedOnBase => "REQUIRES_USED_ON_BASE",
+    /// A service defines one of the reserved `_service` or `_entities` fields
+    /// on its query root.
+    ReservedFieldUsed => "RESERVED_FIELD_USED",
+    /// A service with a custom query root also defines a `Query` type.
+    RootQueryUsed => "ROOT_QUERY_USED",
+    /// A service with a custom mutation root also defines a `Mutation` type.
+    RootMutationUsed => "ROOT_MUTATION_USED",
+    /// A service with a custom subscription root also defines a
+    /// `Subscription` type.
+    RootSubscriptionUsed => "ROOT_SUBSCRIPTION_USED",
+    /// An enum doesn't have identical values in every service defining it.
+    EnumMismatch => "ENUM_MISMATCH",
+    /// A type is an enum in some services, but not in others.
+    EnumMismatchType => "ENUM_MISMATCH_TYPE",
+    /// A union value type doesn't have the same members in every service.
+    ValueTypeUnionTypesMismatch => "VALUE_TYPE_UNION_TYPES_MISMATCH",
+    /// A type is extended with an extension of a different kind.
+    ExtensionOfWrongKind => "EXTENSION_OF_WRONG_KIND",
+    /// A type is extended, but never defined.
+    ExtensionWithNoBase => "EXTENSION_WITH_NO_BASE",
+    /// A value type is defined with different kinds in different services.
+    ValueTypeKindMismatch => "VALUE_TYPE_KIND_MISMATCH",
+    /// A field of a value type has different types in different services.
+    ValueTypeFieldTypeMismatch => "VALUE_TYPE_FIELD_TYPE_MISMATCH",
+    /// An argument of a value type's field has different types in different
+    /// services.
+    ValueTypeInputValueMismatch => "VALUE_TYPE_INPUT_VALUE_MISMATCH",
+    /// A value type uses the `@key` directive.
+    ValueTypeNoEntity => "VALUE_TYPE_NO_ENTITY",
+}
+
+impl From<String> for CompositionErrorCode {
+    fn from(code: String) -> Self {
+        match CompositionErrorCode::from(code.as_str()) {
+            CompositionErrorCode::Unknown(_) => CompositionErrorCode::Unknown(code),
+            known => known,
+        }
+    }
+}
+
+impl From<CompositionErrorCode> for String {
+    fn from(code: CompositionErrorCode) -> Self {
+        match code {
+            CompositionErrorCode::Unknown(code) => code,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl FromStr for CompositionErrorCode {
+    type Err = std::convert::Infallible;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Ok(code.into())
+    }
+}
+
+impl fmt::Display for CompositionErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompositionErrorCode;
+
+    #[test]
+    fn it_round_trips_every_known_code() {
+        for code in CompositionErrorCode::KNOWN {
+            assert_eq!(&CompositionErrorCode::from(code.as_str()), code);
+        }
+
+        assert_eq!(
+            CompositionErrorCode::from("SOMETHING_NEW"),
+            CompositionErrorCode::Unknown("SOMETHING_NEW".to_string())
+        );
+    }
+
+    /// Every code that the bundled composition library can emit must be known
+    /// to [`CompositionErrorCode`].  Codes are (almost) always passed to
+    /// `errorWithCode` as string literals, which we can find in the bundle.
+    #[test]
+    #[cfg(feature = "js")]
+    fn it_knows_every_code_in_the_bundle() {
+        let bundle = include_str!("../dist/composition.js");
+        let mut found = 0;
+
+        for (index, _) in bundle.match_indices("errorWithCode(") {
+            let call = bundle[index + "errorWithCode(".len()..].trim_start();
+            let quote = match call.chars().next() {
+                Some(quote @ '\'') | Some(quote @ '"') => quote,
+                // This is the definition of `errorWithCode` or a code which
+                // is built at runtime (like the `ROOT_*_USED` codes), neither
+                // of which we can check here.
+                _ => continue,
+            };
+            let code = &call[1..call[1..].find(quote).expect("unterminated string") + 1];
+
+            found += 1;
+            assert!(
+                !matches!(
+                    CompositionErrorCode::from(code),
+                    CompositionErrorCode::Unknown(_)
+                ),
+                "`{}` is emitted by the bundled composition library but isn't a known CompositionErrorCode",
+                code
+            );
+        }
+
+        assert!(found > 0, "no error codes found in dist/composition.js");
+    }
+}
@@ -15,11 +15,53 @@ notion that exists within the JavaScript composition library), this crate uses
 [`deno_core`] to invoke the JavaScript within V8.  This is ultimately
 accomplished using [`rusty_v8`]'s V8 bindings to V8.
 
+The bundle also includes the [`@apollo/query-planner`] library, which [`plan`] uses
+to build the query plan for an operation against a composed supergraph.
+Supergraphs can also be checked without any JavaScript at all, using
+[`Supergraph::parse`].
+
+Starting V8 and evaluating the bundled library is not free, so callers that
+compose many times can create a [`Harmonizer`] once and call
+[`Harmonizer::compose`] on it repeatedly instead.
+When only a few of the subgraphs change between compositions,
+[`Harmonizer::recompose`] avoids parsing and validating the others again.
+
+To compose many service lists in parallel, a [`HarmonizerPool`] keeps a number
+of warm runtimes on dedicated threads and distributes the service lists across
+them.
+
+Callers within async services can use an `AsyncHarmonizer` instead, which is
+available with the `async` cargo feature and owns its runtimes on dedicated
+threads.
+
+Most compositions are of subgraphs which have been composed before, so with
+the `cache` cargo feature, `harmonize_cached` keeps the results of
+composition in a cache (in memory or on disk) keyed by a hash of the
+subgraphs and the version of `@apollo/federation`, and only composes on a
+miss.
+
+Enabling the `snapshot` cargo feature goes one step further and evaluates the
+bundle at build time, capturing the result in a V8 startup snapshot that is
+embedded in the crate.  Runtimes are then booted from that snapshot, which
+makes even the first composition in a process start nearly instantly.
+
 While we intend for a future version of composition to be done natively within
 Rust, this allows us to provide a more stable transition using an already stable
-composition implementation while we work toward something else.
+composition implementation while we work toward something else.  That work has
+started behind the `native` cargo feature, which provides `native::harmonize`
+and `native::validate_subgraph` to compose and validate within Rust instead.
+Whenever the `js` feature is enabled, [`harmonize`] itself composes with
+JavaScript, since native composition doesn't report hints yet and leaves the
+rules which `graphql-js` applies unchecked.  Everything else still needs the
+bundle, which is what the default `js` feature provides, so
+`default-features = false` along with `native` leaves V8 out of the build
+altogether, and the crate root's `harmonize`, `validate_subgraph` and (with
+`cache`) `harmonize_cached` are then the native ones.  With both features,
+`compare_composers` composes a corpus of service lists both ways and reports
+any which don't come out with the same supergraph SDL or error codes.
 
 [`@apollo/federation`]: https://npm.im/@apollo/federation
+[`@apollo/query-planner`]: https://npm.im/@apollo/query-planner
 [IIFE]: https://developer.mozilla.org/en-US/docs/Glossary/IIFE
 [Rollup.js]: http://rollupjs.org/
 [`deno_core`]: https://crates.io/crates/deno_core
@@ -29,12 +71,80 @@ composition implementation while we work toward something else.
 #![forbid(unsafe_code)]
 #![deny(missing_debug_implementations, nonstandard_style)]
 #![warn(missing_docs, future_incompatible, unreachable_pub, rust_2018_idioms)]
-use deno_core::{op_sync, JsRuntime};
 use serde::{Deserialize, Serialize};
-use std::sync::mpsc::channel;
-use std::{fmt::Display, io::Write};
+use std::fmt::Display;
 use thiserror::Error;
 
+#[cfg(not(any(feature = "js", feature = "native")))]
+compile_error!("the harmonizer needs either the `js` or the `native` feature to compose");
+
+#[cfg(feature = "async")]
+mod async_harmonizer;
+#[cfg(feature = "async")]
+pub use async_harmonizer::AsyncHarmonizer;
+
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(all(feature = "cache", feature = "js"))]
+pub use cache::harmonize_cached;
+#[cfg(feature = "cache")]
+pub use cache::{CacheKey, CompositionCache, DiskCache, MemoryCache};
+
+#[cfg(feature = "js")]
+mod console;
+#[cfg(feature = "js")]
+pub use console::ConsoleLevel;
+
+#[cfg(all(feature = "js", feature = "native"))]
+mod differential;
+#[cfg(all(feature = "js", feature = "native"))]
+pub use differential::{compare_composers, CompositionDifference, CompositionOutcome};
+
+mod error_code;
+pub use error_code::CompositionErrorCode;
+
+#[cfg(feature = "native")]
+pub mod native;
+#[cfg(all(feature = "native", feature = "cache", not(feature = "js")))]
+pub use native::harmonize_cached;
+#[cfg(all(feature = "native", not(feature = "js")))]
+pub use native::{harmonize, validate_subgraph};
+
+#[cfg(feature = "js")]
+mod options;
+#[cfg(feature = "js")]
+pub use options::ComposeOptions;
+
+mod output;
+pub use output::{CompositionHint, CompositionHintCode, CompositionOutput, PartialComposition};
+
+mod plan;
+pub use plan::{
+    FetchNode, FlattenNode, ParallelNode, PlanNode, PlanningError, PlanningErrorLocation,
+    QueryPlan, QueryPlanSelectionNode, ResponsePathElement, SequenceNode,
+};
+
+#[cfg(feature = "js")]
+mod runtime;
+#[cfg(feature = "js")]
+pub use runtime::{CancellationHandle, Harmonizer};
+
+#[cfg(feature = "js")]
+mod pool;
+#[cfg(feature = "js")]
+pub use pool::HarmonizerPool;
+
+#[cfg(feature = "js")]
+mod workers;
+
+mod supergraph;
+pub use supergraph::{
+    EntityKey, Graph, Supergraph, SupergraphError, SupergraphErrorLocation, SupergraphField,
+    SupergraphType, SupergraphTypeKind,
+};
+
+mod syntax;
+
 /// The `ServiceDefinition` represents everything we need to know about a
 /// service (subgraph) for its GraphQL runtime responsibilities.  It is not
 /// at all different from the notion of [`ServiceDefinition` in TypeScript]
@@ -44,7 +154,7 @@ use thiserror::Error;
 /// will be serialized into camelCase, to match the JavaScript expectations.
 ///
 /// [`ServiceDefinition` in TypeScript]: https://github.com/apollographql/federation/blob/d2e34909/federation-js/src/composition/types.ts#L49-L53
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceDefinition {
     /// The name of the service (subgraph).  We use this name internally to
@@ -84,12 +194,25 @@ pub type ServiceList = Vec<ServiceDefinition>;
 ///
 /// [`graphql-js']: https://npm.im/graphql
 /// [`GraphQLError`]: https://github.com/graphql/graphql-js/blob/3869211/src/error/GraphQLError.js#L18-L75
-#[derive(Debug, Error, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Error, Serialize, Deserialize, PartialEq)]
 pub struct CompositionError {
     /// A human-readable description of the error that prevented composition.
     pub message: Option<String>,
     /// [`CompositionErrorExtensions`]
     pub extensions: Option<CompositionErrorExtensions>,
+    /// The places within the subgraphs' SDL that this error refers to, derived
+    /// from the AST `nodes` (or, for syntax errors, the `source`) of the
+    /// `GraphQLError`.
+    #[serde(default)]
+    pub locations: Vec<CompositionErrorLocation>,
+    /// The names of the subgraphs this error originated from, if they could be
+    /// determined.
+    #[serde(default)]
+    pub subgraphs: Vec<String>,
+    /// The schema coordinate this error is about (e.g., `User` or
+    /// `User.favorites`), if it could be determined.
+    #[serde(default)]
+    pub coordinate: Option<String>,
 }
 
 impl Display for CompositionError {
@@ -102,6 +225,30 @@ impl Display for CompositionError {
     }
 }
 
+/// A position within the SDL of a subgraph, as reported by the `locations` of a
+/// JavaScript [`GraphQLError`].
+///
+/// [`GraphQLError`]: https://github.com/graphql/graphql-js/blob/3869211/src/error/GraphQLError.js#L18-L75
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompositionErrorLocation {
+    /// The name of the subgraph whose SDL this location is within, if it could
+    /// be determined.
+    pub subgraph: Option<String>,
+    /// The line within the SDL, starting at 1.
+    pub line: usize,
+    /// The column within the line, starting at 1.
+    pub column: usize,
+}
+
+impl Display for CompositionErrorLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(subgraph) = &self.subgraph {
+            write!(f, "{}:", subgraph)?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 /// Mimicking the JavaScript-world from which this error comes, this represents
 /// the `extensions` property of a JavaScript [`GraphQLError`] from the
 /// [`graphql-js`] library. Such errors are created when errors have prevented
@@ -112,22 +259,12 @@ impl Display for CompositionError {
 /// [`GraphQLError`]: https://github.com/graphql/graphql-js/blob/3869211/src/error/GraphQLError.js#L18-L75
 /// [`errorWithCode`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/utils.ts#L200-L216
 /// [example]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/executableDirectivesInAllServices.ts#L47-L53
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CompositionErrorExtensions {
-    /// An Apollo Federation composition error code.
-    ///
-    /// A non-exhaustive list of error codes that this includes, is:
-    ///
-    ///   - EXTERNAL_TYPE_MISMATCH
-    ///   - EXTERNAL_UNUSED
-    ///   - KEY_FIELDS_MISSING_ON_BASE
-    ///   - KEY_MISSING_ON_BASE
-    ///   - KEY_NOT_SPECIFIED
-    ///   - PROVIDES_FIELDS_MISSING_EXTERNAL
-    ///
-    /// ...and many more!  See the `federation-js` composition library for
-    /// more details (and search for `errorWithCode`).
-    pub code: String,
+    /// An Apollo Federation composition error code.  See
+    /// [`CompositionErrorCode`] for the codes which are known to this version
+    /// of the harmonizer.
+    pub code: CompositionErrorCode,
 }
 
 /// An error that was received during composition within JavaScript.
@@ -135,134 +272,182 @@ impl CompositionError {
     /// Retrieve the error code from an error received during composition.
     pub fn code(&self) -> &str {
         match self.extensions {
-            Some(ref ext) => &*ext.code,
+            Some(ref ext) => ext.code.as_str(),
             None => "UNKNOWN",
         }
     }
+
+    /// Retrieve the typed error code from an error received during
+    /// composition, if it had one.
+    pub fn error_code(&self) -> Option<&CompositionErrorCode> {
+        self.extensions.as_ref().map(|ext| &ext.code)
+    }
 }
 
-/// The `harmonize` function receives a [`ServiceList`] and invokes JavaScript
-/// composition on it.
+/// An error which prevented a supergraph (or a query plan) from being produced.
 ///
-pub fn harmonize(service_list: ServiceList) -> Result<String, Vec<CompositionError>> {
-    // Initialize a runtime instance
-    let mut runtime = JsRuntime::new(Default::default());
-
-    // We'll use this channel to get the results
-    let (tx, rx) = channel();
-
-    // The first thing we do is define an op so we can print data to STDOUT,
-    // because by default the JavaScript console functions are just stubs (they
-    // don't do anything).
-
-    // Register the op for outputting bytes to stdout. It can be invoked with
-    // Deno.core.dispatch and the id this method returns or
-    // Deno.core.dispatchByName and the name provided.
-    runtime.register_op(
-        "op_print",
-        // The op_fn callback takes a state object OpState,
-        // a structured arg of type `T` and an optional ZeroCopyBuf,
-        // a mutable reference to a JavaScript ArrayBuffer
-        op_sync(|_state, _msg: Option<String>, zero_copy| {
-            let mut out = std::io::stdout();
-
-            // Write the contents of every buffer to stdout
-            if let Some(buf) = zero_copy {
-                out.write_all(&buf)
-                    .expect("failure writing buffered output");
-            }
+/// Only [`HarmonizerError::Composition`], [`HarmonizerError::Planning`] and
+/// [`HarmonizerError::Supergraph`] are the result of problems with the
+/// subgraphs, operations or supergraphs themselves, while
+/// [`HarmonizerError::Timeout`] and [`HarmonizerError::HeapLimitExceeded`]
+/// mean that they were too costly to process within the limits of the
+/// [`ComposeOptions`] and [`HarmonizerError::Cancelled`] that the caller gave
+/// up on them.  Every
+/// other variant represents a failure within the harmonizer or its JavaScript
+/// runtime, which a long-running process can report and recover from,
+/// typically by discarding the [`Harmonizer`] which produced it.
+#[derive(Debug, Error)]
+pub enum HarmonizerError {
+    /// Composition ran to completion, but the subgraphs could not be composed
+    /// into a supergraph.
+    #[error("{count} {errors} occurred during composition", count = .0.len(), errors = if .0.len() == 1 { "error" } else { "errors" })]
+    Composition(Vec<CompositionError>),
+
+    /// The operation could not be planned, e.g., because it isn't valid
+    /// against the supergraph.
+    #[error("{count} {errors} occurred during query planning", count = .0.len(), errors = if .0.len() == 1 { "error" } else { "errors" })]
+    Planning(Vec<PlanningError>),
+
+    /// A supergraph SDL is not a valid supergraph.  See [`Supergraph::parse`].
+    #[error("{count} {errors} found in the supergraph", count = .0.len(), errors = if .0.len() == 1 { "error" } else { "errors" })]
+    Supergraph(Vec<SupergraphError>),
+
+    /// The call did not finish within the [`ComposeOptions::timeout`], and
+    /// was terminated.
+    #[error("composition did not finish within {0:?}")]
+    Timeout(std::time::Duration),
+
+    /// The call needed a larger heap than the [`ComposeOptions::max_heap_size`]
+    /// (in bytes), and was terminated.
+    #[error("composition exceeded the maximum heap size of {0} bytes")]
+    HeapLimitExceeded(usize),
+
+    /// The call was cancelled with a [`CancellationHandle`].
+    #[error("composition was cancelled")]
+    Cancelled,
+
+    /// Evaluating a script within the JavaScript runtime failed.  This
+    /// includes exceptions thrown by composition itself, e.g., when a service
+    /// in the [`ServiceList`] is missing its name.
+    #[error("error evaluating {script} in the composition runtime: {source}")]
+    JavaScript {
+        /// The name of the script which was being evaluated.
+        script: &'static str,
+        /// The underlying error, which is usually a JavaScript exception.
+        source: anyhow::Error,
+    },
+
+    /// The [`ServiceList`] could not be serialized for the JavaScript runtime.
+    #[error("unable to serialize service list into the composition runtime: {0}")]
+    SerializeServiceList(#[source] serde_json::Error),
+
+    /// The result reported by the JavaScript runtime could not be
+    /// deserialized.
+    #[error("unable to deserialize composition result: {0}")]
+    DeserializeResult(#[source] serde_json::Error),
+
+    /// The JavaScript runtime finished without reporting a result.
+    #[error("composition runtime did not report a result")]
+    NoResult,
+
+    /// The thread which a call was handed to stopped without reporting its
+    /// result.
+    #[error("composition worker stopped without reporting a result")]
+    WorkerStopped,
+
+    /// A thread for composing on could not be spawned.
+    #[error("unable to spawn a composition worker: {0}")]
+    SpawnWorker(#[source] std::io::Error),
+}
 
-            Ok(()) // No meaningful result
-        }),
-    );
+impl HarmonizerError {
+    /// Whether this error was caused by a failure within the harmonizer
+    /// rather than by the subgraphs which were being composed, the operation
+    /// which was being planned or the supergraph which was being parsed.
+    pub fn is_internal(&self) -> bool {
+        !matches!(
+            self,
+            HarmonizerError::Composition(_)
+                | HarmonizerError::Planning(_)
+                | HarmonizerError::Supergraph(_)
+                | HarmonizerError::Timeout(_)
+                | HarmonizerError::HeapLimitExceeded(_)
+                | HarmonizerError::Cancelled
+        )
+    }
+}
 
-    runtime.register_op(
-        "op_composition_result",
-        op_sync(move |_state, value, _zero_copy| {
-            tx.send(serde_json::from_value(value).expect("deserializing composition result"))
-                .expect("channel must be open");
+/// Validate a single subgraph on its own, without composing it into a
+/// supergraph.  See [`Harmonizer::validate_subgraph`].
+#[cfg(feature = "js")]
+pub fn validate_subgraph(
+    service: ServiceDefinition,
+) -> Result<Vec<CompositionError>, HarmonizerError> {
+    Harmonizer::new()?.validate_subgraph(service)
+}
 
-            Ok(serde_json::json!(null))
+/// Normalize the SDL of a single subgraph into the form that composition
+/// actually sees.  See [`Harmonizer::normalize`].
+#[cfg(feature = "js")]
+pub fn normalize(service: ServiceDefinition) -> Result<String, HarmonizerError> {
+    Harmonizer::new()?.normalize(service)
+}
 
-            // Don't return anything to JS
-        }),
-    );
+/// Build a [`QueryPlan`] for an operation against a supergraph.  See
+/// [`Harmonizer::plan`].
+#[cfg(feature = "js")]
+pub fn plan(
+    supergraph_sdl: &str,
+    operation: &str,
+    operation_name: Option<&str>,
+) -> Result<QueryPlan, HarmonizerError> {
+    Harmonizer::new()?.plan(supergraph_sdl, operation, operation_name)
+}
 
-    // The runtime automatically contains a Deno.core object with several
-    // functions for interacting with it.
-    runtime
-        .execute(
-            "<init>",
-            r#"
-// First we initialize the ops cache.
-// This maps op names to their id's.
-Deno.core.ops();
-
-// Then we define a print function that uses
-// our op_print op to display the stringified argument.
-const _newline = new Uint8Array([10]);
-function print(value) {
-  Deno.core.dispatchByName('op_print', 0, value.toString(), _newline);
+/// The `harmonize` function receives a [`ServiceList`] and invokes JavaScript
+/// composition on it, returning the supergraph SDL along with any
+/// [`CompositionHint`]s about the subgraphs.
+///
+/// Every call starts a new JavaScript runtime.  Use a [`Harmonizer`] to
+/// compose repeatedly without paying that cost each time.
+#[cfg(feature = "js")]
+pub fn harmonize(service_list: ServiceList) -> Result<CompositionOutput, HarmonizerError> {
+    Harmonizer::new()?.compose(service_list)
 }
 
-function done(result) {
-  Deno.core.opSync('op_composition_result', result);
+/// Like [`harmonize`], but within the time and memory limits of the given
+/// [`ComposeOptions`].
+#[cfg(feature = "js")]
+pub fn harmonize_with_options(
+    service_list: ServiceList,
+    options: ComposeOptions,
+) -> Result<CompositionOutput, HarmonizerError> {
+    Harmonizer::with_options(options)?.compose(service_list)
 }
 
-// We build some of the preliminary objects that our Rollup-built package is
-// expecting to be present in the environment.
-// node_fetch_1 is an unused external dependency we don't bundle.  See the
-// configuration in this package's 'rollup.config.js' for where this is marked
-// as an external dependency and thus not packaged into the bundle.
-node_fetch_1 = {};
-// 'process' is a Node.js ism.  We rely on process.env.NODE_ENV, in
-// particular, to determine whether or not we are running in a debug
-// mode.  For the purposes of harmonizer, we don't gain anything from
-// running in such a mode.
-process = { env: { "NODE_ENV": "production" }};
-// Some JS runtime implementation specific bits that we rely on that
-// need to be initialized as empty objects.
-global = {};
-exports = {};
-"#,
-        )
-        .expect("unable to initialize composition runtime environment");
-
-    // Load the composition library.
-    runtime
-        .execute("composition.js", include_str!("../dist/composition.js"))
-        .expect("unable to evaluate composition module");
-
-    // We literally just turn it into a JSON object that we'll execute within
-    // the runtime.
-    let service_list_javascript = format!(
-        "serviceList = {}",
-        serde_json::to_string(&service_list)
-            .expect("unable to serialize service list into JavaScript runtime")
-    );
-
-    runtime
-        .execute("<set_service_list>", &service_list_javascript)
-        .expect("unable to evaluate service list in JavaScript runtime");
-
-    runtime
-        .execute("do_compose.js", include_str!("../js/do_compose.js"))
-        .expect("unable to invoke composition in JavaScript runtime");
-
-    rx.recv().expect("channel remains open")
+/// Like [`harmonize`], but rather than failing when the subgraphs can't be
+/// composed, this returns the composition errors along with the supergraph
+/// that composition managed to put together regardless.  See
+/// [`Harmonizer::compose_best_effort`].
+#[cfg(feature = "js")]
+pub fn harmonize_best_effort(
+    service_list: ServiceList,
+) -> Result<PartialComposition, HarmonizerError> {
+    Harmonizer::new()?.compose_best_effort(service_list)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "js"))]
 mod tests {
     #[test]
     fn it_works() {
         use crate::{harmonize, ServiceDefinition};
 
-        insta::assert_snapshot!(harmonize(vec![
-            ServiceDefinition::new(
-                "users",
-                "undefined",
-                "
+        insta::assert_snapshot!(
+            harmonize(vec![
+                ServiceDefinition::new(
+                    "users",
+                    "undefined",
+                    "
             type User {
               id: ID
               name: String
@@ -272,11 +457,11 @@ mod tests {
               users: [User!]
             }
           "
-            ),
-            ServiceDefinition::new(
-                "movies",
-                "undefined",
-                "
+                ),
+                ServiceDefinition::new(
+                    "movies",
+                    "undefined",
+                    "
             type Movie {
               title: String
               name: String
@@ -290,8 +475,363 @@ mod tests {
               movies: [Movie!]
             }
           "
-            )
+                )
+            ])
+            .unwrap()
+            .supergraph_sdl
+        );
+    }
+
+    #[test]
+    fn it_composes_repeatedly_with_one_runtime() {
+        use crate::{harmonize, Harmonizer, ServiceDefinition};
+
+        let service_list = || {
+            vec![
+                ServiceDefinition::new("users", "undefined", "type Query { users: [String!] }"),
+                ServiceDefinition::new("movies", "undefined", "type Query { movies: [String!] }"),
+            ]
+        };
+
+        let mut harmonizer = Harmonizer::new().unwrap();
+        let first = harmonizer.compose(service_list()).unwrap();
+
+        // A failing composition in between must not affect the ones after it.
+        assert!(harmonizer
+            .compose(vec![ServiceDefinition::new(
+                "broken",
+                "undefined",
+                "type {"
+            )])
+            .is_err());
+
+        let second = harmonizer.compose(service_list()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, harmonize(service_list()).unwrap());
+    }
+
+    #[test]
+    fn it_reports_invalid_service_lists_without_panicking() {
+        use crate::{harmonize, HarmonizerError, ServiceDefinition};
+
+        let error = harmonize(vec![ServiceDefinition::new(
+            "",
+            "undefined",
+            "type Query { a: String }",
+        )])
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            HarmonizerError::JavaScript {
+                script: "do_compose.js",
+                ..
+            }
+        ));
+        assert!(error.is_internal());
+    }
+
+    #[test]
+    fn it_attributes_errors_to_subgraphs() {
+        use crate::{
+            harmonize, CompositionErrorCode, CompositionErrorLocation, HarmonizerError,
+            ServiceDefinition,
+        };
+
+        let errors = match harmonize(vec![
+            ServiceDefinition::new(
+                "users",
+                "undefined",
+                r#"
+            type User @key(fields: "id") {
+              id: ID!
+              name: String
+            }
+
+            type Query {
+              me: User
+            }
+          "#,
+            ),
+            ServiceDefinition::new(
+                "movies",
+                "undefined",
+                r#"
+            extend type User @key(fields: "id") {
+              id: ID! @external
+              name: String @external
+              favorites: [String!]
+            }
+          "#,
+            ),
+        ]) {
+            Err(HarmonizerError::Composition(errors)) => errors,
+            other => panic!("expected composition errors, got {:?}", other),
+        };
+
+        let external_unused = errors
+            .iter()
+            .find(|err| err.error_code() == Some(&CompositionErrorCode::ExternalUnused))
+            .expect("an EXTERNAL_UNUSED error");
+        assert_eq!(external_unused.subgraphs, vec!["movies".to_string()]);
+        assert_eq!(external_unused.coordinate.as_deref(), Some("User.name"));
+        assert_eq!(
+            external_unused.locations,
+            vec![CompositionErrorLocation {
+                subgraph: Some("movies".to_string()),
+                line: 4,
+                column: 28,
+            }]
+        );
+    }
+
+    #[test]
+    fn it_reports_hints_alongside_the_supergraph() {
+        use crate::{harmonize, CompositionHintCode, ServiceDefinition};
+
+        let output = harmonize(vec![
+            ServiceDefinition::new(
+                "users",
+                "undefined",
+                r#"
+            directive @auth on FIELD_DEFINITION
+
+            """A point on a map"""
+            type Point {
+              x: Int
+            }
+
+            type Orphan {
+              id: ID
+            }
+
+            type Query {
+              home: Point @auth
+            }
+          "#,
+            ),
+            ServiceDefinition::new(
+                "places",
+                "undefined",
+                r#"
+            """Coordinates"""
+            type Point {
+              x: Int
+            }
+
+            type Query {
+              places: [Point!]
+            }
+          "#,
+            ),
+        ])
+        .unwrap();
+
+        let hint = |code| {
+            output
+                .hints
+                .iter()
+                .find(|hint| hint.code == code)
+                .unwrap_or_else(|| panic!("a {} hint in {:?}", code, output.hints))
+        };
+
+        let description = hint(CompositionHintCode::InconsistentDescription);
+        assert_eq!(description.coordinate.as_deref(), Some("Point"));
+        assert_eq!(
+            description.subgraphs,
+            vec!["places".to_string(), "users".to_string()]
+        );
+
+        let unused = hint(CompositionHintCode::UnusedType);
+        assert_eq!(unused.coordinate.as_deref(), Some("Orphan"));
+
+        let dropped = hint(CompositionHintCode::DirectiveDropped);
+        assert_eq!(dropped.coordinate.as_deref(), Some("@auth"));
+        assert_eq!(dropped.subgraphs, vec!["users".to_string()]);
+    }
+
+    #[test]
+    fn it_composes_what_it_can_in_best_effort_mode() {
+        use crate::{harmonize_best_effort, CompositionErrorCode, ServiceDefinition};
+
+        let composition = harmonize_best_effort(vec![
+            ServiceDefinition::new(
+                "users",
+                "undefined",
+                r#"
+            type User @key(fields: "id") {
+              id: ID!
+              name: String
+            }
+
+            type Query {
+              me: User
+            }
+          "#,
+            ),
+            ServiceDefinition::new(
+                "movies",
+                "undefined",
+                r#"
+            extend type User @key(fields: "id") {
+              id: ID! @external
+              name: String @external
+              favorites: [String!]
+            }
+          "#,
+            ),
         ])
-        .unwrap());
+        .unwrap();
+
+        assert!(!composition.is_complete());
+        assert!(composition
+            .errors
+            .iter()
+            .any(|err| err.error_code() == Some(&CompositionErrorCode::ExternalUnused)));
+        let supergraph_sdl = composition.supergraph_sdl.expect("a partial supergraph");
+        assert!(supergraph_sdl.contains("favorites: [String!]"));
+
+        let composition = harmonize_best_effort(vec![ServiceDefinition::new(
+            "broken",
+            "undefined",
+            "type {",
+        )])
+        .unwrap();
+        assert_eq!(composition.errors.len(), 1);
+        assert_eq!(composition.supergraph_sdl, None);
+    }
+
+    #[test]
+    fn it_validates_a_subgraph_on_its_own() {
+        use crate::{validate_subgraph, CompositionErrorCode, ServiceDefinition};
+
+        let errors = validate_subgraph(ServiceDefinition::new(
+            "movies",
+            "undefined",
+            r#"
+            extend type User @key(fields: "id") {
+              id: ID! @external
+              favorites: [Movie!]
+            }
+
+            type Movie @key(fields: "title") {
+              title: String! @external
+            }
+
+            type Query {
+              movies: [Movie!]
+            }
+          "#,
+        ))
+        .unwrap();
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].error_code(),
+            Some(&CompositionErrorCode::ExternalUsedOnBase)
+        );
+        assert_eq!(errors[0].coordinate.as_deref(), Some("Movie.title"));
+    }
+
+    #[test]
+    fn it_normalizes_a_subgraph() {
+        use crate::{normalize, ServiceDefinition};
+
+        let normalized = normalize(ServiceDefinition::new(
+            "movies",
+            "undefined",
+            r#"
+            type User @key(fields: "id") @extends {
+              id: ID! @external
+              favorites: [String!]
+            }
+
+            type Query {
+              movies: [String!]
+            }
+          "#,
+        ))
+        .unwrap();
+
+        assert!(normalized.contains("extend type User @key(fields: \"id\")"));
+        assert!(normalized.contains("extend type Query"));
+        assert!(!normalized.contains("@extends"));
+    }
+
+    #[test]
+    fn it_plans_operations_against_the_supergraph() {
+        use crate::{
+            harmonize, plan, FetchNode, FlattenNode, HarmonizerError, PlanNode,
+            ResponsePathElement, SequenceNode, ServiceDefinition,
+        };
+
+        let supergraph_sdl = harmonize(vec![
+            ServiceDefinition::new(
+                "users",
+                "undefined",
+                r#"
+            type User @key(fields: "id") {
+              id: ID!
+              name: String
+            }
+
+            type Query {
+              users: [User!]
+            }
+          "#,
+            ),
+            ServiceDefinition::new(
+                "movies",
+                "undefined",
+                r#"
+            extend type User @key(fields: "id") {
+              id: ID! @external
+              favorites: [String!]
+            }
+          "#,
+            ),
+        ])
+        .unwrap()
+        .supergraph_sdl;
+
+        let query_plan = plan(&supergraph_sdl, "{ users { name favorites } }", None).unwrap();
+        let nodes = match query_plan.node {
+            Some(PlanNode::Sequence(SequenceNode { nodes })) => nodes,
+            other => panic!("expected a sequence, got {:?}", other),
+        };
+        assert_eq!(nodes.len(), 2);
+        assert!(matches!(
+            &nodes[0],
+            PlanNode::Fetch(FetchNode { service_name, requires: None, .. }) if service_name == "users"
+        ));
+        match &nodes[1] {
+            PlanNode::Flatten(FlattenNode { path, node }) => {
+                assert_eq!(
+                    path,
+                    &vec![
+                        ResponsePathElement::Key("users".to_string()),
+                        ResponsePathElement::Key("@".to_string())
+                    ]
+                );
+                assert!(matches!(
+                    &**node,
+                    PlanNode::Fetch(FetchNode { service_name, requires: Some(_), .. }) if service_name == "movies"
+                ));
+            }
+            other => panic!("expected a flatten, got {:?}", other),
+        }
+
+        let error = plan(&supergraph_sdl, "{ nope }", None).unwrap_err();
+        assert!(matches!(error, HarmonizerError::Planning(ref errors) if errors.len() == 1));
+        assert!(!error.is_internal());
+
+        let error = plan("type Query {", "{ users { name } }", None).unwrap_err();
+        match error {
+            HarmonizerError::Supergraph(ref errors) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].locations[0].to_string(), "1:13");
+            }
+            other => panic!("expected a supergraph error, got {:?}", other),
+        }
+        assert!(!error.is_internal());
     }
 }
@@ -0,0 +1,274 @@
+//! Composition of subgraphs into a supergraph natively within Rust, which is
+//! only available with the `native` cargo feature.
+//!
+//! This is a port of [`composeServices`] from `@apollo/federation`, along with
+//! the [`normalizeTypeDefs`] which `composeAndValidate` applies to every
+//! subgraph beforehand and the [`printSupergraphSdl`] which prints its result,
+//! and it is meant to produce exactly the same supergraph SDL as they do.  The
+//! validation which `composeAndValidate` does around `composeServices` has
+//! been ported as well, save for the rules which `graphql-js` itself applies
+//! (e.g., that every type which is referred to is defined).
+//!
+//! That, and the fact that native composition doesn't report any
+//! [`CompositionHint`]s yet, is why the crate root still composes with
+//! JavaScript whenever the `js` cargo feature is enabled, and native
+//! composition has entry points of its own: [`harmonize`] and
+//! [`validate_subgraph`].  Without the `js` feature, these are what the crate
+//! root exports in its place.
+//!
+//! [`CompositionHint`]: crate::CompositionHint
+//! [`composeServices`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/compose.ts
+//! [`normalizeTypeDefs`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/normalize.ts
+//! [`printSupergraphSdl`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/service/printSupergraphSdl.ts
+
+use crate::syntax::parse_type_defs;
+#[cfg(feature = "cache")]
+use crate::{CacheKey, CompositionCache};
+use crate::{
+    CompositionError, CompositionErrorCode, CompositionOutput, HarmonizerError, ServiceDefinition,
+    ServiceList,
+};
+
+mod collate;
+mod compose;
+mod normalize;
+mod print;
+mod validate;
+
+type Document = graphql_parser::schema::Document<'static, String>;
+
+/// A subgraph whose SDL has been parsed (and possibly normalized).
+#[derive(Clone)]
+struct Subgraph {
+    name: String,
+    document: Document,
+}
+
+/// Compose a [`ServiceList`] into a supergraph natively, like
+/// [`crate::harmonize`] does with JavaScript, returning the supergraph SDL.
+pub fn harmonize(service_list: ServiceList) -> Result<CompositionOutput, HarmonizerError> {
+    compose(&service_list)
+}
+
+/// Like [`harmonize`], but returns the composition from `cache` if the same
+/// [`ServiceList`] has been composed natively before.  Otherwise, the
+/// composition is added to `cache`.
+///
+/// This is only available with the `cache` cargo feature, too.
+#[cfg(feature = "cache")]
+pub fn harmonize_cached(
+    service_list: ServiceList,
+    cache: &dyn CompositionCache,
+) -> Result<CompositionOutput, HarmonizerError> {
+    let key = CacheKey::for_native_service_list(&service_list);
+    crate::cache::cached(key, service_list, cache, harmonize)
+}
+
+/// Compose `service_list` into a supergraph, or report the subgraphs which
+/// can't be parsed and the errors of validating them and their composition.
+pub(crate) fn compose(
+    service_list: &[ServiceDefinition],
+) -> Result<CompositionOutput, HarmonizerError> {
+    let subgraphs = parse_subgraphs(service_list).map_err(HarmonizerError::Composition)?;
+
+    let mut errors = validate::validate_services_before_normalization(&subgraphs);
+    let normalized = normalize_subgraphs(&subgraphs);
+    errors.extend(validate::validate_services_before_composition(&normalized));
+    let (schema, composition_errors) = compose::compose_services(&normalized);
+    errors.extend(composition_errors);
+    errors.extend(validate::validate_composed_schema(&schema, &subgraphs));
+    if !errors.is_empty() {
+        return Err(HarmonizerError::Composition(errors));
+    }
+
+    Ok(CompositionOutput {
+        supergraph_sdl: print::print_supergraph_sdl(&schema, service_list),
+        hints: Vec::new(),
+    })
+}
+
+/// Validate a single subgraph natively, the way [`harmonize`] would validate
+/// it short of the rules which need the whole supergraph, like
+/// [`crate::validate_subgraph`] does with JavaScript.
+pub fn validate_subgraph(
+    service: ServiceDefinition,
+) -> Result<Vec<CompositionError>, HarmonizerError> {
+    let subgraphs = match parse_subgraphs(std::slice::from_ref(&service)) {
+        Ok(subgraphs) => subgraphs,
+        Err(errors) => return Ok(errors),
+    };
+
+    let mut errors = validate::validate_services_before_normalization(&subgraphs);
+    let normalized = normalize_subgraphs(&subgraphs);
+    errors.extend(validate::validate_services_before_composition(&normalized));
+    let (_, composition_errors) = compose::compose_services(&normalized);
+    // A subgraph on its own may well extend types which others define.
+    errors.extend(composition_errors.into_iter().filter(|error| {
+        !matches!(
+            error.error_code(),
+            Some(CompositionErrorCode::ExtensionWithNoBase)
+        )
+    }));
+    Ok(errors)
+}
+
+/// Parse the SDL of every subgraph, or report the ones which can't be parsed.
+fn parse_subgraphs(
+    service_list: &[ServiceDefinition],
+) -> Result<Vec<Subgraph>, Vec<CompositionError>> {
+    let mut subgraphs = Vec::with_capacity(service_list.len());
+    let mut errors = Vec::new();
+    for service in service_list {
+        match parse_type_defs(service) {
+            Ok(document) => subgraphs.push(Subgraph {
+                name: service.name.clone(),
+                document,
+            }),
+            Err(syntax_errors) => errors.extend(syntax_errors),
+        }
+    }
+    if errors.is_empty() {
+        Ok(subgraphs)
+    } else {
+        Err(errors)
+    }
+}
+
+fn normalize_subgraphs(subgraphs: &[Subgraph]) -> Vec<Subgraph> {
+    subgraphs
+        .iter()
+        .map(|subgraph| Subgraph {
+            name: subgraph.name.clone(),
+            document: normalize::normalize_type_defs(subgraph.document.clone()),
+        })
+        .collect()
+}
+
+/// The subgraphs of the integration test suite which `printSupergraphSdl`
+/// snapshots the supergraph of.
+#[cfg(test)]
+pub(crate) fn fixtures() -> crate::ServiceList {
+    vec![
+        fixture(
+            "accounts",
+            include_str!("../../federation-integration-testsuite-js/src/fixtures/accounts.ts"),
+        ),
+        fixture(
+            "books",
+            include_str!("../../federation-integration-testsuite-js/src/fixtures/books.ts"),
+        ),
+        fixture(
+            "documents",
+            include_str!("../../federation-integration-testsuite-js/src/fixtures/documents.ts"),
+        ),
+        fixture(
+            "inventory",
+            include_str!("../../federation-integration-testsuite-js/src/fixtures/inventory.ts"),
+        ),
+        fixture(
+            "product",
+            include_str!("../../federation-integration-testsuite-js/src/fixtures/product.ts"),
+        ),
+        fixture(
+            "reviews",
+            include_str!("../../federation-integration-testsuite-js/src/fixtures/reviews.ts"),
+        ),
+    ]
+}
+
+/// A subgraph of the integration test suite, whose `typeDefs` are the
+/// (uninterpolated) contents of a `gql` template literal.
+#[cfg(test)]
+fn fixture(name: &str, source: &str) -> ServiceDefinition {
+    let type_defs = source
+        .split("gql`")
+        .nth(1)
+        .and_then(|rest| rest.split("`;").next())
+        .expect("fixtures export their typeDefs");
+    ServiceDefinition::new(name, format!("https://{}.api.com", name), type_defs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compose, fixtures, validate_subgraph};
+    use crate::{CompositionErrorCode, HarmonizerError, ServiceDefinition};
+
+    /// The supergraph SDL which `printSupergraphSdl` prints for the fixtures,
+    /// as it's snapshotted by its own tests.
+    fn inline_snapshot() -> String {
+        let test =
+            include_str!("../../federation-js/src/service/__tests__/printSupergraphSdl.test.ts");
+        let snapshot = test
+            .split("prints a fully composed schema correctly")
+            .nth(1)
+            .and_then(|rest| rest.split("toMatchInlineSnapshot(`\n").nth(1))
+            .and_then(|rest| rest.split("\n    `);").next())
+            .expect("the test snapshots the supergraph SDL");
+        let snapshot = snapshot
+            .lines()
+            .map(|line| line.get(6..).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .replace("\\\\\"", "\"");
+        snapshot[1..snapshot.len() - 1].to_string()
+    }
+
+    #[test]
+    fn it_composes_the_fixtures_like_javascript() {
+        let output = compose(&fixtures()).unwrap();
+        assert_eq!(output.supergraph_sdl, inline_snapshot());
+        assert!(output.hints.is_empty());
+    }
+
+    #[test]
+    fn it_reports_syntax_errors_by_subgraph() {
+        let error = compose(&[ServiceDefinition::new(
+            "users",
+            "undefined",
+            "type Query { users: [String!] ",
+        )])
+        .unwrap_err();
+
+        let errors = match error {
+            HarmonizerError::Composition(errors) => errors,
+            _ => panic!("expected composition errors, got {:?}", error),
+        };
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0]
+            .message
+            .as_deref()
+            .unwrap_or_default()
+            .starts_with("Syntax Error: "));
+        assert_eq!(errors[0].subgraphs, vec!["users".to_string()]);
+    }
+
+    #[test]
+    fn it_validates_a_subgraph_on_its_own() {
+        let errors = validate_subgraph(ServiceDefinition::new(
+            "movies",
+            "undefined",
+            r#"
+            extend type User @key(fields: "id") {
+              id: ID! @external
+              favorites: [Movie!]
+            }
+
+            type Movie @key(fields: "title") {
+              title: String! @external
+            }
+
+            type Query {
+              movies: [Movie!]
+            }
+          "#,
+        ))
+        .unwrap();
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].error_code(),
+            Some(&CompositionErrorCode::ExternalUsedOnBase)
+        );
+        assert_eq!(errors[0].coordinate.as_deref(), Some("Movie.title"));
+    }
+}
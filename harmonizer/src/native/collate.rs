@@ -0,0 +1,85 @@
+//! The order in which `printSupergraphSdl` prints types and subgraphs, which
+//! it sorts with `String.prototype.localeCompare`.  Within V8 that compares
+//! strings using the root collation of ICU, which this approximates well
+//! enough for the names of GraphQL types and subgraphs: whitespace and
+//! punctuation come first, then digits, then letters regardless of their
+//! case, with lowercase before uppercase when that's the only difference.
+
+use std::cmp::Ordering;
+
+/// The punctuation and symbols of ASCII, in the order the root collation puts
+/// them.
+const PUNCTUATION: &str = "_-,;:!?.'\"()[]{}@*/\\&#%`^+<=>|~$";
+
+pub(super) fn locale_compare(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .map(primary_weight)
+        .cmp(b.chars().map(primary_weight))
+        .then_with(|| {
+            a.chars()
+                .map(tertiary_weight)
+                .cmp(b.chars().map(tertiary_weight))
+        })
+        .then_with(|| a.cmp(b))
+}
+
+fn primary_weight(c: char) -> u32 {
+    if c.is_whitespace() {
+        0
+    } else if let Some(index) = PUNCTUATION.find(c) {
+        1 + index as u32
+    } else if let Some(digit) = c.to_digit(10) {
+        0x100 + digit
+    } else if c.is_ascii_alphabetic() {
+        0x200 + (c.to_ascii_lowercase() as u32 - 'a' as u32)
+    } else {
+        0x300 + c as u32
+    }
+}
+
+fn tertiary_weight(c: char) -> u8 {
+    if c.is_uppercase() {
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::locale_compare;
+
+    #[test]
+    fn it_sorts_like_locale_compare() {
+        let mut names = vec![
+            "join__Graph",
+            "User",
+            "_Entity",
+            "Book",
+            "book",
+            "join__FieldSet",
+            "Query",
+            "Library2",
+            "Library10",
+            "UserMetadata",
+            "User_Name",
+        ];
+        names.sort_by(|a, b| locale_compare(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "_Entity",
+                "book",
+                "Book",
+                "join__FieldSet",
+                "join__Graph",
+                "Library10",
+                "Library2",
+                "Query",
+                "User",
+                "User_Name",
+                "UserMetadata",
+            ]
+        );
+    }
+}
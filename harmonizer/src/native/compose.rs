@@ -0,0 +1,914 @@
+//! A port of [`composeServices`], which merges the type definitions and
+//! extensions of every subgraph into the schema of the supergraph, and
+//! records which subgraph is responsible for each of its types and fields
+//! (like `addFederationMetadataToSchemaNodes` does).  The definitions and the
+//! extensions are checked by the [SDL rules](super::validate::sdl) on the way.
+//!
+//! [`composeServices`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/compose.ts
+
+use super::print::print_field_set;
+use super::validate::sdl;
+use super::Subgraph;
+use crate::syntax::split_syntax_error;
+use crate::{CompositionError, CompositionErrorLocation};
+use graphql_parser::query::{self, OperationDefinition};
+use graphql_parser::schema::{
+    Definition, Directive, DirectiveDefinition, DirectiveLocation, Field, InputValue, ObjectType,
+    TypeDefinition, TypeExtension, Value,
+};
+use std::collections::{HashMap, HashSet};
+
+type TypeDefinition_ = TypeDefinition<'static, String>;
+type TypeExtension_ = TypeExtension<'static, String>;
+type DirectiveDefinition_ = DirectiveDefinition<'static, String>;
+type Field_ = Field<'static, String>;
+
+/// The scalars which are defined by the GraphQL specification, and so can't
+/// be (re)defined by subgraphs.
+pub(super) const SPECIFIED_SCALARS: &[&str] = &["String", "Int", "Float", "Boolean", "ID"];
+
+/// The composed schema of the supergraph, along with what federation knows
+/// about its types and fields.
+#[derive(Debug, Default)]
+pub(super) struct Schema {
+    /// Every type of the schema, with their fields, arguments, enum values and
+    /// so on sorted like `lexicographicSortSchema` sorts them.
+    pub(super) types: HashMap<String, TypeDefinition_>,
+    /// The executable directives of the subgraphs, sorted by name.
+    pub(super) directives: Vec<DirectiveDefinition_>,
+    /// Every subgraph's definition of each of the `directives`, in the order
+    /// of the subgraphs, like the `FederationDirective` which
+    /// `composeServices` attaches to them.
+    pub(super) directive_definitions: HashMap<String, Vec<(String, DirectiveDefinition_)>>,
+    pub(super) type_metadata: HashMap<String, TypeMetadata>,
+    /// Keyed by the name of the type and then the name of the field.
+    pub(super) field_metadata: HashMap<(String, String), FieldMetadata>,
+}
+
+/// What federation knows about a type of the supergraph, like the
+/// `FederationType` which `composeServices` attaches to it.
+#[derive(Debug, Default)]
+pub(super) struct TypeMetadata {
+    /// The subgraph which owns the type, which is `None` for value types
+    /// (i.e., types which are defined identically by several subgraphs).
+    pub(super) service_name: Option<String>,
+    pub(super) is_value_type: bool,
+    /// The field sets of the `@key`s of an entity, by subgraph, in the order
+    /// the subgraphs first declared them.
+    pub(super) keys: Option<Vec<(String, Vec<String>)>>,
+    /// The fields which subgraphs extend the type with as `@external`, by
+    /// subgraph.  Composition strips them from the extensions, since they
+    /// belong to another subgraph, but validation still needs them.
+    pub(super) externals: Vec<(String, Vec<Field_>)>,
+}
+
+/// What federation knows about a field of the supergraph, like the
+/// `FederationField` which `composeServices` attaches to it.  Fields of the
+/// subgraph which owns their type have none.
+#[derive(Debug, Default)]
+pub(super) struct FieldMetadata {
+    pub(super) service_name: Option<String>,
+    pub(super) requires: Option<String>,
+    pub(super) provides: Option<String>,
+}
+
+impl Schema {
+    pub(super) fn has_type(&self, name: &str) -> bool {
+        self.types.contains_key(name)
+    }
+}
+
+/// Where a type is defined and which subgraphs extend it with which fields.
+#[derive(Debug, Default)]
+struct TypeToService {
+    owning_service: Option<String>,
+    extension_fields: HashMap<String, String>,
+}
+
+/// The definitions and extensions of every subgraph, by type, along with
+/// what federation needs to know about them.
+///
+/// Definitions and extensions are paired with the subgraph they came from,
+/// which is the `serviceName` that `composeServices` attaches to them (and
+/// which the `Query` type it makes up for subgraphs which only extend it
+/// doesn't have).
+#[derive(Debug, Default)]
+struct Maps {
+    type_to_service: HashMap<String, TypeToService>,
+    type_definitions: HashMap<String, Vec<(Option<String>, TypeDefinition_)>>,
+    type_extensions: HashMap<String, Vec<(String, TypeExtension_)>>,
+    /// Every subgraph's definition of each executable directive.
+    directive_definitions: HashMap<String, Vec<(String, DirectiveDefinition_)>>,
+    /// The fields stripped from the extensions of each type for being
+    /// `@external`, by subgraph.
+    externals: HashMap<String, Vec<(String, Vec<Field_>)>>,
+    keys: HashMap<String, Vec<(String, Vec<String>)>>,
+    value_types: HashSet<String>,
+    errors: Vec<CompositionError>,
+}
+
+/// Compose the subgraphs into a schema, along with the errors which prevent
+/// it from being a supergraph.  Like `composeServices`, this returns the
+/// schema either way, since the errors of the composed schema are still
+/// worth [validating](super::validate::validate_composed_schema) for.
+pub(super) fn compose_services(subgraphs: &[Subgraph]) -> (Schema, Vec<CompositionError>) {
+    let mut maps = build_maps(subgraphs);
+    let mut schema = build_schema(&mut maps);
+    add_federation_metadata(&mut schema, &mut maps);
+    (schema, maps.errors)
+}
+
+fn build_maps(subgraphs: &[Subgraph]) -> Maps {
+    let mut maps = Maps::default();
+
+    for subgraph in subgraphs {
+        let service_name = &subgraph.name;
+        for definition in &subgraph.document.definitions {
+            match definition.clone() {
+                Definition::TypeDefinition(definition) => {
+                    if let TypeDefinition::Object(object) = &definition {
+                        maps.add_keys(service_name, &object.name, &object.directives);
+                    }
+
+                    let type_name = type_definition_name(&definition).to_string();
+                    maps.type_to_service
+                        .entry(type_name.clone())
+                        .or_default()
+                        .owning_service = Some(service_name.clone());
+
+                    let definitions = maps.type_definitions.entry(type_name.clone()).or_default();
+                    if let Some((_, last)) = definitions.last() {
+                        if type_nodes_are_equivalent(last, &definition) {
+                            maps.value_types.insert(type_name);
+                        }
+                    }
+                    definitions.push((Some(service_name.clone()), definition));
+                }
+                Definition::TypeExtension(mut extension) => {
+                    // Fields which are `@external` belong to another subgraph.
+                    match &mut extension {
+                        TypeExtension::Object(object) => {
+                            maps.strip_externals(service_name, &object.name, &mut object.fields);
+                            maps.add_keys(service_name, &object.name, &object.directives);
+                        }
+                        TypeExtension::Interface(interface) => {
+                            maps.strip_externals(
+                                service_name,
+                                &interface.name,
+                                &mut interface.fields,
+                            );
+                        }
+                        _ => {}
+                    }
+
+                    let extended_names: Vec<&String> = match &extension {
+                        TypeExtension::Object(object) => {
+                            object.fields.iter().map(|field| &field.name).collect()
+                        }
+                        TypeExtension::InputObject(input) => {
+                            input.fields.iter().map(|field| &field.name).collect()
+                        }
+                        TypeExtension::Enum(enum_type) => {
+                            enum_type.values.iter().map(|value| &value.name).collect()
+                        }
+                        _ => Vec::new(),
+                    };
+                    let type_name = type_extension_name(&extension).to_string();
+                    if !extended_names.is_empty()
+                        || matches!(
+                            extension,
+                            TypeExtension::Object(_)
+                                | TypeExtension::InputObject(_)
+                                | TypeExtension::Enum(_)
+                        )
+                    {
+                        let extension_fields = &mut maps
+                            .type_to_service
+                            .entry(type_name.clone())
+                            .or_default()
+                            .extension_fields;
+                        for name in extended_names {
+                            extension_fields.insert(name.clone(), service_name.clone());
+                        }
+                    }
+
+                    maps.type_extensions
+                        .entry(type_name)
+                        .or_default()
+                        .push((service_name.clone(), extension));
+                }
+                Definition::DirectiveDefinition(mut directive) => {
+                    // Only executable directives make it into the supergraph.
+                    directive.locations.retain(is_executable_location);
+                    if directive.locations.is_empty() {
+                        continue;
+                    }
+                    let definitions = maps
+                        .directive_definitions
+                        .entry(directive.name.clone())
+                        .or_default();
+                    match definitions
+                        .iter_mut()
+                        .find(|(service, _)| service == service_name)
+                    {
+                        Some((_, definition)) => *definition = directive,
+                        None => definitions.push((service_name.clone(), directive)),
+                    }
+                }
+                Definition::SchemaDefinition(_) => {}
+            }
+        }
+    }
+
+    // The root operation types of the subgraphs are all extensions, which
+    // need something to extend.
+    if !maps.type_definitions.contains_key("Query") {
+        maps.type_definitions.insert(
+            "Query".to_string(),
+            vec![(None, empty_object_type("Query"))],
+        );
+    }
+    if maps.type_extensions.contains_key("Mutation")
+        && !maps.type_definitions.contains_key("Mutation")
+    {
+        maps.type_definitions.insert(
+            "Mutation".to_string(),
+            vec![(None, empty_object_type("Mutation"))],
+        );
+    }
+
+    maps
+}
+
+impl Maps {
+    /// Strip the `@external` fields from an extension of a type, keeping
+    /// them aside for validation.
+    fn strip_externals(&mut self, service_name: &str, type_name: &str, fields: &mut Vec<Field_>) {
+        let (externals, others) = fields.drain(..).partition(is_external);
+        *fields = others;
+        if externals.is_empty() {
+            return;
+        }
+
+        let by_service = self.externals.entry(type_name.to_string()).or_default();
+        match by_service
+            .iter_mut()
+            .find(|(service, _)| service == service_name)
+        {
+            Some((_, fields)) => fields.extend(externals),
+            None => by_service.push((service_name.to_string(), externals)),
+        }
+    }
+
+    fn add_keys(
+        &mut self,
+        service_name: &str,
+        type_name: &str,
+        directives: &[Directive<'static, String>],
+    ) {
+        for directive in directives
+            .iter()
+            .filter(|directive| directive.name == "key")
+        {
+            let fields = match directive.arguments.first() {
+                Some((_, Value::String(fields))) => fields,
+                _ => continue,
+            };
+            let field_set = match parse_field_set(fields) {
+                Ok(field_set) => field_set,
+                Err(err) => {
+                    self.errors.push(err.into_error(service_name, type_name));
+                    continue;
+                }
+            };
+
+            let keys = self.keys.entry(type_name.to_string()).or_default();
+            match keys.iter_mut().find(|(service, _)| service == service_name) {
+                Some((_, field_sets)) => field_sets.push(field_set),
+                None => keys.push((service_name.to_string(), vec![field_set])),
+            }
+        }
+    }
+}
+
+/// Extend the definitions of every type with their extensions, like
+/// `buildSchemaFromDefinitionsAndExtensions` does, and sort the result.
+fn build_schema(maps: &mut Maps) -> Schema {
+    let mut schema = Schema::default();
+
+    maps.errors
+        .extend(sdl::validate_type_definitions(&maps.type_definitions));
+    let mut definers = HashMap::new();
+    for (name, definitions) in maps.type_definitions.drain() {
+        if SPECIFIED_SCALARS.contains(&name.as_str()) {
+            continue;
+        }
+        let (service_name, definition) = merge_definitions(definitions);
+        definers.insert(name.clone(), service_name);
+        schema.types.insert(name, definition);
+    }
+
+    maps.errors.extend(sdl::validate_type_extensions(
+        &maps.type_extensions,
+        &schema.types,
+        &definers,
+    ));
+    for (name, extensions) in maps.type_extensions.drain() {
+        // Extensions of types which no subgraph defines are dropped.
+        if let Some(definition) = schema.types.get_mut(&name) {
+            for (_, extension) in extensions {
+                extend(definition, extension);
+            }
+        }
+    }
+
+    schema.directive_definitions = std::mem::take(&mut maps.directive_definitions);
+    schema.directives = schema
+        .directive_definitions
+        .values()
+        .map(|definitions| definitions[0].1.clone())
+        .collect();
+
+    lexicographic_sort(&mut schema);
+    schema
+}
+
+/// The definition a type ends up with when several subgraphs define it (and
+/// the subgraph it came from): the last one, unless any of them implement
+/// interfaces, in which case it's the first one, implementing every one of
+/// those interfaces.
+fn merge_definitions(
+    mut definitions: Vec<(Option<String>, TypeDefinition_)>,
+) -> (Option<String>, TypeDefinition_) {
+    let mut interfaces: Vec<String> = Vec::new();
+    for (_, definition) in &definitions {
+        let implements = match definition {
+            TypeDefinition::Object(object) => &object.implements_interfaces,
+            TypeDefinition::Interface(interface) => &interface.implements_interfaces,
+            _ => continue,
+        };
+        for interface in implements {
+            if !interfaces.contains(interface) {
+                interfaces.push(interface.clone());
+            }
+        }
+    }
+
+    if interfaces.is_empty() {
+        return definitions.pop().expect("every type has a definition");
+    }
+    let mut first = definitions.swap_remove(0);
+    match &mut first.1 {
+        TypeDefinition::Object(object) => object.implements_interfaces = interfaces,
+        TypeDefinition::Interface(interface) => interface.implements_interfaces = interfaces,
+        _ => {}
+    }
+    first
+}
+
+/// Apply an extension to a type the way `extendSchema` does: fields (and
+/// enum values) which are already there are replaced, and everything else is
+/// added.
+fn extend(definition: &mut TypeDefinition_, extension: TypeExtension_) {
+    match (definition, extension) {
+        (TypeDefinition::Object(object), TypeExtension::Object(extension)) => {
+            object
+                .implements_interfaces
+                .extend(extension.implements_interfaces);
+            merge_by_name(&mut object.fields, extension.fields, |field| &field.name);
+        }
+        (TypeDefinition::Interface(interface), TypeExtension::Interface(extension)) => {
+            interface
+                .implements_interfaces
+                .extend(extension.implements_interfaces);
+            merge_by_name(&mut interface.fields, extension.fields, |field| &field.name);
+        }
+        (TypeDefinition::Union(union), TypeExtension::Union(extension)) => {
+            union.types.extend(extension.types);
+        }
+        (TypeDefinition::Enum(enum_type), TypeExtension::Enum(extension)) => {
+            merge_by_name(&mut enum_type.values, extension.values, |value| &value.name);
+        }
+        (TypeDefinition::InputObject(input), TypeExtension::InputObject(extension)) => {
+            merge_by_name(&mut input.fields, extension.fields, |field| &field.name);
+        }
+        _ => {}
+    }
+}
+
+fn merge_by_name<T, F>(items: &mut Vec<T>, additions: Vec<T>, name: F)
+where
+    F: Fn(&T) -> &String,
+{
+    for addition in additions {
+        match items.iter().position(|item| name(item) == name(&addition)) {
+            Some(index) => items[index] = addition,
+            None => items.push(addition),
+        }
+    }
+}
+
+/// Sort the schema like `lexicographicSortSchema` does, which (unlike the
+/// printer, which sorts the types themselves) compares names by code unit.
+fn lexicographic_sort(schema: &mut Schema) {
+    fn sort_arguments(arguments: &mut [InputValue<'static, String>]) {
+        arguments.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    fn sort_fields(fields: &mut [Field_]) {
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+        for field in fields {
+            sort_arguments(&mut field.arguments);
+        }
+    }
+
+    for definition in schema.types.values_mut() {
+        match definition {
+            TypeDefinition::Object(object) => {
+                // Several definitions and extensions may implement the same
+                // interface, which it should only implement once.
+                let mut seen = HashSet::new();
+                object
+                    .implements_interfaces
+                    .retain(|interface| seen.insert(interface.clone()));
+                object.implements_interfaces.sort();
+                sort_fields(&mut object.fields);
+            }
+            TypeDefinition::Interface(interface) => {
+                interface.implements_interfaces.sort();
+                sort_fields(&mut interface.fields);
+            }
+            TypeDefinition::Union(union) => union.types.sort(),
+            TypeDefinition::Enum(enum_type) => {
+                enum_type.values.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+            TypeDefinition::InputObject(input) => sort_arguments(&mut input.fields),
+            TypeDefinition::Scalar(_) => {}
+        }
+    }
+
+    schema.directives.sort_by(|a, b| a.name.cmp(&b.name));
+    for directive in &mut schema.directives {
+        sort_arguments(&mut directive.arguments);
+    }
+}
+
+/// Record which subgraph owns each type, the keys of the entities, and which
+/// subgraphs resolve the fields that other subgraphs add to entities, like
+/// `addFederationMetadataToSchemaNodes` does.
+fn add_federation_metadata(schema: &mut Schema, maps: &mut Maps) {
+    for (type_name, type_to_service) in maps.type_to_service.drain() {
+        let definition = match schema.types.get(&type_name) {
+            Some(definition) => definition,
+            None => continue,
+        };
+
+        let is_value_type = maps.value_types.contains(&type_name);
+        let service_name = if is_value_type {
+            None
+        } else {
+            type_to_service.owning_service
+        };
+
+        if let TypeDefinition::Object(object) = definition {
+            for field in &object.fields {
+                let provides = match field_set_argument(&field.directives, "provides") {
+                    Some(Ok(provides)) => provides,
+                    Some(Err(err)) => {
+                        let coordinate = format!("{}.{}", type_name, field.name);
+                        let service = service_name.as_deref().unwrap_or_default();
+                        maps.errors.push(err.into_error(service, &coordinate));
+                        continue;
+                    }
+                    None => continue,
+                };
+                schema.field_metadata.insert(
+                    (type_name.clone(), field.name.clone()),
+                    FieldMetadata {
+                        service_name: service_name.clone(),
+                        provides: Some(provides),
+                        ..FieldMetadata::default()
+                    },
+                );
+            }
+
+            for (field_name, extending_service) in type_to_service.extension_fields {
+                let field = match object.fields.iter().find(|field| field.name == field_name) {
+                    Some(field) => field,
+                    None => continue,
+                };
+                let requires = match field_set_argument(&field.directives, "requires") {
+                    Some(Ok(requires)) => Some(requires),
+                    Some(Err(err)) => {
+                        let coordinate = format!("{}.{}", type_name, field_name);
+                        maps.errors
+                            .push(err.into_error(&extending_service, &coordinate));
+                        None
+                    }
+                    None => None,
+                };
+                let metadata = schema
+                    .field_metadata
+                    .entry((type_name.clone(), field_name))
+                    .or_default();
+                metadata.service_name = Some(extending_service);
+                if requires.is_some() {
+                    metadata.requires = requires;
+                }
+            }
+        }
+
+        let keys = maps.keys.remove(&type_name);
+        schema.type_metadata.insert(
+            type_name,
+            TypeMetadata {
+                service_name,
+                is_value_type,
+                keys,
+                externals: Vec::new(),
+            },
+        );
+    }
+
+    for (type_name, externals) in maps.externals.drain() {
+        if schema.has_type(&type_name) {
+            schema.type_metadata.entry(type_name).or_default().externals = externals;
+        }
+    }
+}
+
+/// The field set of the first `@provides` or `@requires` directive among
+/// `directives`, if it has one.
+fn field_set_argument(
+    directives: &[Directive<'static, String>],
+    name: &str,
+) -> Option<Result<String, FieldSetError>> {
+    let directive = directives.iter().find(|directive| directive.name == name)?;
+    match directive.arguments.first() {
+        Some((_, Value::String(fields))) => Some(parse_field_set(fields)),
+        _ => None,
+    }
+}
+
+/// Why a field set couldn't be parsed by [`parse_field_set`].
+#[derive(Debug)]
+struct FieldSetError {
+    message: String,
+    location: Option<graphql_parser::Pos>,
+}
+
+impl FieldSetError {
+    /// A [`CompositionError`] about the field set of a directive on the type
+    /// or field at `coordinate` within `subgraph`.
+    fn into_error(self, subgraph: &str, coordinate: &str) -> CompositionError {
+        CompositionError {
+            message: Some(self.message),
+            extensions: None,
+            locations: self
+                .location
+                .map(|position| CompositionErrorLocation {
+                    subgraph: None,
+                    line: position.line,
+                    column: position.column,
+                })
+                .into_iter()
+                .collect(),
+            subgraphs: if subgraph.is_empty() {
+                Vec::new()
+            } else {
+                vec![subgraph.to_string()]
+            },
+            coordinate: Some(coordinate.to_string()),
+        }
+    }
+}
+
+/// Parse a field set like `parseSelections` does, and print it again like
+/// `printFieldSet` does, which is all the supergraph needs of it.
+fn parse_field_set(source: &str) -> Result<String, FieldSetError> {
+    let document = query::parse_query::<String>(&format!("{{{}}}", source))
+        .map_err(|err| {
+            let (location, description) = split_syntax_error(&err.to_string());
+            FieldSetError {
+                message: format!("Syntax Error: {}", description),
+                location,
+            }
+        })?
+        .into_static();
+
+    match document.definitions.as_slice() {
+        [query::Definition::Operation(OperationDefinition::SelectionSet(selection_set))] => {
+            Ok(print_field_set(&selection_set.items))
+        }
+        _ => Err(FieldSetError {
+            message: "Unexpected } found in FieldSet".to_string(),
+            location: None,
+        }),
+    }
+}
+
+/// Whether two definitions of a type are the same, which makes it a value
+/// type, like `typeNodesAreEquivalent` does: they must be of the same kind,
+/// with fields, arguments and input fields of the same types and the same
+/// members if they're unions.
+fn type_nodes_are_equivalent(first: &TypeDefinition_, second: &TypeDefinition_) -> bool {
+    let diff = diff_type_nodes(first, second);
+    diff.kinds.is_none()
+        && diff.fields.is_empty()
+        && diff.input_values.is_empty()
+        && diff.union_types.is_empty()
+}
+
+/// How two definitions of a type differ, like the result of `diffTypeNodes`.
+#[derive(Debug, Default)]
+pub(super) struct TypeNodeDiff {
+    /// The kinds of both definitions, if they're of different kinds.
+    pub(super) kinds: Option<(&'static str, &'static str)>,
+    /// The (printed) types of the fields which only one of the definitions
+    /// has, or which both have with different types, in the order they were
+    /// first seen in.
+    pub(super) fields: Vec<(String, Vec<String>)>,
+    /// Likewise for arguments and input fields.
+    pub(super) input_values: Vec<(String, Vec<String>)>,
+    /// The members which only one of the definitions (if they're unions) has.
+    pub(super) union_types: Vec<String>,
+}
+
+pub(super) fn diff_type_nodes(first: &TypeDefinition_, second: &TypeDefinition_) -> TypeNodeDiff {
+    // Like `diffTypeNodes`, every field (and input value) is entered into the
+    // diff when it is seen first, and removed again when it is seen with the
+    // same type, so only the differences remain.
+    fn toggle(diff: &mut Vec<(String, Vec<String>)>, name: &str, ty: String) {
+        match diff.iter().position(|(seen, _)| seen == name) {
+            None => diff.push((name.to_string(), vec![ty])),
+            Some(index) if diff[index].1[0] == ty => {
+                diff.remove(index);
+            }
+            Some(index) => diff[index].1.push(ty),
+        }
+    }
+
+    let mut diff = TypeNodeDiff::default();
+    for definition in &[first, second] {
+        let (definition_fields, definition_inputs) = match definition {
+            TypeDefinition::Object(object) => (object.fields.as_slice(), &[][..]),
+            TypeDefinition::Interface(interface) => (interface.fields.as_slice(), &[][..]),
+            TypeDefinition::InputObject(input) => (&[][..], input.fields.as_slice()),
+            TypeDefinition::Union(union) => {
+                for member in &union.types {
+                    match diff.union_types.iter().position(|seen| seen == member) {
+                        Some(index) => {
+                            diff.union_types.remove(index);
+                        }
+                        None => diff.union_types.push(member.clone()),
+                    }
+                }
+                continue;
+            }
+            TypeDefinition::Scalar(_) | TypeDefinition::Enum(_) => continue,
+        };
+        for field in definition_fields {
+            toggle(&mut diff.fields, &field.name, field.field_type.to_string());
+            for argument in &field.arguments {
+                toggle(
+                    &mut diff.input_values,
+                    &argument.name,
+                    argument.value_type.to_string(),
+                );
+            }
+        }
+        for input in definition_inputs {
+            toggle(
+                &mut diff.input_values,
+                &input.name,
+                input.value_type.to_string(),
+            );
+        }
+    }
+
+    let kinds = (definition_kind(first), definition_kind(second));
+    if kinds.0 != kinds.1 {
+        diff.kinds = Some(kinds);
+    }
+    diff
+}
+
+/// The `Kind` of a definition's AST node within `graphql-js`, which some
+/// messages mention.
+pub(super) fn definition_kind(definition: &TypeDefinition_) -> &'static str {
+    match definition {
+        TypeDefinition::Scalar(_) => "ScalarTypeDefinition",
+        TypeDefinition::Object(_) => "ObjectTypeDefinition",
+        TypeDefinition::Interface(_) => "InterfaceTypeDefinition",
+        TypeDefinition::Union(_) => "UnionTypeDefinition",
+        TypeDefinition::Enum(_) => "EnumTypeDefinition",
+        TypeDefinition::InputObject(_) => "InputObjectTypeDefinition",
+    }
+}
+
+fn is_external(field: &Field_) -> bool {
+    field
+        .directives
+        .iter()
+        .any(|directive| directive.name == "external")
+}
+
+fn is_executable_location(location: &DirectiveLocation) -> bool {
+    matches!(
+        location,
+        DirectiveLocation::Query
+            | DirectiveLocation::Mutation
+            | DirectiveLocation::Subscription
+            | DirectiveLocation::Field
+            | DirectiveLocation::FragmentDefinition
+            | DirectiveLocation::FragmentSpread
+            | DirectiveLocation::InlineFragment
+            | DirectiveLocation::VariableDefinition
+    )
+}
+
+fn empty_object_type(name: &str) -> TypeDefinition_ {
+    TypeDefinition::Object(ObjectType::new(name.to_string()))
+}
+
+pub(super) fn type_definition_name(definition: &TypeDefinition_) -> &str {
+    match definition {
+        TypeDefinition::Scalar(scalar) => &scalar.name,
+        TypeDefinition::Object(object) => &object.name,
+        TypeDefinition::Interface(interface) => &interface.name,
+        TypeDefinition::Union(union) => &union.name,
+        TypeDefinition::Enum(enum_type) => &enum_type.name,
+        TypeDefinition::InputObject(input) => &input.name,
+    }
+}
+
+pub(super) fn type_extension_name<'e>(extension: &'e TypeExtension<'static, String>) -> &'e str {
+    match extension {
+        TypeExtension::Scalar(scalar) => &scalar.name,
+        TypeExtension::Object(object) => &object.name,
+        TypeExtension::Interface(interface) => &interface.name,
+        TypeExtension::Union(union) => &union.name,
+        TypeExtension::Enum(enum_type) => &enum_type.name,
+        TypeExtension::InputObject(input) => &input.name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compose_services, Schema};
+    use crate::native::{normalize_subgraphs, parse_subgraphs};
+    use crate::{CompositionError, ServiceDefinition};
+    use graphql_parser::schema::TypeDefinition;
+
+    fn compose(subgraphs: &[(&str, &str)]) -> (Schema, Vec<CompositionError>) {
+        let service_list: Vec<ServiceDefinition> = subgraphs
+            .iter()
+            .map(|(name, type_defs)| ServiceDefinition::new(*name, "undefined", *type_defs))
+            .collect();
+        compose_services(&normalize_subgraphs(
+            &parse_subgraphs(&service_list).unwrap(),
+        ))
+    }
+
+    fn field_names(schema: &Schema, type_name: &str) -> Vec<String> {
+        match &schema.types[type_name] {
+            TypeDefinition::Object(object) => object
+                .fields
+                .iter()
+                .map(|field| field.name.clone())
+                .collect(),
+            definition => panic!("expected an object type, got {:?}", definition),
+        }
+    }
+
+    #[test]
+    fn it_records_which_subgraphs_own_and_extend_entities() {
+        let (schema, errors) = compose(&[
+            (
+                "accounts",
+                r#"type Query { me: User } type User @key(fields: "id") { id: ID! name: String }"#,
+            ),
+            (
+                "reviews",
+                r#"type Review { author: User @provides(fields: "name") } extend type User @key(fields: "id") { id: ID! @external name: String @external reviews: [Review] @requires(fields: "name") }"#,
+            ),
+        ]);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // The `@external` fields are left to the subgraph which owns them,
+        // and everything is sorted by name.
+        assert_eq!(field_names(&schema, "User"), vec!["id", "name", "reviews"]);
+
+        let user = &schema.type_metadata["User"];
+        assert_eq!(user.service_name.as_deref(), Some("accounts"));
+        assert!(!user.is_value_type);
+        assert_eq!(
+            user.keys,
+            Some(vec![
+                ("accounts".to_string(), vec!["id".to_string()]),
+                ("reviews".to_string(), vec!["id".to_string()]),
+            ])
+        );
+        let externals: Vec<(&str, Vec<&str>)> = user
+            .externals
+            .iter()
+            .map(|(service_name, fields)| {
+                let names = fields.iter().map(|field| field.name.as_str()).collect();
+                (service_name.as_str(), names)
+            })
+            .collect();
+        assert_eq!(externals, vec![("reviews", vec!["id", "name"])]);
+
+        let reviews = &schema.field_metadata[&("User".to_string(), "reviews".to_string())];
+        assert_eq!(reviews.service_name.as_deref(), Some("reviews"));
+        assert_eq!(reviews.requires.as_deref(), Some("name"));
+        assert!(!schema
+            .field_metadata
+            .contains_key(&("User".to_string(), "name".to_string())));
+
+        let author = &schema.field_metadata[&("Review".to_string(), "author".to_string())];
+        assert_eq!(author.service_name.as_deref(), Some("reviews"));
+        assert_eq!(author.provides.as_deref(), Some("name"));
+    }
+
+    #[test]
+    fn it_merges_value_types() {
+        let (schema, errors) = compose(&[
+            (
+                "users",
+                "type Query { me: User } type User { name: Name } type Name { first: String last: String }",
+            ),
+            (
+                "reviews",
+                "extend type Query { author: Name } type Name { first: String last: String }",
+            ),
+        ]);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let name = &schema.type_metadata["Name"];
+        assert!(name.is_value_type);
+        assert_eq!(name.service_name, None);
+        assert_eq!(field_names(&schema, "Name"), vec!["first", "last"]);
+        assert_eq!(field_names(&schema, "Query"), vec!["author", "me"]);
+    }
+
+    #[test]
+    fn it_makes_up_the_query_type_for_subgraphs_which_only_extend_it() {
+        let (schema, errors) = compose(&[
+            ("users", "extend type Query { me: String }"),
+            ("movies", "extend type Query { movies: [String] }"),
+        ]);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(field_names(&schema, "Query"), vec!["me", "movies"]);
+        assert!(!schema.has_type("Mutation"));
+    }
+
+    #[test]
+    fn it_keeps_the_executable_directives_of_every_subgraph() {
+        let (schema, errors) = compose(&[
+            (
+                "users",
+                "directive @stream on FIELD directive @tag on FIELD_DEFINITION type Query { me: String }",
+            ),
+            (
+                "movies",
+                "directive @stream on FIELD | FIELD_DEFINITION extend type Query { movies: [String] }",
+            ),
+        ]);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let directives: Vec<&str> = schema
+            .directives
+            .iter()
+            .map(|directive| directive.name.as_str())
+            .collect();
+        assert_eq!(directives, vec!["stream"]);
+        let definitions: Vec<String> = schema.directive_definitions["stream"]
+            .iter()
+            .map(|(service_name, definition)| {
+                format!("{}: {}", service_name, definition.to_string().trim_end())
+            })
+            .collect();
+        assert_eq!(
+            definitions,
+            vec![
+                "users: directive @stream on FIELD",
+                "movies: directive @stream on FIELD",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_field_sets_which_can_not_be_parsed() {
+        let (_, errors) = compose(&[(
+            "users",
+            r#"type Query { me: User } type User @key(fields: "id {") { id: ID! }"#,
+        )]);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].code(), "UNKNOWN");
+        // The rest of the message is up to `graphql-parser`.
+        let message = errors[0].message.as_deref().unwrap_or_default();
+        assert!(message.starts_with("Syntax Error: "), "{}", message);
+        assert_eq!(errors[0].coordinate.as_deref(), Some("User"));
+        assert_eq!(errors[0].subgraphs, vec!["users".to_string()]);
+    }
+}
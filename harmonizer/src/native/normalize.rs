@@ -0,0 +1,391 @@
+//! A port of [`normalizeTypeDefs`], which turns the SDL of a subgraph into the
+//! form which composition expects: types which are `@extends`-ed are
+//! extensions, the root operation types have their default names (and are
+//! extensions too), and the federation primitives which subgraphs may include
+//! in their SDL are gone.
+//!
+//! [`normalizeTypeDefs`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/normalize.ts
+
+use super::Document;
+use graphql_parser::schema::{
+    Definition, Directive, Field, InterfaceTypeExtension, ObjectTypeExtension, Type,
+    TypeDefinition, TypeExtension,
+};
+use std::collections::HashMap;
+
+/// The default names of the query, mutation and subscription types.
+pub(super) const DEFAULT_ROOT_OPERATION_NAMES: &[&str] = &["Query", "Mutation", "Subscription"];
+
+/// The directives which are defined by federation or by the GraphQL
+/// specification, whose definitions subgraphs may include in their SDL.
+const COMMON_DIRECTIVES: &[&str] = &[
+    "key",
+    "extends",
+    "external",
+    "requires",
+    "provides",
+    "include",
+    "skip",
+    "deprecated",
+    "specifiedBy",
+];
+
+/// The fields which federation adds to the query type of a subgraph.
+pub(super) const RESERVED_ROOT_FIELDS: &[&str] = &["_service", "_entities"];
+
+type FieldDefinition = Field<'static, String>;
+
+pub(super) fn normalize_type_defs(document: Document) -> Document {
+    // `strip_common_primitives` relies upon the query type having its default
+    // name, so the order of these matters.
+    strip_common_primitives(default_root_operation_types(
+        replace_extended_definitions_with_extensions(document),
+    ))
+}
+
+/// Object and interface types with an `@extends` directive are treated as
+/// extensions.
+fn replace_extended_definitions_with_extensions(mut document: Document) -> Document {
+    for definition in &mut document.definitions {
+        let extension = match definition {
+            Definition::TypeDefinition(TypeDefinition::Object(object))
+                if has_directive(&object.directives, "extends") =>
+            {
+                TypeExtension::Object(ObjectTypeExtension {
+                    position: object.position,
+                    name: std::mem::take(&mut object.name),
+                    implements_interfaces: std::mem::take(&mut object.implements_interfaces),
+                    directives: without_directive(&mut object.directives, "extends"),
+                    fields: std::mem::take(&mut object.fields),
+                })
+            }
+            Definition::TypeDefinition(TypeDefinition::Interface(interface))
+                if has_directive(&interface.directives, "extends") =>
+            {
+                TypeExtension::Interface(InterfaceTypeExtension {
+                    position: interface.position,
+                    name: std::mem::take(&mut interface.name),
+                    implements_interfaces: std::mem::take(&mut interface.implements_interfaces),
+                    directives: without_directive(&mut interface.directives, "extends"),
+                    fields: std::mem::take(&mut interface.fields),
+                })
+            }
+            _ => continue,
+        };
+        *definition = Definition::TypeExtension(extension);
+    }
+    document
+}
+
+/// Rename the root operation types of a subgraph which has a schema
+/// definition to their default names, and turn them into extensions of the
+/// root operation types of the supergraph.
+fn default_root_operation_types(mut document: Document) -> Document {
+    // The names of the root operation types, mapped to their default names.
+    let mut root_operation_types: HashMap<String, &str> = HashMap::new();
+    for definition in &document.definitions {
+        if let Definition::SchemaDefinition(schema) = definition {
+            for (name, default_name) in &[
+                (&schema.query, "Query"),
+                (&schema.mutation, "Mutation"),
+                (&schema.subscription, "Subscription"),
+            ] {
+                if let Some(name) = name {
+                    root_operation_types.insert(name.clone(), *default_name);
+                }
+            }
+        }
+    }
+
+    if root_operation_types.is_empty() {
+        for name in DEFAULT_ROOT_OPERATION_NAMES {
+            root_operation_types.insert(name.to_string(), name);
+        }
+    } else {
+        // With a schema definition, types with the default names which aren't
+        // root operation types can't be composed, so they're dropped, along
+        // with the fields which return them.
+        let is_conflicting = |name: &str| {
+            DEFAULT_ROOT_OPERATION_NAMES.contains(&name) && !root_operation_types.contains_key(name)
+        };
+        document.definitions.retain(|definition| match definition {
+            Definition::TypeDefinition(TypeDefinition::Object(object)) => {
+                !is_conflicting(&object.name)
+            }
+            Definition::TypeExtension(TypeExtension::Object(object)) => {
+                !is_conflicting(&object.name)
+            }
+            _ => true,
+        });
+        for definition in &mut document.definitions {
+            if let Some(fields) = fields_mut(definition) {
+                fields.retain(|field| !returns_default_root_operation_type(field));
+            }
+        }
+    }
+
+    let rename = |name: &mut String| {
+        if let Some(default_name) = root_operation_types.get(name.as_str()) {
+            *name = default_name.to_string();
+        }
+    };
+    document
+        .definitions
+        .retain(|definition| !matches!(definition, Definition::SchemaDefinition(_)));
+    for definition in &mut document.definitions {
+        if let Definition::TypeDefinition(TypeDefinition::Object(object)) = definition {
+            if root_operation_types.contains_key(&object.name)
+                || DEFAULT_ROOT_OPERATION_NAMES.contains(&object.name.as_str())
+            {
+                *definition =
+                    Definition::TypeExtension(TypeExtension::Object(ObjectTypeExtension {
+                        position: object.position,
+                        name: std::mem::take(&mut object.name),
+                        implements_interfaces: std::mem::take(&mut object.implements_interfaces),
+                        directives: std::mem::take(&mut object.directives),
+                        fields: std::mem::take(&mut object.fields),
+                    }));
+            }
+        }
+        if let Definition::TypeExtension(TypeExtension::Object(object)) = definition {
+            rename(&mut object.name);
+        }
+        rename_named_types(definition, &rename);
+    }
+
+    document
+}
+
+/// Drop the definitions of federation's own directives and types, and the
+/// fields it adds to the query type, which subgraphs may include in their SDL.
+fn strip_common_primitives(mut document: Document) -> Document {
+    for definition in &mut document.definitions {
+        match definition {
+            Definition::TypeDefinition(TypeDefinition::Object(object))
+                if object.name == "Query" =>
+            {
+                strip_reserved_root_fields(&mut object.fields)
+            }
+            Definition::TypeExtension(TypeExtension::Object(object)) if object.name == "Query" => {
+                strip_reserved_root_fields(&mut object.fields)
+            }
+            _ => {}
+        }
+    }
+
+    document.definitions.retain(|definition| match definition {
+        Definition::DirectiveDefinition(directive) => {
+            !COMMON_DIRECTIVES.contains(&directive.name.as_str())
+        }
+        Definition::TypeDefinition(TypeDefinition::Scalar(scalar)) => {
+            scalar.name != "_Any" && scalar.name != "_FieldSet"
+        }
+        Definition::TypeDefinition(TypeDefinition::Union(union)) => union.name != "_Entity",
+        Definition::TypeDefinition(TypeDefinition::Object(object)) => {
+            keep_object_type(&object.name, &object.fields)
+        }
+        Definition::TypeExtension(TypeExtension::Object(object)) => {
+            keep_object_type(&object.name, &object.fields)
+        }
+        _ => true,
+    });
+    document
+}
+
+fn strip_reserved_root_fields(fields: &mut Vec<FieldDefinition>) {
+    fields.retain(|field| !RESERVED_ROOT_FIELDS.contains(&field.name.as_str()));
+}
+
+/// Whether an object type is kept by [`strip_common_primitives`], which drops
+/// `_Service`, and the query type too if federation's fields were all it had.
+fn keep_object_type(name: &str, fields: &[FieldDefinition]) -> bool {
+    match name {
+        "Query" => !fields.is_empty(),
+        "_Service" => false,
+        _ => true,
+    }
+}
+
+fn returns_default_root_operation_type(field: &FieldDefinition) -> bool {
+    let name = match &field.field_type {
+        Type::NamedType(name) => name,
+        Type::NonNullType(inner) => match inner.as_ref() {
+            Type::NamedType(name) => name,
+            _ => return false,
+        },
+        Type::ListType(_) => return false,
+    };
+    DEFAULT_ROOT_OPERATION_NAMES.contains(&name.as_str())
+}
+
+fn fields_mut<'d>(
+    definition: &'d mut Definition<'static, String>,
+) -> Option<&'d mut Vec<FieldDefinition>> {
+    match definition {
+        Definition::TypeDefinition(TypeDefinition::Object(object)) => Some(&mut object.fields),
+        Definition::TypeDefinition(TypeDefinition::Interface(interface)) => {
+            Some(&mut interface.fields)
+        }
+        Definition::TypeExtension(TypeExtension::Object(object)) => Some(&mut object.fields),
+        Definition::TypeExtension(TypeExtension::Interface(interface)) => {
+            Some(&mut interface.fields)
+        }
+        _ => None,
+    }
+}
+
+/// Apply `rename` to every reference to a type within `definition`: the
+/// types of its fields, arguments and input fields, the interfaces it
+/// implements and the members of a union.
+fn rename_named_types<F>(definition: &mut Definition<'static, String>, rename: &F)
+where
+    F: Fn(&mut String),
+{
+    fn rename_type<F: Fn(&mut String)>(ty: &mut Type<'static, String>, rename: &F) {
+        match ty {
+            Type::NamedType(name) => rename(name),
+            Type::ListType(inner) | Type::NonNullType(inner) => rename_type(inner, rename),
+        }
+    }
+    let rename_fields = |fields: &mut Vec<FieldDefinition>| {
+        for field in fields {
+            rename_type(&mut field.field_type, rename);
+            for argument in &mut field.arguments {
+                rename_type(&mut argument.value_type, rename);
+            }
+        }
+    };
+
+    match definition {
+        Definition::TypeDefinition(TypeDefinition::Object(object)) => {
+            object.implements_interfaces.iter_mut().for_each(rename);
+            rename_fields(&mut object.fields);
+        }
+        Definition::TypeDefinition(TypeDefinition::Interface(interface)) => {
+            interface.implements_interfaces.iter_mut().for_each(rename);
+            rename_fields(&mut interface.fields);
+        }
+        Definition::TypeDefinition(TypeDefinition::Union(union)) => {
+            union.types.iter_mut().for_each(rename);
+        }
+        Definition::TypeDefinition(TypeDefinition::InputObject(input)) => {
+            for field in &mut input.fields {
+                rename_type(&mut field.value_type, rename);
+            }
+        }
+        Definition::TypeExtension(TypeExtension::Object(object)) => {
+            object.implements_interfaces.iter_mut().for_each(rename);
+            rename_fields(&mut object.fields);
+        }
+        Definition::TypeExtension(TypeExtension::Interface(interface)) => {
+            interface.implements_interfaces.iter_mut().for_each(rename);
+            rename_fields(&mut interface.fields);
+        }
+        Definition::TypeExtension(TypeExtension::Union(union)) => {
+            union.types.iter_mut().for_each(rename);
+        }
+        Definition::TypeExtension(TypeExtension::InputObject(input)) => {
+            for field in &mut input.fields {
+                rename_type(&mut field.value_type, rename);
+            }
+        }
+        Definition::DirectiveDefinition(directive) => {
+            for argument in &mut directive.arguments {
+                rename_type(&mut argument.value_type, rename);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_directive(directives: &[Directive<'static, String>], name: &str) -> bool {
+    directives.iter().any(|directive| directive.name == name)
+}
+
+fn without_directive(
+    directives: &mut Vec<Directive<'static, String>>,
+    name: &str,
+) -> Vec<Directive<'static, String>> {
+    let mut directives = std::mem::take(directives);
+    directives.retain(|directive| directive.name != name);
+    directives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_type_defs;
+
+    fn normalize(sdl: &str) -> String {
+        let document = graphql_parser::parse_schema::<String>(sdl)
+            .unwrap()
+            .into_static();
+        normalize_type_defs(document).to_string()
+    }
+
+    #[test]
+    fn it_renames_root_operation_types_to_their_defaults() {
+        let normalized = normalize(
+            r#"
+            schema {
+              query: RootQuery
+              mutation: RootMutation
+            }
+
+            type Query {
+              ignored: String
+            }
+
+            type RootQuery {
+              me: User
+              root: Query
+              self: RootQuery!
+            }
+
+            type RootMutation {
+              login: User
+            }
+
+            type User @key(fields: "id") @extends {
+              id: ID! @external
+            }
+          "#,
+        );
+
+        assert_eq!(
+            normalized,
+            "extend type Query {\n  me: User\n  self: Query!\n}\n\n\
+             extend type Mutation {\n  login: User\n}\n\n\
+             extend type User @key(fields: \"id\") {\n  id: ID! @external\n}\n"
+        );
+    }
+
+    #[test]
+    fn it_strips_federation_primitives() {
+        let normalized = normalize(
+            r#"
+            scalar _Any
+            scalar _FieldSet
+            directive @key(fields: _FieldSet!) on OBJECT | INTERFACE
+            directive @custom on FIELD
+            union _Entity = User
+            type _Service {
+              sdl: String
+            }
+
+            type User {
+              id: ID!
+            }
+
+            type Query {
+              _service: _Service!
+              _entities(representations: [_Any!]!): [_Entity]!
+            }
+          "#,
+        );
+
+        assert_eq!(
+            normalized,
+            "directive @custom on FIELD\n\ntype User {\n  id: ID!\n}\n"
+        );
+    }
+}
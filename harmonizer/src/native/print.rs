@@ -0,0 +1,883 @@
+//! A port of [`printSupergraphSdl`], which prints the composed schema along
+//! with the core and join directives that tell the router which subgraph
+//! resolves what.
+//!
+//! [`printSupergraphSdl`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/service/printSupergraphSdl.ts
+
+use super::collate::locale_compare;
+use super::compose::{Schema, TypeMetadata, SPECIFIED_SCALARS};
+use crate::ServiceDefinition;
+use graphql_parser::query::{Selection, Type};
+use graphql_parser::schema::{
+    Directive, DirectiveDefinition, EnumType, Field, InputObjectType, InputValue, InterfaceType,
+    ObjectType, TypeDefinition, UnionType, Value,
+};
+use std::collections::HashMap;
+
+/// The directives which every supergraph defines, as they're printed.
+const JOIN_DIRECTIVES: &[&str] = &[
+    "directive @core(feature: String!) repeatable on SCHEMA",
+    "directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet) on FIELD_DEFINITION",
+    "directive @join__type(graph: join__Graph!, key: join__FieldSet) repeatable on OBJECT | INTERFACE",
+    "directive @join__owner(graph: join__Graph!) on OBJECT | INTERFACE",
+    "directive @join__graph(name: String!, url: String!) on ENUM_VALUE",
+];
+
+const DEFAULT_DEPRECATION_REASON: &str = "No longer supported";
+
+pub(super) fn print_supergraph_sdl(schema: &Schema, service_list: &[ServiceDefinition]) -> String {
+    let printer = Printer {
+        schema,
+        graphs: JoinGraphs::new(service_list),
+    };
+
+    let mut type_names: Vec<&str> = schema
+        .types
+        .keys()
+        .map(String::as_str)
+        .chain(vec!["join__FieldSet", "join__Graph"])
+        .filter(|name| !SPECIFIED_SCALARS.contains(name))
+        .collect();
+    type_names.sort_by(|a, b| locale_compare(a, b));
+
+    let mut sections = vec![printer.print_schema_definition()];
+    sections.extend(
+        JOIN_DIRECTIVES
+            .iter()
+            .map(|directive| directive.to_string()),
+    );
+    sections.extend(
+        schema
+            .directives
+            .iter()
+            .map(|directive| printer.print_directive(directive)),
+    );
+    sections.extend(type_names.into_iter().map(|name| match name {
+        "join__FieldSet" => "scalar join__FieldSet".to_string(),
+        "join__Graph" => printer.graphs.print_enum(),
+        _ => printer.print_type(&schema.types[name]),
+    }));
+    sections.join("\n\n") + "\n"
+}
+
+/// The values of the `join__Graph` enum, which stand for the subgraphs.
+struct JoinGraphs<'a> {
+    /// The name of each value, and the subgraph it stands for, in the order
+    /// they're printed.
+    values: Vec<(String, &'a ServiceDefinition)>,
+    by_subgraph: HashMap<&'a str, String>,
+}
+
+impl<'a> JoinGraphs<'a> {
+    /// Name the values after their subgraphs like `getJoinGraphEnum` does,
+    /// which sanitizes the names of the subgraphs into uppercase GraphQL
+    /// names, and numbers any which end up the same.
+    fn new(service_list: &'a [ServiceDefinition]) -> Self {
+        let mut sorted: Vec<&ServiceDefinition> = service_list.iter().collect();
+        sorted.sort_by(|a, b| locale_compare(&a.name, &b.name));
+
+        let mut sanitized: Vec<(String, Vec<&ServiceDefinition>)> = Vec::new();
+        for service in sorted {
+            let name = sanitize_graph_name(&service.name);
+            match sanitized
+                .iter_mut()
+                .find(|(sanitized, _)| *sanitized == name)
+            {
+                Some((_, services)) => services.push(service),
+                None => sanitized.push((name, vec![service])),
+            }
+        }
+
+        let mut values = Vec::new();
+        for (name, services) in sanitized {
+            if services.len() == 1 {
+                values.push((name, services[0]));
+            } else {
+                for (index, service) in services.into_iter().enumerate() {
+                    values.push((format!("{}_{}", name, index + 1), service));
+                }
+            }
+        }
+
+        let by_subgraph = values
+            .iter()
+            .map(|(value, service)| (service.name.as_str(), value.clone()))
+            .collect();
+        JoinGraphs {
+            values,
+            by_subgraph,
+        }
+    }
+
+    /// The value which stands for `subgraph`.
+    fn get<'s>(&'s self, subgraph: &'s str) -> &'s str {
+        self.by_subgraph
+            .get(subgraph)
+            .map(String::as_str)
+            .unwrap_or(subgraph)
+    }
+
+    fn print_enum(&self) -> String {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(value, service)| {
+                format!(
+                    "  {} @join__graph(name: {} url: {})",
+                    value,
+                    print_string(&service.name),
+                    print_string(&service.url)
+                )
+            })
+            .collect();
+        format!("enum join__Graph{}", print_block(&values, false))
+    }
+}
+
+fn sanitize_graph_name(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            sanitized.push(c);
+        } else {
+            // JavaScript replaces every UTF-16 code unit on its own.
+            for _ in 0..c.len_utf16() {
+                sanitized.push('_');
+            }
+        }
+    }
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    let without_digits = sanitized.trim_end_matches(|c: char| c.is_ascii_digit());
+    if without_digits.len() < sanitized.len() && without_digits.ends_with('_') {
+        sanitized.push('_');
+    }
+    sanitized.to_uppercase()
+}
+
+struct Printer<'a> {
+    schema: &'a Schema,
+    graphs: JoinGraphs<'a>,
+}
+
+impl Printer<'_> {
+    fn print_schema_definition(&self) -> String {
+        let mut operation_types = vec!["  query: Query"];
+        if self.schema.has_type("Mutation") {
+            operation_types.push("  mutation: Mutation");
+        }
+        if self.schema.has_type("Subscription") {
+            operation_types.push("  subscription: Subscription");
+        }
+
+        // `printSupergraphSdl` concatenates an array of the `@core` directives
+        // onto the string, which separates them with commas.
+        format!(
+            "schema\n  @core(feature: \"https://specs.apollo.dev/core/v0.1\"),\n  @core(feature: \"https://specs.apollo.dev/join/v0.1\")\n{{\n{}\n}}",
+            operation_types.join("\n")
+        )
+    }
+
+    fn print_directive(&self, directive: &DirectiveDefinition<'static, String>) -> String {
+        let locations: Vec<&str> = directive
+            .locations
+            .iter()
+            .map(|location| location.as_str())
+            .collect();
+        format!(
+            "{}directive @{}{}{} on {}",
+            print_description(&directive.description, "", true),
+            directive.name,
+            self.print_args(&directive.arguments, ""),
+            if directive.repeatable {
+                " repeatable"
+            } else {
+                ""
+            },
+            locations.join(" | ")
+        )
+    }
+
+    fn print_type(&self, definition: &TypeDefinition<'static, String>) -> String {
+        match definition {
+            TypeDefinition::Scalar(scalar) => format!(
+                "{}scalar {}",
+                print_description(&scalar.description, "", true),
+                scalar.name
+            ),
+            TypeDefinition::Object(object) => self.print_object(object),
+            TypeDefinition::Interface(interface) => self.print_interface(interface),
+            TypeDefinition::Union(union) => print_union(union),
+            TypeDefinition::Enum(enum_type) => print_enum(enum_type),
+            TypeDefinition::InputObject(input) => self.print_input_object(input),
+        }
+    }
+
+    fn print_object(&self, object: &ObjectType<'static, String>) -> String {
+        let implemented_interfaces = if object.implements_interfaces.is_empty() {
+            String::new()
+        } else {
+            format!(" implements {}", object.implements_interfaces.join(" & "))
+        };
+        format!(
+            "{}type {}{}{}{}",
+            print_description(&object.description, "", true),
+            object.name,
+            implemented_interfaces,
+            self.print_type_join_directives(&object.name, true),
+            self.print_fields(&object.name, &object.fields, true)
+        )
+    }
+
+    fn print_interface(&self, interface: &InterfaceType<'static, String>) -> String {
+        format!(
+            "{}interface {}{}{}",
+            print_description(&interface.description, "", true),
+            interface.name,
+            self.print_type_join_directives(&interface.name, false),
+            self.print_fields(&interface.name, &interface.fields, false)
+        )
+    }
+
+    /// The `@join__owner` and `@join__type` directives of an entity, with
+    /// the keys of the subgraph which owns it first.
+    fn print_type_join_directives(&self, type_name: &str, is_object: bool) -> String {
+        let metadata = match self.schema.type_metadata.get(type_name) {
+            Some(metadata) => metadata,
+            None => return String::new(),
+        };
+        let (owner, keys) = match (&metadata.service_name, &metadata.keys) {
+            (Some(owner), Some(keys)) => (owner, keys),
+            _ => return String::new(),
+        };
+
+        let mut printed = String::new();
+        if is_object {
+            printed += &format!("\n  @join__owner(graph: {})", self.graphs.get(owner));
+        }
+        let owner_keys = keys.iter().filter(|(service, _)| service == owner);
+        let other_keys = keys.iter().filter(|(service, _)| service != owner);
+        for (service, field_sets) in owner_keys.chain(other_keys) {
+            for field_set in field_sets {
+                printed += &format!(
+                    "\n  @join__type(graph: {}, key: {})",
+                    self.graphs.get(service),
+                    print_string(field_set)
+                );
+            }
+        }
+        printed
+    }
+
+    fn print_fields(
+        &self,
+        type_name: &str,
+        fields: &[Field<'static, String>],
+        is_object: bool,
+    ) -> String {
+        let fields: Vec<String> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let join_field = if is_object {
+                    self.print_join_field_directive(type_name, &field.name)
+                } else {
+                    String::new()
+                };
+                format!(
+                    "{}  {}{}: {}{}{}",
+                    print_description(&field.description, "  ", i == 0),
+                    field.name,
+                    self.print_args(&field.arguments, "  "),
+                    field.field_type,
+                    print_deprecated(&field.directives),
+                    join_field
+                )
+            })
+            .collect();
+
+        // Entities print their fields on a new line, after their directives.
+        let is_entity = matches!(
+            self.schema.type_metadata.get(type_name),
+            Some(TypeMetadata { keys: Some(_), .. })
+        );
+        print_block(&fields, is_entity)
+    }
+
+    /// The `@join__field` directive of a field, which fields resolved by the
+    /// subgraph which owns their entity only have for the sake of the
+    /// `@join__type` directives of the entity.
+    fn print_join_field_directive(&self, type_name: &str, field_name: &str) -> String {
+        let metadata = match self
+            .schema
+            .field_metadata
+            .get(&(type_name.to_string(), field_name.to_string()))
+        {
+            Some(metadata) => metadata,
+            None => {
+                return match self.schema.type_metadata.get(type_name) {
+                    Some(TypeMetadata {
+                        service_name: Some(owner),
+                        keys: Some(_),
+                        ..
+                    }) => format!(" @join__field(graph: {})", self.graphs.get(owner)),
+                    _ => String::new(),
+                };
+            }
+        };
+
+        let mut arguments = Vec::new();
+        if let Some(service_name) = metadata.service_name.as_deref().filter(|s| !s.is_empty()) {
+            arguments.push(format!("graph: {}", self.graphs.get(service_name)));
+        }
+        if let Some(requires) = metadata.requires.as_deref().filter(|s| !s.is_empty()) {
+            arguments.push(format!("requires: {}", print_string(requires)));
+        }
+        if let Some(provides) = metadata.provides.as_deref().filter(|s| !s.is_empty()) {
+            arguments.push(format!("provides: {}", print_string(provides)));
+        }
+        format!(" @join__field({})", arguments.join(", "))
+    }
+
+    fn print_input_object(&self, input: &InputObjectType<'static, String>) -> String {
+        let fields: Vec<String> = input
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                format!(
+                    "{}  {}",
+                    print_description(&field.description, "  ", i == 0),
+                    self.print_input_value(field)
+                )
+            })
+            .collect();
+        format!(
+            "{}input {}{}",
+            print_description(&input.description, "", true),
+            input.name,
+            print_block(&fields, false)
+        )
+    }
+
+    fn print_args(&self, args: &[InputValue<'static, String>], indentation: &str) -> String {
+        if args.is_empty() {
+            return String::new();
+        }
+
+        // Without descriptions, the arguments all fit on one line.
+        if args.iter().all(|arg| arg.description.is_none()) {
+            let args: Vec<String> = args.iter().map(|arg| self.print_input_value(arg)).collect();
+            return format!("({})", args.join(", "));
+        }
+
+        let args: Vec<String> = args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                format!(
+                    "{}  {}{}",
+                    print_description(&arg.description, &format!("  {}", indentation), i == 0),
+                    indentation,
+                    self.print_input_value(arg)
+                )
+            })
+            .collect();
+        format!("(\n{}\n{})", args.join("\n"), indentation)
+    }
+
+    fn print_input_value(&self, input: &InputValue<'static, String>) -> String {
+        let default_value = input
+            .default_value
+            .as_ref()
+            .and_then(|value| self.coerce(value, &input.value_type))
+            .and_then(|value| self.ast_from_value(&value, &input.value_type));
+        match default_value {
+            Some(default_value) => {
+                format!("{}: {} = {}", input.name, input.value_type, default_value)
+            }
+            None => format!("{}: {}", input.name, input.value_type),
+        }
+    }
+
+    /// Coerce a default value to its type like `valueFromAST` does, which is
+    /// `None` if it isn't a valid value of that type.
+    fn coerce(
+        &self,
+        value: &Value<'static, String>,
+        ty: &Type<'static, String>,
+    ) -> Option<Coerced> {
+        let named_type = match ty {
+            Type::NonNullType(inner) => {
+                return match value {
+                    Value::Null => None,
+                    _ => self.coerce(value, inner),
+                }
+            }
+            _ if matches!(value, Value::Null) => return Some(Coerced::Null),
+            Type::ListType(item_type) => {
+                return match value {
+                    Value::List(items) => items
+                        .iter()
+                        .map(|item| self.coerce(item, item_type))
+                        .collect::<Option<_>>()
+                        .map(Coerced::List),
+                    _ => self
+                        .coerce(value, item_type)
+                        .map(|item| Coerced::List(vec![item])),
+                }
+            }
+            Type::NamedType(name) => name.as_str(),
+        };
+
+        match (named_type, value) {
+            (_, Value::Variable(_)) => None,
+            ("Int", Value::Int(n)) => n
+                .as_i64()
+                .filter(|n| i32::MIN as i64 <= *n && *n <= i32::MAX as i64)
+                .map(|n| Coerced::Number(n as f64)),
+            ("Float", Value::Int(n)) => n.as_i64().map(|n| Coerced::Number(n as f64)),
+            ("Float", Value::Float(f)) => Some(Coerced::Number(*f)),
+            ("String", Value::String(s)) => Some(Coerced::String(s.clone())),
+            ("Boolean", Value::Boolean(b)) => Some(Coerced::Boolean(*b)),
+            ("ID", Value::String(s)) => Some(Coerced::String(s.clone())),
+            ("ID", Value::Int(n)) => n.as_i64().map(|n| Coerced::String(n.to_string())),
+            (name, _) if SPECIFIED_SCALARS.contains(&name) => None,
+            (name, value) => match (self.schema.types.get(name)?, value) {
+                (TypeDefinition::Scalar(_), value) => Some(coerce_untyped(value)),
+                (TypeDefinition::Enum(enum_type), Value::Enum(value)) => enum_type
+                    .values
+                    .iter()
+                    .find(|enum_value| enum_value.name == *value)
+                    .map(|_| Coerced::Enum(value.clone())),
+                (TypeDefinition::InputObject(input), Value::Object(fields)) => {
+                    let mut coerced = Vec::new();
+                    for field in &input.fields {
+                        let value = match fields.get(&field.name) {
+                            Some(value) => self.coerce(value, &field.value_type)?,
+                            None => match field
+                                .default_value
+                                .as_ref()
+                                .and_then(|value| self.coerce(value, &field.value_type))
+                            {
+                                Some(default_value) => default_value,
+                                None if matches!(field.value_type, Type::NonNullType(_)) => {
+                                    return None
+                                }
+                                None => continue,
+                            },
+                        };
+                        coerced.push((field.name.clone(), value));
+                    }
+                    Some(Coerced::Object(coerced))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Print a coerced value as a literal of its type like `astFromValue`
+    /// does, which is `None` if it can't be.
+    fn ast_from_value(&self, value: &Coerced, ty: &Type<'static, String>) -> Option<String> {
+        let named_type = match ty {
+            Type::NonNullType(inner) => {
+                return self
+                    .ast_from_value(value, inner)
+                    .filter(|printed| printed != "null")
+            }
+            _ if matches!(value, Coerced::Null) => return Some("null".to_string()),
+            Type::ListType(item_type) => {
+                return match value {
+                    Coerced::List(items) => {
+                        let items: Vec<String> = items
+                            .iter()
+                            .filter_map(|item| self.ast_from_value(item, item_type))
+                            .collect();
+                        Some(format!("[{}]", items.join(", ")))
+                    }
+                    _ => self.ast_from_value(value, item_type),
+                }
+            }
+            Type::NamedType(name) => name.as_str(),
+        };
+
+        match (self.schema.types.get(named_type), value) {
+            (Some(TypeDefinition::InputObject(input)), Coerced::Object(fields)) => {
+                let fields: Vec<String> = input
+                    .fields
+                    .iter()
+                    .filter_map(|field| {
+                        let (_, value) = fields.iter().find(|(name, _)| *name == field.name)?;
+                        let value = self.ast_from_value(value, &field.value_type)?;
+                        Some(format!("{}: {}", field.name, value))
+                    })
+                    .collect();
+                Some(format!("{{{}}}", fields.join(", ")))
+            }
+            (Some(TypeDefinition::InputObject(_)), _) => None,
+            (_, Coerced::Boolean(b)) => Some(b.to_string()),
+            (_, Coerced::Number(n)) => Some(print_number(*n)),
+            (Some(TypeDefinition::Enum(_)), Coerced::Enum(name)) => Some(name.clone()),
+            (None, Coerced::String(s)) if named_type == "ID" && is_integer(s) => Some(s.clone()),
+            (_, Coerced::String(s)) => Some(print_string(s)),
+            _ => None,
+        }
+    }
+}
+
+/// A default value, coerced to its type.
+#[derive(Debug)]
+enum Coerced {
+    Null,
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Enum(String),
+    List(Vec<Coerced>),
+    Object(Vec<(String, Coerced)>),
+}
+
+/// Coerce a value of a custom scalar like `valueFromASTUntyped` does.
+fn coerce_untyped(value: &Value<'static, String>) -> Coerced {
+    match value {
+        Value::Int(n) => Coerced::Number(n.as_i64().unwrap_or_default() as f64),
+        Value::Float(f) => Coerced::Number(*f),
+        Value::String(s) | Value::Enum(s) => Coerced::String(s.clone()),
+        Value::Boolean(b) => Coerced::Boolean(*b),
+        Value::List(items) => Coerced::List(items.iter().map(coerce_untyped).collect()),
+        Value::Object(fields) => Coerced::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), coerce_untyped(value)))
+                .collect(),
+        ),
+        Value::Null | Value::Variable(_) => Coerced::Null,
+    }
+}
+
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && (digits == "0" || !digits.starts_with('0'))
+}
+
+/// Print a number the way JavaScript's `String` does.
+fn print_number(n: f64) -> String {
+    if n == 0.0 {
+        return "0".to_string();
+    }
+    if n.abs() >= 1e21 || n.abs() < 1e-6 {
+        let printed = format!("{:e}", n);
+        return match printed.find('e') {
+            Some(e) if !printed[e + 1..].starts_with('-') => {
+                format!("{}e+{}", &printed[..e], &printed[e + 1..])
+            }
+            _ => printed,
+        };
+    }
+    format!("{}", n)
+}
+
+fn print_union(union: &UnionType<'static, String>) -> String {
+    let possible_types = if union.types.is_empty() {
+        String::new()
+    } else {
+        format!(" = {}", union.types.join(" | "))
+    };
+    format!(
+        "{}union {}{}",
+        print_description(&union.description, "", true),
+        union.name,
+        possible_types
+    )
+}
+
+fn print_enum(enum_type: &EnumType<'static, String>) -> String {
+    let values: Vec<String> = enum_type
+        .values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            format!(
+                "{}  {}{}",
+                print_description(&value.description, "  ", i == 0),
+                value.name,
+                print_deprecated(&value.directives)
+            )
+        })
+        .collect();
+    format!(
+        "{}enum {}{}",
+        print_description(&enum_type.description, "", true),
+        enum_type.name,
+        print_block(&values, false)
+    )
+}
+
+/// The `@deprecated` directive of a field or enum value, if it has one.
+fn print_deprecated(directives: &[Directive<'static, String>]) -> String {
+    let deprecated = match directives
+        .iter()
+        .find(|directive| directive.name == "deprecated")
+    {
+        Some(deprecated) => deprecated,
+        None => return String::new(),
+    };
+    match deprecated
+        .arguments
+        .iter()
+        .find(|(name, _)| name == "reason")
+        .map(|(_, reason)| reason)
+    {
+        Some(Value::Null) => String::new(),
+        Some(Value::String(reason)) if reason != DEFAULT_DEPRECATION_REASON => {
+            format!(" @deprecated(reason: {})", print_string(reason))
+        }
+        _ => " @deprecated".to_string(),
+    }
+}
+
+fn print_block(items: &[String], on_new_line: bool) -> String {
+    if items.is_empty() {
+        String::new()
+    } else if on_new_line {
+        format!("\n{{\n{}\n}}", items.join("\n"))
+    } else {
+        format!(" {{\n{}\n}}", items.join("\n"))
+    }
+}
+
+fn print_description(
+    description: &Option<String>,
+    indentation: &str,
+    first_in_block: bool,
+) -> String {
+    let description = match description {
+        Some(description) => description,
+        None => return String::new(),
+    };
+
+    let block_string = print_block_string(description, description.chars().count() > 70);
+    let prefix = if !indentation.is_empty() && !first_in_block {
+        format!("\n{}", indentation)
+    } else {
+        indentation.to_string()
+    };
+    format!(
+        "{}{}\n",
+        prefix,
+        block_string.replace('\n', &format!("\n{}", indentation))
+    )
+}
+
+/// Print a description as a block string, on lines of its own unless it fits
+/// on the line of its quotes.
+fn print_block_string(value: &str, prefer_multiple_lines: bool) -> String {
+    let is_single_line = !value.contains('\n');
+    let has_leading_space = value.starts_with(' ') || value.starts_with('\t');
+    let has_trailing_quote = value.ends_with('"');
+    let has_trailing_slash = value.ends_with('\\');
+    let print_as_multiple_lines =
+        !is_single_line || has_trailing_quote || has_trailing_slash || prefer_multiple_lines;
+
+    let mut result = String::new();
+    if print_as_multiple_lines && !(is_single_line && has_leading_space) {
+        result.push('\n');
+    }
+    result.push_str(value);
+    if print_as_multiple_lines {
+        result.push('\n');
+    }
+    format!("\"\"\"{}\"\"\"", result.replace("\"\"\"", "\\\"\"\""))
+}
+
+/// Print a string literal like `printStringLiteral` does, with
+/// `JSON.stringify`.
+fn print_string(value: &str) -> String {
+    serde_json::to_string(value).expect("strings can be serialized")
+}
+
+/// Print a field set like `printFieldSet` does, which prints every selection
+/// with as few characters as `stripIgnoredCharacters` leaves it.
+pub(super) fn print_field_set(selections: &[Selection<'static, String>]) -> String {
+    selections
+        .iter()
+        .map(|selection| {
+            let mut printer = StrippedPrinter::default();
+            printer.selection(selection);
+            printer.output
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Prints selections as tokens which are only separated by a space when
+/// neither of them is punctuation, which is all `stripIgnoredCharacters`
+/// keeps between them.
+#[derive(Default)]
+struct StrippedPrinter {
+    output: String,
+    after_non_punctuator: bool,
+}
+
+impl StrippedPrinter {
+    fn punctuator(&mut self, punctuator: &str) {
+        // A spread would run into a preceding name, like in `a...b`.
+        if punctuator == "..." && self.after_non_punctuator {
+            self.output.push(' ');
+        }
+        self.output.push_str(punctuator);
+        self.after_non_punctuator = false;
+    }
+
+    fn token(&mut self, token: &str) {
+        if self.after_non_punctuator {
+            self.output.push(' ');
+        }
+        self.output.push_str(token);
+        self.after_non_punctuator = true;
+    }
+
+    fn selection(&mut self, selection: &Selection<'static, String>) {
+        match selection {
+            Selection::Field(field) => {
+                if let Some(alias) = &field.alias {
+                    self.token(alias);
+                    self.punctuator(":");
+                }
+                self.token(&field.name);
+                self.arguments(&field.arguments);
+                self.directives(&field.directives);
+                if !field.selection_set.items.is_empty() {
+                    self.selection_set(&field.selection_set.items);
+                }
+            }
+            Selection::FragmentSpread(spread) => {
+                self.punctuator("...");
+                self.token(&spread.fragment_name);
+                self.directives(&spread.directives);
+            }
+            Selection::InlineFragment(fragment) => {
+                self.punctuator("...");
+                if let Some(graphql_parser::query::TypeCondition::On(type_name)) =
+                    &fragment.type_condition
+                {
+                    self.token("on");
+                    self.token(type_name);
+                }
+                self.directives(&fragment.directives);
+                self.selection_set(&fragment.selection_set.items);
+            }
+        }
+    }
+
+    fn selection_set(&mut self, selections: &[Selection<'static, String>]) {
+        self.punctuator("{");
+        for selection in selections {
+            self.selection(selection);
+        }
+        self.punctuator("}");
+    }
+
+    fn arguments(&mut self, arguments: &[(String, Value<'static, String>)]) {
+        if arguments.is_empty() {
+            return;
+        }
+        self.punctuator("(");
+        for (name, value) in arguments {
+            self.token(name);
+            self.punctuator(":");
+            self.value(value);
+        }
+        self.punctuator(")");
+    }
+
+    fn directives(&mut self, directives: &[Directive<'static, String>]) {
+        for directive in directives {
+            self.punctuator("@");
+            self.token(&directive.name);
+            self.arguments(&directive.arguments);
+        }
+    }
+
+    fn value(&mut self, value: &Value<'static, String>) {
+        match value {
+            Value::Variable(name) => {
+                self.punctuator("$");
+                self.token(name);
+            }
+            Value::Int(n) => self.token(&n.as_i64().unwrap_or_default().to_string()),
+            Value::Float(f) => self.token(&f.to_string()),
+            Value::String(s) => self.token(&print_string(s)),
+            Value::Boolean(b) => self.token(&b.to_string()),
+            Value::Null => self.token("null"),
+            Value::Enum(name) => self.token(name),
+            Value::List(items) => {
+                self.punctuator("[");
+                for item in items {
+                    self.value(item);
+                }
+                self.punctuator("]");
+            }
+            Value::Object(fields) => {
+                self.punctuator("{");
+                for (name, value) in fields {
+                    self.token(name);
+                    self.punctuator(":");
+                    self.value(value);
+                }
+                self.punctuator("}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{print_field_set, print_number, sanitize_graph_name};
+    use graphql_parser::query::{Definition, OperationDefinition};
+
+    fn field_set(source: &str) -> String {
+        let document = graphql_parser::parse_query::<String>(source)
+            .unwrap()
+            .into_static();
+        match &document.definitions[0] {
+            Definition::Operation(OperationDefinition::SelectionSet(selection_set)) => {
+                print_field_set(&selection_set.items)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn it_prints_field_sets_without_ignored_characters() {
+        assert_eq!(
+            field_set("{ username  name { first last } }"),
+            "username name{first last}"
+        );
+        assert_eq!(
+            field_set("{ field(arg: 1, other: \"x\") }"),
+            "field(arg:1 other:\"x\")"
+        );
+        assert_eq!(field_set("{ a ... on Foo { x } }"), "a ...on Foo{x}");
+    }
+
+    #[test]
+    fn it_prints_numbers_like_javascript() {
+        assert_eq!(print_number(1.0), "1");
+        assert_eq!(print_number(-0.0), "0");
+        assert_eq!(print_number(0.5), "0.5");
+        assert_eq!(print_number(1e21), "1e+21");
+        assert_eq!(print_number(1.5e-7), "1.5e-7");
+    }
+
+    #[test]
+    fn it_sanitizes_graph_names() {
+        assert_eq!(sanitize_graph_name("accounts"), "ACCOUNTS");
+        assert_eq!(sanitize_graph_name("my-service.v2"), "MY_SERVICE_V2");
+        assert_eq!(sanitize_graph_name("3d"), "_3D");
+        assert_eq!(sanitize_graph_name("service_1"), "SERVICE_1_");
+    }
+}
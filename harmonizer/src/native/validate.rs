@@ -0,0 +1,262 @@
+//! Ports of the validation rules which `composeAndValidate` applies around
+//! `composeServices`: to every subgraph [before](pre_normalization) and
+//! [after](pre_composition) normalizing it, to the definitions and extensions
+//! of the subgraphs as they're [composed](sdl), and to the
+//! [composed schema](post_composition).
+//!
+//! Their errors have the same codes and messages as the ones of
+//! `@apollo/federation`, along with the `subgraphs` and `coordinate` which
+//! `serializeError` derives from those messages.  Their `locations` can't
+//! always be the same, though, since `graphql-parser` doesn't record where
+//! every node is: types are located at their keyword rather than at their
+//! description, and the field sets of directives at the directive.
+//!
+//! The rules which `graphql-js` itself applies to SDL (e.g., that every type
+//! which is referred to is defined) and to schemas (`validateSchema`) haven't
+//! been ported, so those errors are only ever reported by JavaScript
+//! composition.
+
+use super::compose::Schema;
+use super::Subgraph;
+use crate::{
+    CompositionError, CompositionErrorCode, CompositionErrorExtensions, CompositionErrorLocation,
+};
+use graphql_parser::query::{self, OperationDefinition, Selection};
+use graphql_parser::schema::{Directive, Field, Type, TypeDefinition, TypeExtension, Value};
+use graphql_parser::Pos;
+
+mod post_composition;
+mod pre_composition;
+mod pre_normalization;
+pub(super) mod sdl;
+
+type Directive_ = Directive<'static, String>;
+type Field_ = Field<'static, String>;
+type Selection_ = Selection<'static, String>;
+
+/// A node within the SDL of a subgraph, which an error points at.
+type Node<'a> = (&'a str, Pos);
+
+/// Validate the subgraphs as they were given, like
+/// `validateServicesBeforeNormalization`.
+pub(super) fn validate_services_before_normalization(
+    subgraphs: &[Subgraph],
+) -> Vec<CompositionError> {
+    subgraphs
+        .iter()
+        .flat_map(pre_normalization::validate)
+        .collect()
+}
+
+/// Validate the normalized subgraphs, like
+/// `validateServicesBeforeComposition`.
+pub(super) fn validate_services_before_composition(
+    subgraphs: &[Subgraph],
+) -> Vec<CompositionError> {
+    subgraphs
+        .iter()
+        .flat_map(pre_composition::validate)
+        .collect()
+}
+
+/// Validate the schema which the normalized subgraphs were composed into,
+/// like `validateComposedSchema` (short of `validateSchema`).
+pub(super) fn validate_composed_schema(
+    schema: &Schema,
+    subgraphs: &[Subgraph],
+) -> Vec<CompositionError> {
+    post_composition::validate(schema, subgraphs)
+}
+
+/// An error with a `code`, like the ones of `errorWithCode`.
+fn error_with_code(
+    code: CompositionErrorCode,
+    message: String,
+    nodes: &[Node<'_>],
+) -> CompositionError {
+    CompositionError {
+        extensions: Some(CompositionErrorExtensions { code }),
+        ..error(message, nodes)
+    }
+}
+
+/// An error without a code, like the ones `graphql-js` reports, which is
+/// attributed to subgraphs the way `serializeError` attributes them: to the
+/// one its message is prefixed with (see [`log_service_and_type`]) and then
+/// to the ones its nodes are in.
+fn error(message: String, nodes: &[Node<'_>]) -> CompositionError {
+    let (mut subgraphs, coordinate) = match split_prefix(&message) {
+        Some((subgraph, coordinate)) => (vec![subgraph.to_string()], Some(coordinate.to_string())),
+        None => (Vec::new(), None),
+    };
+    for (subgraph, _) in nodes {
+        if !subgraphs.iter().any(|seen| seen == subgraph) {
+            subgraphs.push(subgraph.to_string());
+        }
+    }
+
+    CompositionError {
+        message: Some(message),
+        extensions: None,
+        locations: nodes
+            .iter()
+            .map(|(subgraph, position)| CompositionErrorLocation {
+                subgraph: Some(subgraph.to_string()),
+                line: position.line,
+                column: position.column,
+            })
+            .collect(),
+        subgraphs,
+        coordinate,
+    }
+}
+
+/// The subgraph and the coordinate which a message is prefixed with, which
+/// `serializeError` matches with
+/// `^\[([^\]@][^\]]*)\] (@?[_A-Za-z][_0-9A-Za-z]*(?:\.[_A-Za-z][_0-9A-Za-z]*)?) ->`.
+fn split_prefix(message: &str) -> Option<(&str, &str)> {
+    fn is_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+            && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+    }
+
+    let rest = message.strip_prefix('[')?;
+    let end = rest.find(']')?;
+    let subgraph = &rest[..end];
+    if subgraph.is_empty() || subgraph.starts_with('@') {
+        return None;
+    }
+
+    let rest = rest[end + 1..].strip_prefix(' ')?;
+    let coordinate = &rest[..rest.find(" ->")?];
+    let mut names = coordinate
+        .strip_prefix('@')
+        .unwrap_or(coordinate)
+        .splitn(2, '.');
+    let valid = match (names.next(), names.next()) {
+        (Some(type_name), field_name) => is_name(type_name) && field_name.into_iter().all(is_name),
+        (None, _) => false,
+    };
+    if valid {
+        Some((subgraph, coordinate))
+    } else {
+        None
+    }
+}
+
+/// The prefix of the messages about a type or field within a subgraph, like
+/// `logServiceAndType`.
+fn log_service_and_type(service_name: &str, type_name: &str, field_name: Option<&str>) -> String {
+    match field_name {
+        Some(field_name) => format!("[{}] {}.{} -> ", service_name, type_name, field_name),
+        None => format!("[{}] {} -> ", service_name, type_name),
+    }
+}
+
+/// The prefix of the messages about a directive, like `logDirective`.
+fn log_directive(directive_name: &str) -> String {
+    format!("[@{}] -> ", directive_name)
+}
+
+/// The directives among `directives` with the given name.
+fn find_directives<'d>(
+    directives: &'d [Directive_],
+    name: &'d str,
+) -> impl Iterator<Item = &'d Directive_> + 'd {
+    directives
+        .iter()
+        .filter(move |directive| directive.name == name)
+}
+
+/// The (first) argument of a directive like `@key`, if it is a string.
+fn field_set_argument(directive: &Directive_) -> Option<&str> {
+    match directive.arguments.first() {
+        Some((_, Value::String(fields))) => Some(fields),
+        _ => None,
+    }
+}
+
+/// Parse a field set like `parseSelections` does, or `None` if it can't be
+/// parsed (which composition reports on its own).
+fn parse_selections(source: &str) -> Option<Vec<Selection_>> {
+    let document = query::parse_query::<String>(&format!("{{{}}}", source))
+        .ok()?
+        .into_static();
+    match document.definitions.into_iter().next() {
+        Some(query::Definition::Operation(OperationDefinition::SelectionSet(selection_set))) => {
+            Some(selection_set.items)
+        }
+        _ => None,
+    }
+}
+
+/// The names of the fields which a field set selects at its top level.
+fn selected_field_names(selections: &[Selection_]) -> impl Iterator<Item = &str> {
+    selections.iter().filter_map(|selection| match selection {
+        Selection::Field(field) => Some(field.name.as_str()),
+        _ => None,
+    })
+}
+
+/// Whether a directive like `@key` selects a field with the given name at the
+/// top level of its field set, like `hasMatchingFieldInDirectives`.
+fn selects_field(directive: &Directive_, field_name: &str) -> bool {
+    match field_set_argument(directive).and_then(parse_selections) {
+        Some(selections) => selected_field_names(&selections).any(|name| name == field_name),
+        None => false,
+    }
+}
+
+/// The name of the type which a (list or non-null) type wraps.
+fn named_type<'t>(ty: &'t Type<'static, String>) -> &'t str {
+    match ty {
+        Type::NamedType(name) => name,
+        Type::ListType(ty) | Type::NonNullType(ty) => named_type(ty),
+    }
+}
+
+fn definition_position(definition: &TypeDefinition<'static, String>) -> Pos {
+    match definition {
+        TypeDefinition::Scalar(scalar) => scalar.position,
+        TypeDefinition::Object(object) => object.position,
+        TypeDefinition::Interface(interface) => interface.position,
+        TypeDefinition::Union(union) => union.position,
+        TypeDefinition::Enum(enum_type) => enum_type.position,
+        TypeDefinition::InputObject(input) => input.position,
+    }
+}
+
+fn extension_position(extension: &TypeExtension<'static, String>) -> Pos {
+    match extension {
+        TypeExtension::Scalar(scalar) => scalar.position,
+        TypeExtension::Object(object) => object.position,
+        TypeExtension::Interface(interface) => interface.position,
+        TypeExtension::Union(union) => union.position,
+        TypeExtension::Enum(enum_type) => enum_type.position,
+        TypeExtension::InputObject(input) => input.position,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_prefix;
+
+    #[test]
+    fn it_splits_prefixes_like_serialize_error() {
+        assert_eq!(
+            split_prefix("[users] User.name -> is marked as @external"),
+            Some(("users", "User.name"))
+        );
+        assert_eq!(
+            split_prefix("[users] User -> oops"),
+            Some(("users", "User"))
+        );
+        assert_eq!(split_prefix("[@stream] -> oops"), None);
+        assert_eq!(split_prefix("[users] User.name.first -> oops"), None);
+        assert_eq!(
+            split_prefix("There can be only one type named \"User\"."),
+            None
+        );
+    }
+}
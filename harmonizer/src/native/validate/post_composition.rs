@@ -0,0 +1,1628 @@
+//! A port of the [rules] which `composeAndValidate` applies to the schema
+//! which the subgraphs were composed into.
+//!
+//! Like `validateComposedSchema`, these look at the subgraphs as they were
+//! given (rather than as they were normalized) to locate their errors.
+//!
+//! [rules]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition
+
+use super::{
+    error_with_code, find_directives, log_directive, log_service_and_type, named_type,
+    parse_selections, selects_field, Field_, Node, Selection_,
+};
+use crate::native::compose::{FieldMetadata, Schema, TypeMetadata, SPECIFIED_SCALARS};
+use crate::native::Subgraph;
+use crate::{CompositionError, CompositionErrorCode};
+use graphql_parser::query::Selection;
+use graphql_parser::schema::{
+    Definition, Directive, DirectiveDefinition, ObjectType, Type, TypeDefinition, TypeExtension,
+};
+use graphql_parser::Pos;
+use std::collections::HashSet;
+
+type ObjectType_ = ObjectType<'static, String>;
+type Type_ = Type<'static, String>;
+
+pub(super) fn validate(schema: &Schema, subgraphs: &[Subgraph]) -> Vec<CompositionError> {
+    let context = Context::new(schema, subgraphs);
+    let mut errors = context.external_unused();
+    errors.extend(context.external_missing_on_base());
+    errors.extend(context.external_type_mismatch());
+    errors.extend(context.requires_fields_missing_external());
+    errors.extend(context.requires_fields_missing_on_base());
+    errors.extend(context.key_fields_missing_on_base());
+    errors.extend(context.key_fields_select_invalid_type());
+    errors.extend(context.provides_fields_missing_external());
+    errors.extend(context.provides_fields_select_invalid_type());
+    errors.extend(context.provides_not_on_entity());
+    errors.extend(context.executable_directives_in_all_services());
+    errors.extend(context.executable_directives_identical());
+    errors.extend(context.keys_match_base_service());
+    errors
+}
+
+/// The composed schema, along with the subgraphs it was composed from.
+struct Context<'a> {
+    schema: &'a Schema,
+    subgraphs: &'a [Subgraph],
+    /// The object types of the schema, sorted by name like
+    /// `lexicographicSortSchema` sorts them.
+    object_types: Vec<(&'a str, &'a ObjectType_)>,
+    /// The scalars defined by the GraphQL specification which the schema
+    /// refers to, and so has (along with `String` and `Boolean`, which the
+    /// directives of every schema refer to).
+    specified_scalars: HashSet<&'a str>,
+}
+
+impl<'a> Context<'a> {
+    fn new(schema: &'a Schema, subgraphs: &'a [Subgraph]) -> Self {
+        let mut object_types: Vec<(&str, &ObjectType_)> = schema
+            .types
+            .iter()
+            .filter_map(|(name, definition)| match definition {
+                TypeDefinition::Object(object) => Some((name.as_str(), object)),
+                _ => None,
+            })
+            .collect();
+        object_types.sort_by_key(|(name, _)| *name);
+
+        let mut specified_scalars: HashSet<&str> = ["String", "Boolean"].iter().copied().collect();
+        let mut refer_to = |ty: &'a Type_| {
+            let name = named_type(ty);
+            if SPECIFIED_SCALARS.contains(&name) {
+                specified_scalars.insert(name);
+            }
+        };
+        for definition in schema.types.values() {
+            let fields = match definition {
+                TypeDefinition::Object(object) => &object.fields,
+                TypeDefinition::Interface(interface) => &interface.fields,
+                TypeDefinition::InputObject(input) => {
+                    input
+                        .fields
+                        .iter()
+                        .for_each(|field| refer_to(&field.value_type));
+                    continue;
+                }
+                _ => continue,
+            };
+            for field in fields {
+                refer_to(&field.field_type);
+                for argument in &field.arguments {
+                    refer_to(&argument.value_type);
+                }
+            }
+        }
+        for directive in &schema.directives {
+            for argument in &directive.arguments {
+                refer_to(&argument.value_type);
+            }
+        }
+
+        Context {
+            schema,
+            subgraphs,
+            object_types,
+            specified_scalars,
+        }
+    }
+
+    /// Every `@external` field must be used by a `@key`, `@requires` or
+    /// `@provides`, or be part of an interface, like [`externalUnused`].
+    ///
+    /// [`externalUnused`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/externalUnused.ts
+    fn external_unused(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for &(type_name, object) in &self.object_types {
+            // Entities whose owner has no keys are reported by
+            // `keys_match_base_service` instead.
+            if let Some(TypeMetadata {
+                service_name: Some(owner),
+                keys: Some(keys),
+                ..
+            }) = self.type_metadata(type_name)
+            {
+                if !keys.iter().any(|(service_name, _)| service_name == owner) {
+                    continue;
+                }
+            }
+
+            for (service_name, external_fields) in self.externals(type_name) {
+                for external_field in external_fields {
+                    let field_name = external_field.name.as_str();
+                    let is_used = find_directives(&object.directives, "key")
+                        .any(|key| selects_field(key, field_name))
+                        || self.is_provided(type_name, field_name)
+                        || self.is_required(type_name, field_name)
+                        || object.fields.iter().any(|field| {
+                            let requiring_service = self
+                                .field_metadata(type_name, &field.name)
+                                .and_then(|metadata| metadata.service_name.as_deref());
+                            requiring_service == Some(service_name.as_str())
+                                && find_directives(&field.directives, "requires")
+                                    .any(|requires| selects_field(requires, field_name))
+                        })
+                        || object.implements_interfaces.iter().any(|interface| {
+                            match self.schema.types.get(interface) {
+                                Some(TypeDefinition::Interface(interface)) => interface
+                                    .fields
+                                    .iter()
+                                    .any(|field| field.name == field_name),
+                                _ => false,
+                            }
+                        });
+                    if is_used {
+                        continue;
+                    }
+
+                    errors.push(error_with_code(
+                        CompositionErrorCode::ExternalUnused,
+                        format!(
+                            "{}is marked as @external but is not used by a @requires, @key, or @provides directive.",
+                            log_service_and_type(service_name, type_name, Some(field_name)),
+                        ),
+                        &external_directives(service_name, external_field),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Whether a `@provides` on any field which returns the type selects the
+    /// field.
+    fn is_provided(&self, type_name: &str, field_name: &str) -> bool {
+        self.object_types.iter().any(|(_, object)| {
+            object.fields.iter().any(|field| {
+                named_type(&field.field_type) == type_name
+                    && find_directives(&field.directives, "provides")
+                        .any(|provides| selects_field(provides, field_name))
+            })
+        })
+    }
+
+    /// Whether a `@requires` on any field selects the field of the type,
+    /// however deep within its field set.
+    fn is_required(&self, type_name: &str, field_name: &str) -> bool {
+        self.object_types.iter().any(|(requiring_type, object)| {
+            object.fields.iter().any(|field| {
+                find_directives(&field.directives, "requires").any(|requires| {
+                    match super::field_set_argument(requires).and_then(parse_selections) {
+                        Some(selections) => self.selection_includes_field(
+                            &selections,
+                            requiring_type,
+                            type_name,
+                            field_name,
+                        ),
+                        None => false,
+                    }
+                })
+            })
+        })
+    }
+
+    /// Whether `selections` of the type `selection_set_type` select the
+    /// field of `type_to_find`, like `selectionIncludesField`.
+    fn selection_includes_field(
+        &self,
+        selections: &[Selection_],
+        selection_set_type: &str,
+        type_to_find: &str,
+        field_to_find: &str,
+    ) -> bool {
+        for selection in selections {
+            let selected = match selection {
+                Selection::Field(selected) => selected,
+                _ => continue,
+            };
+            if selected.name == field_to_find && selection_set_type == type_to_find {
+                return true;
+            }
+
+            let field = match self.object_field(selection_set_type, &selected.name) {
+                Some(field) => field,
+                None => continue,
+            };
+            let return_type = named_type(&field.field_type);
+            if self.object_type(return_type).is_none() {
+                continue;
+            }
+            if self.selection_includes_field(
+                &selected.selection_set.items,
+                return_type,
+                type_to_find,
+                field_to_find,
+            ) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Every `@external` field must be a field of the subgraph which owns the
+    /// type, like [`externalMissingOnBase`].
+    ///
+    /// [`externalMissingOnBase`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/externalMissingOnBase.ts
+    fn external_missing_on_base(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for &(type_name, object) in &self.object_types {
+            for (service_name, external_fields) in self.externals(type_name) {
+                for external_field in external_fields {
+                    let field_name = external_field.name.as_str();
+                    let message = match object.fields.iter().find(|field| field.name == field_name) {
+                        None => format!(
+                            "marked @external but {} is not defined on the base service of {} ({})",
+                            field_name,
+                            type_name,
+                            self.owner(type_name),
+                        ),
+                        Some(_) => match self
+                            .field_metadata(type_name, field_name)
+                            .and_then(|metadata| metadata.service_name.as_deref())
+                        {
+                            Some(field_service_name) => format!(
+                                "marked @external but {} was defined in {}, not in the service that owns {} ({})",
+                                field_name,
+                                field_service_name,
+                                type_name,
+                                self.owner(type_name),
+                            ),
+                            None => continue,
+                        },
+                    };
+                    errors.push(error_with_code(
+                        CompositionErrorCode::ExternalMissingOnBase,
+                        format!(
+                            "{}{}",
+                            log_service_and_type(service_name, type_name, Some(field_name)),
+                            message,
+                        ),
+                        &external_directives(service_name, external_field),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Every `@external` field must be of the same type as the field of the
+    /// subgraph which owns the type, like [`externalTypeMismatch`].
+    ///
+    /// [`externalTypeMismatch`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/externalTypeMismatch.ts
+    fn external_type_mismatch(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for &(type_name, object) in &self.object_types {
+            for (service_name, external_fields) in self.externals(type_name) {
+                for external_field in external_fields {
+                    let field_name = external_field.name.as_str();
+                    let message = if !self.has_type(named_type(&external_field.field_type)) {
+                        "the type of the @external field does not exist in the resulting composed schema"
+                            .to_string()
+                    } else {
+                        match object.fields.iter().find(|field| field.name == field_name) {
+                            Some(base_field)
+                                if base_field.field_type != external_field.field_type =>
+                            {
+                                format!(
+                                    "Type `{}` does not match the type of the original field in {} (`{}`)",
+                                    external_field.field_type,
+                                    self.owner(type_name),
+                                    base_field.field_type,
+                                )
+                            }
+                            _ => continue,
+                        }
+                    };
+                    errors.push(error_with_code(
+                        CompositionErrorCode::ExternalTypeMismatch,
+                        format!(
+                            "{}{}",
+                            log_service_and_type(service_name, type_name, Some(field_name)),
+                            message,
+                        ),
+                        &[(service_name, external_field.position)],
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// The fields which a `@requires` selects must be `@external` fields of
+    /// the subgraph, like [`requiresFieldsMissingExternal`].
+    ///
+    /// [`requiresFieldsMissingExternal`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/requiresFieldsMissingExternal.ts
+    fn requires_fields_missing_external(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for (type_name, field_name, service_name, requires) in self.required_fields() {
+            let externals = self
+                .externals(type_name)
+                .iter()
+                .find(|(external_service_name, _)| external_service_name == service_name);
+            for selected in selected_field_names(requires) {
+                let is_external = match externals {
+                    Some((_, fields)) => fields.iter().any(|field| field.name == selected),
+                    None => false,
+                };
+                if is_external {
+                    continue;
+                }
+                errors.push(error_with_code(
+                    CompositionErrorCode::RequiresFieldsMissingExternal,
+                    format!(
+                        "{}requires the field `{}` to be marked as @external.",
+                        log_service_and_type(service_name, type_name, Some(field_name)),
+                        selected,
+                    ),
+                    &self.field_directive_nodes(
+                        type_name,
+                        service_name,
+                        field_name,
+                        "requires",
+                        Some(requires),
+                    ),
+                ));
+            }
+        }
+        errors
+    }
+
+    /// The fields which a `@requires` selects must belong to the subgraph
+    /// which owns the type, like [`requiresFieldsMissingOnBase`].
+    ///
+    /// [`requiresFieldsMissingOnBase`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/requiresFieldsMissingOnBase.ts
+    fn requires_fields_missing_on_base(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for (type_name, field_name, service_name, requires) in self.required_fields() {
+            for selected in selected_field_names(requires) {
+                let is_extension_field = self
+                    .field_metadata(type_name, &selected)
+                    .and_then(|metadata| metadata.service_name.as_ref())
+                    .is_some();
+                if !is_extension_field {
+                    continue;
+                }
+                errors.push(error_with_code(
+                    CompositionErrorCode::RequiresFieldsMissingOnBase,
+                    format!(
+                        "{}requires the field `{}` to be @external. @external fields must exist on the base type, not an extension.",
+                        log_service_and_type(service_name, type_name, Some(field_name)),
+                        selected,
+                    ),
+                    &self.field_directive_nodes(
+                        type_name,
+                        service_name,
+                        field_name,
+                        "requires",
+                        Some(requires),
+                    ),
+                ));
+            }
+        }
+        errors
+    }
+
+    /// The fields of extensions with a `@requires`, along with the subgraph
+    /// which extends the type with them and their (printed) field set.
+    fn required_fields(&self) -> Vec<(&'a str, &'a str, &'a str, &'a str)> {
+        let mut required_fields = Vec::new();
+        for &(type_name, object) in &self.object_types {
+            for field in &object.fields {
+                if let Some(FieldMetadata {
+                    service_name: Some(service_name),
+                    requires: Some(requires),
+                    ..
+                }) = self.field_metadata(type_name, &field.name)
+                {
+                    required_fields.push((
+                        type_name,
+                        field.name.as_str(),
+                        service_name.as_str(),
+                        requires.as_str(),
+                    ));
+                }
+            }
+        }
+        required_fields
+    }
+
+    /// The fields which a `@key` selects must belong to the subgraph which
+    /// owns the type, like [`keyFieldsMissingOnBase`].
+    ///
+    /// [`keyFieldsMissingOnBase`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/keyFieldsMissingOnBase.ts
+    fn key_fields_missing_on_base(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for (type_name, object, service_name, key) in self.keys() {
+            for selected in selected_field_names(key) {
+                if !object.fields.iter().any(|field| field.name == selected) {
+                    continue;
+                }
+                let field_service_name = match self
+                    .field_metadata(type_name, &selected)
+                    .and_then(|metadata| metadata.service_name.as_deref())
+                {
+                    Some(field_service_name) => field_service_name,
+                    None => continue,
+                };
+                errors.push(error_with_code(
+                    CompositionErrorCode::KeyFieldsMissingOnBase,
+                    format!(
+                        "{}A @key selects {selected}, but {}.{selected} was either created or overwritten by {}, not {}",
+                        log_service_and_type(service_name, type_name, None),
+                        type_name,
+                        field_service_name,
+                        service_name,
+                        selected = selected,
+                    ),
+                    &self.type_directive_nodes(type_name, service_name, key),
+                ));
+            }
+        }
+        errors
+    }
+
+    /// The fields which a `@key` selects must exist, and can't be interfaces
+    /// or unions, like [`keyFieldsSelectInvalidType`].
+    ///
+    /// [`keyFieldsSelectInvalidType`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/keyFieldsSelectInvalidType.ts
+    fn key_fields_select_invalid_type(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for (type_name, object, service_name, key) in self.keys() {
+            let nodes = self.type_directive_nodes(type_name, service_name, key);
+            for selected in selected_field_names(key) {
+                let message = match object.fields.iter().find(|field| field.name == selected) {
+                    None => format!(
+                        "A @key selects {}, but {}.{} could not be found",
+                        selected, type_name, selected
+                    ),
+                    Some(field) => match nullable_named_type(&field.field_type)
+                        .and_then(|name| self.schema.types.get(name))
+                    {
+                        Some(TypeDefinition::Interface(_)) => format!(
+                            "A @key selects {}.{}, which is an interface type. Keys cannot select interfaces.",
+                            type_name, selected
+                        ),
+                        Some(TypeDefinition::Union(_)) => format!(
+                            "A @key selects {}.{}, which is a union type. Keys cannot select union types.",
+                            type_name, selected
+                        ),
+                        _ => continue,
+                    },
+                };
+                errors.push(error_with_code(
+                    CompositionErrorCode::KeyFieldsSelectInvalidType,
+                    format!(
+                        "{}{}",
+                        log_service_and_type(service_name, type_name, None),
+                        message
+                    ),
+                    &nodes,
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Every (printed) field set of the `@key`s of every object type, along
+    /// with the subgraph it came from.
+    fn keys(&self) -> Vec<(&'a str, &'a ObjectType_, &'a str, &'a str)> {
+        let mut keys = Vec::new();
+        for &(type_name, object) in &self.object_types {
+            let by_service = match self.type_metadata(type_name) {
+                Some(TypeMetadata {
+                    keys: Some(by_service),
+                    ..
+                }) => by_service,
+                _ => continue,
+            };
+            for (service_name, field_sets) in by_service {
+                for key in field_sets {
+                    keys.push((type_name, object, service_name.as_str(), key.as_str()));
+                }
+            }
+        }
+        keys
+    }
+
+    /// The fields which a `@provides` selects must be `@external` fields of
+    /// the subgraph, like [`providesFieldsMissingExternal`].
+    ///
+    /// [`providesFieldsMissingExternal`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/providesFieldsMissingExternal.ts
+    fn provides_fields_missing_external(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for (type_name, field, service_name, provides) in self.provided_fields() {
+            let field_type = match self.provided_type(field) {
+                Some((field_type, _)) => field_type,
+                None => continue,
+            };
+            let externals = self
+                .externals(field_type)
+                .iter()
+                .find(|(external_service_name, _)| external_service_name == service_name);
+            for selected in selected_field_names(provides) {
+                let is_external = match externals {
+                    Some((_, fields)) => fields.iter().any(|field| field.name == selected),
+                    None => false,
+                };
+                if is_external {
+                    continue;
+                }
+                // Like `providesFieldsMissingExternal`, this points at the
+                // field of the providing type with the name of the provided
+                // field, if there is one.
+                let nodes: Vec<Node<'_>> = self
+                    .type_node(type_name, service_name)
+                    .and_then(|type_node| type_node.field(&selected))
+                    .map(|field| (service_name, field.position))
+                    .into_iter()
+                    .collect();
+                errors.push(error_with_code(
+                    CompositionErrorCode::ProvidesFieldsMissingExternal,
+                    format!(
+                        "{}provides the field `{selected}` and requires {}.{selected} to be marked as @external.",
+                        log_service_and_type(service_name, type_name, Some(&field.name)),
+                        field_type,
+                        selected = selected,
+                    ),
+                    &nodes,
+                ));
+            }
+        }
+        errors
+    }
+
+    /// The fields which a `@provides` selects must exist, and can't be lists,
+    /// interfaces or unions, like [`providesFieldsSelectInvalidType`].
+    ///
+    /// [`providesFieldsSelectInvalidType`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/providesFieldsSelectInvalidType.ts
+    fn provides_fields_select_invalid_type(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for (type_name, field, service_name, provides) in self.provided_fields() {
+            let (field_type, provided_object) = match self.provided_type(field) {
+                Some(provided_type) => provided_type,
+                None => continue,
+            };
+            let nodes = self.field_directive_nodes(
+                type_name,
+                service_name,
+                &field.name,
+                "provides",
+                Some(provides),
+            );
+            for selected in selected_field_names(provides) {
+                let mut messages = Vec::new();
+                match provided_object
+                    .fields
+                    .iter()
+                    .find(|field| field.name == selected)
+                {
+                    None => messages.push(format!(
+                        "A @provides selects {selected}, but {}.{selected} could not be found",
+                        field_type,
+                        selected = selected,
+                    )),
+                    Some(provided_field) => {
+                        if is_list_type(&provided_field.field_type) {
+                            messages.push(format!(
+                                "A @provides selects {}.{}, which is a list type. A field cannot @provide lists.",
+                                field_type, selected
+                            ));
+                        }
+                        match nullable_named_type(&provided_field.field_type)
+                            .and_then(|name| self.schema.types.get(name))
+                        {
+                            Some(TypeDefinition::Interface(_)) => messages.push(format!(
+                                "A @provides selects {}.{}, which is an interface type. A field cannot @provide interfaces.",
+                                field_type, selected
+                            )),
+                            Some(TypeDefinition::Union(_)) => messages.push(format!(
+                                "A @provides selects {}.{}, which is a union type. A field cannot @provide union types.",
+                                field_type, selected
+                            )),
+                            _ => {}
+                        }
+                    }
+                }
+                for message in messages {
+                    errors.push(error_with_code(
+                        CompositionErrorCode::ProvidesFieldsSelectInvalidType,
+                        format!(
+                            "{}{}",
+                            log_service_and_type(service_name, type_name, Some(&field.name)),
+                            message,
+                        ),
+                        &nodes,
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// A field with a `@provides` must return an entity, or a list of them,
+    /// like [`providesNotOnEntity`].
+    ///
+    /// [`providesNotOnEntity`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/providesNotOnEntity.ts
+    fn provides_not_on_entity(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for (type_name, field, service_name, _) in self.provided_fields() {
+            let base_type = named_type(&field.field_type);
+            let message = if self.object_type(base_type).is_none() {
+                format!(
+                    "uses the @provides directive but `{}.{}` returns `{}`, which is not an Object or List type. @provides can only be used on Object types with at least one @key, or Lists of such Objects.",
+                    type_name, field.name, field.field_type
+                )
+            } else if let Some(TypeMetadata { keys: Some(_), .. }) = self.type_metadata(base_type) {
+                continue;
+            } else {
+                format!(
+                    "uses the @provides directive but `{}.{}` does not return a type that has a @key. Try adding a @key to the `{}` type.",
+                    type_name, field.name, base_type
+                )
+            };
+            errors.push(error_with_code(
+                CompositionErrorCode::ProvidesNotOnEntity,
+                format!(
+                    "{}{}",
+                    log_service_and_type(service_name, type_name, Some(&field.name)),
+                    message,
+                ),
+                &self.field_directive_nodes(type_name, service_name, &field.name, "provides", None),
+            ));
+        }
+        errors
+    }
+
+    /// The fields with a `@provides` whose type belongs to a subgraph, along
+    /// with that subgraph and their (printed) field set.
+    fn provided_fields(&self) -> Vec<(&'a str, &'a Field_, &'a str, &'a str)> {
+        let mut provided_fields = Vec::new();
+        for &(type_name, object) in &self.object_types {
+            for field in &object.fields {
+                if let Some(FieldMetadata {
+                    service_name: Some(service_name),
+                    provides: Some(provides),
+                    ..
+                }) = self.field_metadata(type_name, &field.name)
+                {
+                    provided_fields.push((
+                        type_name,
+                        field,
+                        service_name.as_str(),
+                        provides.as_str(),
+                    ));
+                }
+            }
+        }
+        provided_fields
+    }
+
+    /// The object type which a field with a `@provides` returns, if it
+    /// returns one (rather than a list of them, or a non-null one).
+    fn provided_type(&self, field: &'a Field_) -> Option<(&'a str, &'a ObjectType_)> {
+        match &field.field_type {
+            Type::NamedType(name) => Some((name, self.object_type(name)?)),
+            _ => None,
+        }
+    }
+
+    /// Every subgraph must define every custom executable directive, like
+    /// [`executableDirectivesInAllServices`].
+    ///
+    /// [`executableDirectivesInAllServices`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/executableDirectivesInAllServices.ts
+    fn executable_directives_in_all_services(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for directive in &self.schema.directives {
+            let definitions = match self.schema.directive_definitions.get(&directive.name) {
+                Some(definitions) => definitions,
+                None => continue,
+            };
+            let without_directive: Vec<&str> = self
+                .subgraphs
+                .iter()
+                .map(|subgraph| subgraph.name.as_str())
+                .filter(|name| {
+                    !definitions
+                        .iter()
+                        .any(|(service_name, _)| service_name == name)
+                })
+                .collect();
+            if without_directive.is_empty() {
+                continue;
+            }
+
+            let nodes: Vec<Node<'_>> = definitions
+                .first()
+                .map(|(service_name, definition)| (service_name.as_str(), definition.position))
+                .into_iter()
+                .collect();
+            errors.push(error_with_code(
+                CompositionErrorCode::ExecutableDirectivesInAllServices,
+                format!(
+                    "{}Custom directives must be implemented in every service. The following services do not implement the @{} directive: {}.",
+                    log_directive(&directive.name),
+                    directive.name,
+                    without_directive.join(", "),
+                ),
+                &nodes,
+            ));
+        }
+        errors
+    }
+
+    /// Every subgraph must define each custom executable directive the same
+    /// way, like [`executableDirectivesIdentical`].
+    ///
+    /// [`executableDirectivesIdentical`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/executableDirectivesIdentical.ts
+    fn executable_directives_identical(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for directive in &self.schema.directives {
+            let definitions = match self.schema.directive_definitions.get(&directive.name) {
+                Some(definitions) => definitions,
+                None => continue,
+            };
+            if definitions
+                .windows(2)
+                .all(|pair| directive_definitions_are_equivalent(&pair[0].1, &pair[1].1))
+            {
+                continue;
+            }
+
+            let implementations: Vec<String> = definitions
+                .iter()
+                .map(|(service_name, definition)| {
+                    format!("\t{}: {}", service_name, definition.to_string().trim_end())
+                })
+                .collect();
+            let nodes: Vec<Node<'_>> = definitions
+                .iter()
+                .map(|(service_name, definition)| (service_name.as_str(), definition.position))
+                .collect();
+            errors.push(error_with_code(
+                CompositionErrorCode::ExecutableDirectivesIdentical,
+                format!(
+                    "{}custom directives must be defined identically across all services. See below for a list of current implementations:\n{}",
+                    log_directive(&directive.name),
+                    implementations.join("\n"),
+                ),
+                &nodes,
+            ));
+        }
+        errors
+    }
+
+    /// The subgraph which owns an entity must declare its keys, and every
+    /// subgraph which extends it must declare just one of them, like
+    /// [`keysMatchBaseService`].
+    ///
+    /// [`keysMatchBaseService`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/postComposition/keysMatchBaseService.ts
+    fn keys_match_base_service(&self) -> Vec<CompositionError> {
+        let mut errors = Vec::new();
+        for &(type_name, _) in &self.object_types {
+            let (owner, keys) = match self.type_metadata(type_name) {
+                Some(TypeMetadata {
+                    service_name: Some(owner),
+                    keys: Some(keys),
+                    ..
+                }) => (owner.as_str(), keys),
+                _ => continue,
+            };
+
+            let available_keys = match keys.iter().find(|(service_name, _)| service_name == owner) {
+                Some((_, available_keys)) => available_keys,
+                None => {
+                    errors.push(error_with_code(
+                        CompositionErrorCode::KeyMissingOnBase,
+                        format!(
+                            "{}appears to be an entity but no @key directives are specified on the originating type.",
+                            log_service_and_type(owner, type_name, None),
+                        ),
+                        &self.type_nodes(type_name, owner),
+                    ));
+                    continue;
+                }
+            };
+
+            for (service_name, field_sets) in keys {
+                if service_name == owner {
+                    continue;
+                }
+                if field_sets.len() > 1 {
+                    errors.push(error_with_code(
+                        CompositionErrorCode::MultipleKeysOnExtension,
+                        format!(
+                            "{}is extended from service {} but specifies multiple @key directives. Extensions may only specify one @key.",
+                            log_service_and_type(service_name, type_name, None),
+                            owner,
+                        ),
+                        &self.type_nodes(type_name, service_name),
+                    ));
+                    continue;
+                }
+
+                let key = &field_sets[0];
+                if available_keys.contains(key) {
+                    continue;
+                }
+                let available_keys: Vec<String> = available_keys
+                    .iter()
+                    .map(|field_set| format!("@key(fields: \"{}\")", field_set))
+                    .collect();
+                errors.push(error_with_code(
+                    CompositionErrorCode::KeyNotSpecified,
+                    format!(
+                        "{}extends from {} but specifies an invalid @key directive. Valid @key directives are specified by the originating type. Available @key directives for this type are:\n\t{}",
+                        log_service_and_type(service_name, type_name, None),
+                        owner,
+                        available_keys.join("\n\t"),
+                    ),
+                    &self.type_directive_nodes(type_name, service_name, key),
+                ));
+            }
+        }
+        errors
+    }
+
+    fn type_metadata(&self, type_name: &str) -> Option<&'a TypeMetadata> {
+        self.schema.type_metadata.get(type_name)
+    }
+
+    fn field_metadata(&self, type_name: &str, field_name: &str) -> Option<&'a FieldMetadata> {
+        self.schema
+            .field_metadata
+            .get(&(type_name.to_string(), field_name.to_string()))
+    }
+
+    /// The `@external` fields which subgraphs extend the type with, by
+    /// subgraph.
+    fn externals(&self, type_name: &str) -> &'a [(String, Vec<Field_>)] {
+        match self.type_metadata(type_name) {
+            Some(metadata) => &metadata.externals,
+            None => &[],
+        }
+    }
+
+    /// The subgraph which owns a type, as the messages which mention it
+    /// print it.
+    fn owner(&self, type_name: &str) -> &'a str {
+        match self.type_metadata(type_name) {
+            Some(TypeMetadata {
+                service_name: Some(owner),
+                ..
+            }) => owner,
+            Some(TypeMetadata {
+                is_value_type: true,
+                ..
+            }) => "null",
+            _ => "undefined",
+        }
+    }
+
+    fn has_type(&self, name: &str) -> bool {
+        self.schema.has_type(name) || self.specified_scalars.contains(name)
+    }
+
+    fn object_type(&self, name: &str) -> Option<&'a ObjectType_> {
+        match self.schema.types.get(name) {
+            Some(TypeDefinition::Object(object)) => Some(object),
+            _ => None,
+        }
+    }
+
+    fn object_field(&self, type_name: &str, field_name: &str) -> Option<&'a Field_> {
+        self.object_type(type_name)?
+            .fields
+            .iter()
+            .find(|field| field.name == field_name)
+    }
+
+    /// The first definition or extension of a type within a subgraph, like
+    /// `findTypeNodeInServiceList`.
+    fn type_node(&self, type_name: &str, service_name: &str) -> Option<TypeNode<'a>> {
+        let subgraph = self
+            .subgraphs
+            .iter()
+            .find(|subgraph| subgraph.name == service_name)?;
+        subgraph
+            .document
+            .definitions
+            .iter()
+            .find_map(|definition| match definition {
+                Definition::TypeDefinition(definition)
+                    if super::super::compose::type_definition_name(definition) == type_name =>
+                {
+                    Some(TypeNode::Definition(definition))
+                }
+                Definition::TypeExtension(extension)
+                    if super::super::compose::type_extension_name(extension) == type_name =>
+                {
+                    Some(TypeNode::Extension(extension))
+                }
+                _ => None,
+            })
+    }
+
+    /// The node of a type within a subgraph.
+    fn type_nodes(&self, type_name: &str, service_name: &'a str) -> Vec<Node<'a>> {
+        self.type_node(type_name, service_name)
+            .map(|type_node| (service_name, type_node.position()))
+            .into_iter()
+            .collect()
+    }
+
+    /// The `@key` of a type within a subgraph with the given field set, like
+    /// `findSelectionSetOnNode`.
+    fn type_directive_nodes(
+        &self,
+        type_name: &str,
+        service_name: &'a str,
+        field_set: &str,
+    ) -> Vec<Node<'a>> {
+        self.type_node(type_name, service_name)
+            .and_then(|type_node| find_field_set(type_node.directives(), "key", field_set))
+            .map(|position| (service_name, position))
+            .into_iter()
+            .collect()
+    }
+
+    /// The directives with the given name on a field of a type within a
+    /// subgraph, or just the one with the given field set, like
+    /// `findSelectionSetOnNode`.
+    fn field_directive_nodes(
+        &self,
+        type_name: &str,
+        service_name: &'a str,
+        field_name: &str,
+        directive_name: &str,
+        field_set: Option<&str>,
+    ) -> Vec<Node<'a>> {
+        let field = match self
+            .type_node(type_name, service_name)
+            .and_then(|type_node| type_node.field(field_name))
+        {
+            Some(field) => field,
+            None => return Vec::new(),
+        };
+        match field_set {
+            Some(field_set) => find_field_set(&field.directives, directive_name, field_set)
+                .map(|position| (service_name, position))
+                .into_iter()
+                .collect(),
+            None => find_directives(&field.directives, directive_name)
+                .map(|directive| (service_name, directive.position))
+                .collect(),
+        }
+    }
+}
+
+/// A definition or an extension of a type within a subgraph.
+#[derive(Clone, Copy)]
+enum TypeNode<'a> {
+    Definition(&'a TypeDefinition<'static, String>),
+    Extension(&'a TypeExtension<'static, String>),
+}
+
+impl<'a> TypeNode<'a> {
+    fn position(self) -> Pos {
+        match self {
+            TypeNode::Definition(definition) => super::definition_position(definition),
+            TypeNode::Extension(extension) => super::extension_position(extension),
+        }
+    }
+
+    fn directives(self) -> &'a [Directive<'static, String>] {
+        match self {
+            TypeNode::Definition(TypeDefinition::Scalar(scalar)) => &scalar.directives,
+            TypeNode::Definition(TypeDefinition::Object(object)) => &object.directives,
+            TypeNode::Definition(TypeDefinition::Interface(interface)) => &interface.directives,
+            TypeNode::Definition(TypeDefinition::Union(union)) => &union.directives,
+            TypeNode::Definition(TypeDefinition::Enum(enum_type)) => &enum_type.directives,
+            TypeNode::Definition(TypeDefinition::InputObject(input)) => &input.directives,
+            TypeNode::Extension(TypeExtension::Scalar(scalar)) => &scalar.directives,
+            TypeNode::Extension(TypeExtension::Object(object)) => &object.directives,
+            TypeNode::Extension(TypeExtension::Interface(interface)) => &interface.directives,
+            TypeNode::Extension(TypeExtension::Union(union)) => &union.directives,
+            TypeNode::Extension(TypeExtension::Enum(enum_type)) => &enum_type.directives,
+            TypeNode::Extension(TypeExtension::InputObject(input)) => &input.directives,
+        }
+    }
+
+    fn field(self, field_name: &str) -> Option<&'a Field_> {
+        let fields = match self {
+            TypeNode::Definition(TypeDefinition::Object(object)) => &object.fields,
+            TypeNode::Definition(TypeDefinition::Interface(interface)) => &interface.fields,
+            TypeNode::Extension(TypeExtension::Object(object)) => &object.fields,
+            TypeNode::Extension(TypeExtension::Interface(interface)) => &interface.fields,
+            _ => return None,
+        };
+        fields.iter().find(|field| field.name == field_name)
+    }
+}
+
+/// The `@external` directives of an external field.
+fn external_directives<'a>(service_name: &'a str, field: &Field_) -> Vec<Node<'a>> {
+    find_directives(&field.directives, "external")
+        .map(|directive| (service_name, directive.position))
+        .collect()
+}
+
+/// The position of the directive with the given name whose argument is the
+/// given (printed) field set.
+fn find_field_set(
+    directives: &[Directive<'static, String>],
+    name: &str,
+    field_set: &str,
+) -> Option<Pos> {
+    find_directives(directives, name)
+        .find(|directive| {
+            directive.arguments.iter().any(|(_, value)| {
+                matches!(value, graphql_parser::schema::Value::String(value) if value == field_set)
+            })
+        })
+        .map(|directive| directive.position)
+}
+
+/// The names of the fields which a (printed) field set selects at its top
+/// level.
+fn selected_field_names(field_set: &str) -> Vec<String> {
+    match parse_selections(field_set) {
+        Some(selections) => super::selected_field_names(&selections)
+            .map(str::to_string)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// The named type of a type which may be non-null, but not a list.
+fn nullable_named_type(ty: &Type_) -> Option<&str> {
+    match ty {
+        Type::NamedType(name) => Some(name),
+        Type::NonNullType(ty) => match &**ty {
+            Type::NamedType(name) => Some(name),
+            _ => None,
+        },
+        Type::ListType(_) => None,
+    }
+}
+
+fn is_list_type(ty: &Type_) -> bool {
+    match ty {
+        Type::ListType(_) => true,
+        Type::NonNullType(ty) => matches!(&**ty, Type::ListType(_)),
+        Type::NamedType(_) => false,
+    }
+}
+
+/// Whether two definitions of a directive have the same locations and
+/// arguments of the same types, like `typeNodesAreEquivalent`.
+fn directive_definitions_are_equivalent(
+    first: &DirectiveDefinition<'static, String>,
+    second: &DirectiveDefinition<'static, String>,
+) -> bool {
+    let locations = |definition: &DirectiveDefinition<'static, String>| -> HashSet<String> {
+        definition
+            .locations
+            .iter()
+            .map(|location| location.as_str().to_string())
+            .collect()
+    };
+    let arguments =
+        |definition: &DirectiveDefinition<'static, String>| -> HashSet<(String, String)> {
+            definition
+                .arguments
+                .iter()
+                .map(|argument| (argument.name.clone(), argument.value_type.to_string()))
+                .collect()
+        };
+    locations(first) == locations(second) && arguments(first) == arguments(second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Context;
+    use crate::native::compose::compose_services;
+    use crate::native::{compose, normalize_subgraphs, parse_subgraphs};
+    use crate::{CompositionError, HarmonizerError, ServiceDefinition};
+
+    fn composition_errors(service_list: &[ServiceDefinition]) -> Vec<CompositionError> {
+        match compose(service_list) {
+            Err(HarmonizerError::Composition(errors)) => errors,
+            result => panic!("expected composition errors, got {:?}", result),
+        }
+    }
+
+    /// The errors of a single rule, run on the schema the subgraphs were
+    /// composed into (whether or not composition succeeded), like the tests
+    /// of `federation-js` run them.
+    fn validate_with<R>(subgraphs: &[(&str, &str)], rule: R) -> Vec<CompositionError>
+    where
+        R: Fn(&Context<'_>) -> Vec<CompositionError>,
+    {
+        let service_list: Vec<ServiceDefinition> = subgraphs
+            .iter()
+            .map(|(name, type_defs)| ServiceDefinition::new(*name, "undefined", *type_defs))
+            .collect();
+        let subgraphs = parse_subgraphs(&service_list).unwrap();
+        let (schema, _) = compose_services(&normalize_subgraphs(&subgraphs));
+        rule(&Context::new(&schema, &subgraphs))
+    }
+
+    fn codes_and_messages(errors: &[CompositionError]) -> Vec<(&str, &str)> {
+        errors
+            .iter()
+            .map(|error| (error.code(), error.message.as_deref().unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn it_reports_unused_external_fields() {
+        let errors = composition_errors(&[
+            ServiceDefinition::new(
+                "accounts",
+                "undefined",
+                r#"type Query { me: User } type User @key(fields: "id") { id: ID! name: String }"#,
+            ),
+            ServiceDefinition::new(
+                "reviews",
+                "undefined",
+                r#"extend type User @key(fields: "id") { id: ID! @external name: String @external reviews: [String] }"#,
+            ),
+        ]);
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].code(), "EXTERNAL_UNUSED");
+        assert_eq!(
+            errors[0].message.as_deref(),
+            Some("[reviews] User.name -> is marked as @external but is not used by a @requires, @key, or @provides directive.")
+        );
+        assert_eq!(errors[0].coordinate.as_deref(), Some("User.name"));
+        assert_eq!(errors[0].locations[0].subgraph.as_deref(), Some("reviews"));
+    }
+
+    #[test]
+    fn it_reports_extensions_with_keys_the_owner_does_not_have() {
+        let errors = composition_errors(&[
+            ServiceDefinition::new(
+                "accounts",
+                "undefined",
+                r#"type Query { me: User } type User @key(fields: "id") { id: ID! email: String }"#,
+            ),
+            ServiceDefinition::new(
+                "reviews",
+                "undefined",
+                r#"extend type User @key(fields: "email") { email: String @external reviews: [String] }"#,
+            ),
+        ]);
+
+        // Like `externalUnused`, only the `@key`s of the owner count as uses.
+        let codes: Vec<&str> = errors.iter().map(|error| error.code()).collect();
+        assert_eq!(codes, vec!["EXTERNAL_UNUSED", "KEY_NOT_SPECIFIED"]);
+        assert_eq!(
+            errors[1].message.as_deref(),
+            Some("[reviews] User -> extends from accounts but specifies an invalid @key directive. Valid @key directives are specified by the originating type. Available @key directives for this type are:\n\t@key(fields: \"id\")")
+        );
+    }
+
+    #[test]
+    fn it_reports_external_fields_missing_on_the_base_type() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") { sku: String! upc: String! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product @key(fields: "sku") { sku: String! @external id: String! @external price: Int! @requires(fields: "sku id") }"#,
+                ),
+                (
+                    "serviceC",
+                    r#"extend type Product @key(fields: "sku") { sku: String! @external id: String! test: Int @external }"#,
+                ),
+            ],
+            |context| context.external_missing_on_base(),
+        );
+
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![
+                ("EXTERNAL_MISSING_ON_BASE", "[serviceB] Product.id -> marked @external but id was defined in serviceC, not in the service that owns Product (serviceA)"),
+                ("EXTERNAL_MISSING_ON_BASE", "[serviceC] Product.test -> marked @external but test is not defined on the base service of Product (serviceA)"),
+            ]
+        );
+        assert_eq!(errors[0].locations[0].subgraph.as_deref(), Some("serviceB"));
+    }
+
+    #[test]
+    fn it_reports_external_fields_of_another_type_than_the_base_field() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku skew") { sku: String! skew: String upc: String! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product { sku: String @external skew: String! @external price: Int! @requires(fields: "sku skew") }"#,
+                ),
+            ],
+            |context| context.external_type_mismatch(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![
+                ("EXTERNAL_TYPE_MISMATCH", "[serviceB] Product.sku -> Type `String` does not match the type of the original field in serviceA (`String!`)"),
+                ("EXTERNAL_TYPE_MISMATCH", "[serviceB] Product.skew -> Type `String!` does not match the type of the original field in serviceA (`String`)"),
+            ]
+        );
+
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") { sku: String! upc: String! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product { sku: NonExistentType! @external id: String! @requires(fields: "sku") }"#,
+                ),
+            ],
+            |context| context.external_type_mismatch(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "EXTERNAL_TYPE_MISMATCH",
+                "[serviceB] Product.sku -> the type of the @external field does not exist in the resulting composed schema"
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_required_fields_which_are_not_external() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") { sku: String! upc: String! id: ID! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product { price: Int! @requires(fields: "id") }"#,
+                ),
+            ],
+            |context| context.requires_fields_missing_external(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "REQUIRES_FIELDS_MISSING_EXTERNAL",
+                "[serviceB] Product.price -> requires the field `id` to be marked as @external."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_required_fields_which_are_not_on_the_base_type() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") { sku: String! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product @key(fields: "sku") { id: ID! }"#,
+                ),
+                (
+                    "serviceC",
+                    r#"extend type Product @key(fields: "sku") { id: ID! @external weight: Float! @requires(fields: "id") }"#,
+                ),
+            ],
+            |context| context.requires_fields_missing_on_base(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "REQUIRES_FIELDS_MISSING_ON_BASE",
+                "[serviceC] Product.weight -> requires the field `id` to be @external. @external fields must exist on the base type, not an extension."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_keys_which_select_fields_of_extensions() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku uid") { sku: String! upc: String! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product { uid: String! sku: String! @external price: Int! @requires(fields: "sku") }"#,
+                ),
+            ],
+            |context| context.key_fields_missing_on_base(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "KEY_FIELDS_MISSING_ON_BASE",
+                "[serviceA] Product -> A @key selects uid, but Product.uid was either created or overwritten by serviceB, not serviceA"
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_keys_which_select_interfaces_or_unions() {
+        let extension = (
+            "serviceB",
+            r#"extend type Product { sku: String! @external price: Int! @requires(fields: "sku") }"#,
+        );
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "featuredItem") { featuredItem: Node! sku: String! } interface Node { id: ID! }"#,
+                ),
+                extension,
+            ],
+            |context| context.key_fields_select_invalid_type(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "KEY_FIELDS_SELECT_INVALID_TYPE",
+                "[serviceA] Product -> A @key selects Product.featuredItem, which is an interface type. Keys cannot select interfaces."
+            )]
+        );
+        assert_eq!(errors[0].locations[0].subgraph.as_deref(), Some("serviceA"));
+
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "price") { sku: String! price: Numeric! } union Numeric = Float | Int"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product { sku: String! @external name: String! }"#,
+                ),
+            ],
+            |context| context.key_fields_select_invalid_type(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "KEY_FIELDS_SELECT_INVALID_TYPE",
+                "[serviceA] Product -> A @key selects Product.price, which is a union type. Keys cannot select union types."
+            )]
+        );
+
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") { sku: String! upc: String! color: Color! } type Color { id: ID! value: String! }"#,
+                ),
+                extension,
+            ],
+            |context| context.key_fields_select_invalid_type(),
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn it_reports_provided_fields_which_are_not_external() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") { sku: String! upc: String! id: ID! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"type Review @key(fields: "id") { id: ID! product: Product @provides(fields: "id") } extend type Product @key(fields: "sku") { sku: String! @external price: Int! }"#,
+                ),
+            ],
+            |context| context.provides_fields_missing_external(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "PROVIDES_FIELDS_MISSING_EXTERNAL",
+                "[serviceB] Review.product -> provides the field `id` and requires Product.id to be marked as @external."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_provided_fields_which_are_lists_or_interfaces() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Review @key(fields: "id") { id: ID! author: User @provides(fields: "wishLists") } extend type User @key(fields: "id") { id: ID! @external wishLists: [WishList] @external } extend type WishList @key(fields: "id") { id: ID! @external }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"type User @key(fields: "id") { id: ID! wishLists: [WishList] } type WishList @key(fields: "id") { id: ID! }"#,
+                ),
+            ],
+            |context| context.provides_fields_select_invalid_type(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "PROVIDES_FIELDS_SELECT_INVALID_TYPE",
+                "[serviceA] Review.author -> A @provides selects User.wishLists, which is a list type. A field cannot @provide lists."
+            )]
+        );
+
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Review @key(fields: "id") { id: ID! author: User @provides(fields: "account") } extend type User @key(fields: "id") { id: ID! @external account: Account @external } extend interface Account { username: String @external }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"type User @key(fields: "id") { id: ID! account: Account } interface Account { username: String }"#,
+                ),
+            ],
+            |context| context.provides_fields_select_invalid_type(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "PROVIDES_FIELDS_SELECT_INVALID_TYPE",
+                "[serviceA] Review.author -> A @provides selects User.account, which is an interface type. A field cannot @provide interfaces."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_provides_on_fields_which_do_not_return_entities() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") { sku: String! upc: String! id: ID! } type LineItem { sku: String! quantity: Int! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product @key(fields: "sku") { sku: String! @external lineItem: LineItem @provides(fields: "quantity") }"#,
+                ),
+            ],
+            |context| context.provides_not_on_entity(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "PROVIDES_NOT_ON_ENTITY",
+                "[serviceB] Product.lineItem -> uses the @provides directive but `Product.lineItem` does not return a type that has a @key. Try adding a @key to the `LineItem` type."
+            )]
+        );
+
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") { sku: String! upc: String! id: ID! } enum Category { BOOK MOVIE SONG ALBUM }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product @key(fields: "sku") { sku: String! @external category: Category @provides(fields: "id") }"#,
+                ),
+            ],
+            |context| context.provides_not_on_entity(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "PROVIDES_NOT_ON_ENTITY",
+                "[serviceB] Product.category -> uses the @provides directive but `Product.category` returns `Category`, which is not an Object or List type. @provides can only be used on Object types with at least one @key, or Lists of such Objects."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_executable_directives_which_some_services_do_not_define() {
+        let errors = validate_with(
+            &[
+                ("serviceA", "directive @stream on FIELD"),
+                ("serviceB", "extend type Query { thing: String }"),
+                ("serviceC", "extend type Query { otherThing: String }"),
+            ],
+            |context| context.executable_directives_in_all_services(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "EXECUTABLE_DIRECTIVES_IN_ALL_SERVICES",
+                "[@stream] -> Custom directives must be implemented in every service. The following services do not implement the @stream directive: serviceB, serviceC."
+            )]
+        );
+        assert_eq!(errors[0].locations.len(), 1);
+        assert_eq!(errors[0].locations[0].subgraph.as_deref(), Some("serviceA"));
+    }
+
+    #[test]
+    fn it_reports_executable_directives_which_services_define_differently() {
+        let errors = validate_with(
+            &[
+                ("serviceA", "directive @stream on FIELD"),
+                ("serviceB", "directive @stream on FIELD | QUERY"),
+                ("serviceC", "directive @stream on INLINE_FRAGMENT"),
+            ],
+            |context| context.executable_directives_identical(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "EXECUTABLE_DIRECTIVES_IDENTICAL",
+                "[@stream] -> custom directives must be defined identically across all services. See below for a list of current implementations:\n\tserviceA: directive @stream on FIELD\n\tserviceB: directive @stream on FIELD | QUERY\n\tserviceC: directive @stream on INLINE_FRAGMENT"
+            )]
+        );
+        assert_eq!(errors[0].locations.len(), 3);
+
+        // Type system locations are left out of the comparison.
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    "directive @instrument(tag: String!) on FIELD | FIELD_DEFINITION",
+                ),
+                ("serviceB", "directive @instrument(tag: String!) on FIELD"),
+            ],
+            |context| context.executable_directives_identical(),
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn it_reports_entities_whose_owner_has_no_keys() {
+        let errors = validate_with(
+            &[
+                ("serviceA", "type Product { sku: String! upc: String! }"),
+                (
+                    "serviceB",
+                    r#"extend type Product @key(fields: "sku") { sku: String! @external price: Int! }"#,
+                ),
+            ],
+            |context| context.keys_match_base_service(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "KEY_MISSING_ON_BASE",
+                "[serviceA] Product -> appears to be an entity but no @key directives are specified on the originating type."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_extensions_with_several_keys() {
+        let errors = validate_with(
+            &[
+                (
+                    "serviceA",
+                    r#"type Product @key(fields: "sku") @key(fields: "upc") { sku: String! upc: String! }"#,
+                ),
+                (
+                    "serviceB",
+                    r#"extend type Product @key(fields: "sku") @key(fields: "upc") { sku: String! @external upc: String! @external price: Int! }"#,
+                ),
+            ],
+            |context| context.keys_match_base_service(),
+        );
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "MULTIPLE_KEYS_ON_EXTENSION",
+                "[serviceB] Product -> is extended from service serviceA but specifies multiple @key directives. Extensions may only specify one @key."
+            )]
+        );
+    }
+}
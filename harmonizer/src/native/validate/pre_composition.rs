@@ -0,0 +1,535 @@
+//! A port of the [rules] which `composeAndValidate` applies to every subgraph
+//! after normalizing it, but before composing them.
+//!
+//! [rules]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preComposition
+
+use super::{
+    error_with_code, field_set_argument, find_directives, log_service_and_type, named_type,
+    parse_selections, Field_, Node, Selection_,
+};
+use crate::native::normalize::RESERVED_ROOT_FIELDS;
+use crate::native::Subgraph;
+use crate::{CompositionError, CompositionErrorCode};
+use graphql_parser::query::{Selection, TypeCondition};
+use graphql_parser::schema::{Definition, TypeDefinition, TypeExtension};
+use graphql_parser::Pos;
+use std::collections::{HashMap, HashSet};
+
+pub(super) fn validate(subgraph: &Subgraph) -> Vec<CompositionError> {
+    let mut errors = external_used_on_base(subgraph);
+    errors.extend(requires_used_on_base(subgraph));
+    errors.extend(key_fields_missing_external(subgraph));
+    errors.extend(reserved_field_used(subgraph));
+    errors.extend(duplicate_enum_or_scalar(subgraph));
+    errors.extend(duplicate_enum_value(subgraph));
+    errors
+}
+
+/// Fields of type definitions can't be `@external`, like
+/// [`externalUsedOnBase`].
+///
+/// [`externalUsedOnBase`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preComposition/externalUsedOnBase.ts
+fn external_used_on_base(subgraph: &Subgraph) -> Vec<CompositionError> {
+    directive_used_on_base(
+        subgraph,
+        "external",
+        CompositionErrorCode::ExternalUsedOnBase,
+    )
+}
+
+/// Fields of type definitions can't be `@requires`, like
+/// [`requiresUsedOnBase`].
+///
+/// [`requiresUsedOnBase`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preComposition/requiresUsedOnBase.ts
+fn requires_used_on_base(subgraph: &Subgraph) -> Vec<CompositionError> {
+    directive_used_on_base(
+        subgraph,
+        "requires",
+        CompositionErrorCode::RequiresUsedOnBase,
+    )
+}
+
+fn directive_used_on_base(
+    subgraph: &Subgraph,
+    directive_name: &str,
+    code: CompositionErrorCode,
+) -> Vec<CompositionError> {
+    let mut errors = Vec::new();
+    for definition in &subgraph.document.definitions {
+        let object = match definition {
+            Definition::TypeDefinition(TypeDefinition::Object(object)) => object,
+            _ => continue,
+        };
+        for field in &object.fields {
+            let nodes: Vec<Node<'_>> = find_directives(&field.directives, directive_name)
+                .map(|directive| (subgraph.name.as_str(), directive.position))
+                .collect();
+            for _ in &nodes {
+                errors.push(error_with_code(
+                    code.clone(),
+                    format!(
+                        "{}Found extraneous @{directive} directive. @{directive} cannot be used on base types.",
+                        log_service_and_type(&subgraph.name, &object.name, Some(&field.name)),
+                        directive = directive_name,
+                    ),
+                    &nodes,
+                ));
+            }
+        }
+    }
+    errors
+}
+
+/// The fields which the `@key`s of type extensions select must be `@external`
+/// fields of the subgraph, like [`keyFieldsMissingExternal`].
+///
+/// [`keyFieldsMissingExternal`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preComposition/keyFieldsMissingExternal.ts
+fn key_fields_missing_external(subgraph: &Subgraph) -> Vec<CompositionError> {
+    let types = ServiceTypes::new(subgraph);
+
+    let mut errors = Vec::new();
+    for definition in &subgraph.document.definitions {
+        let object = match definition {
+            Definition::TypeExtension(TypeExtension::Object(object)) => object,
+            _ => continue,
+        };
+        for key in find_directives(&object.directives, "key") {
+            // Keys which can't be parsed are reported by composition.
+            let selections = match field_set_argument(key).and_then(parse_selections) {
+                Some(selections) => selections,
+                None => continue,
+            };
+            types.check_key_selections(
+                &subgraph.name,
+                &object.name,
+                &selections,
+                key.position,
+                &mut errors,
+            );
+        }
+    }
+    errors
+}
+
+/// The composite types of a subgraph, with the fields of their definitions
+/// and extensions, which is as much of the schema built from its SDL as
+/// `keyFieldsMissingExternal` needs.
+struct ServiceTypes<'s> {
+    fields: HashMap<&'s str, Vec<&'s Field_>>,
+    unions: HashSet<&'s str>,
+}
+
+impl<'s> ServiceTypes<'s> {
+    fn new(subgraph: &'s Subgraph) -> Self {
+        let mut types = ServiceTypes {
+            fields: HashMap::new(),
+            unions: HashSet::new(),
+        };
+        for definition in &subgraph.document.definitions {
+            let (name, fields) = match definition {
+                Definition::TypeDefinition(TypeDefinition::Object(object)) => {
+                    (&object.name, &object.fields)
+                }
+                Definition::TypeDefinition(TypeDefinition::Interface(interface)) => {
+                    (&interface.name, &interface.fields)
+                }
+                Definition::TypeExtension(TypeExtension::Object(object)) => {
+                    (&object.name, &object.fields)
+                }
+                Definition::TypeExtension(TypeExtension::Interface(interface)) => {
+                    (&interface.name, &interface.fields)
+                }
+                Definition::TypeDefinition(TypeDefinition::Union(union)) => {
+                    types.unions.insert(&union.name);
+                    continue;
+                }
+                Definition::TypeExtension(TypeExtension::Union(union)) => {
+                    types.unions.insert(&union.name);
+                    continue;
+                }
+                _ => continue,
+            };
+            types
+                .fields
+                .entry(name.as_str())
+                .or_default()
+                .extend(fields.iter());
+        }
+        types
+    }
+
+    fn is_composite(&self, type_name: &str) -> bool {
+        self.fields.contains_key(type_name) || self.unions.contains(type_name)
+    }
+
+    /// The field of a type with the given name, where the fields of
+    /// extensions take the place of the ones they redefine.
+    fn field(&self, type_name: &str, field_name: &str) -> Option<&'s Field_> {
+        self.fields
+            .get(type_name)?
+            .iter()
+            .rev()
+            .find(|field| field.name == field_name)
+            .copied()
+    }
+
+    fn check_key_selections(
+        &self,
+        service_name: &str,
+        parent_type: &str,
+        selections: &[Selection_],
+        key_position: Pos,
+        errors: &mut Vec<CompositionError>,
+    ) {
+        if !self.is_composite(parent_type) {
+            return;
+        }
+
+        for selection in selections {
+            let selected = match selection {
+                Selection::Field(selected) => selected,
+                Selection::InlineFragment(fragment) => {
+                    let type_condition = match &fragment.type_condition {
+                        Some(TypeCondition::On(type_condition)) => type_condition,
+                        None => parent_type,
+                    };
+                    self.check_key_selections(
+                        service_name,
+                        type_condition,
+                        &fragment.selection_set.items,
+                        key_position,
+                        errors,
+                    );
+                    continue;
+                }
+                Selection::FragmentSpread(_) => continue,
+            };
+
+            // `__typename` is always there, but never `@external`.
+            let field = if selected.name == "__typename" {
+                None
+            } else {
+                match self.field(parent_type, &selected.name) {
+                    Some(field) => Some(field),
+                    None => {
+                        errors.push(error_with_code(
+                            CompositionErrorCode::KeyFieldsMissingExternal,
+                            format!(
+                                "{}A @key directive specifies a field which is not found in this service. Add a field to this type with @external.",
+                                log_service_and_type(service_name, parent_type, None),
+                            ),
+                            &[(service_name, key_position)],
+                        ));
+                        continue;
+                    }
+                }
+            };
+
+            let nodes: Option<Vec<Node<'_>>> = match field {
+                Some(field)
+                    if find_directives(&field.directives, "external")
+                        .next()
+                        .is_some() =>
+                {
+                    None
+                }
+                Some(field) => Some(vec![(service_name, field.position)]),
+                None => Some(Vec::new()),
+            };
+            if let Some(nodes) = nodes {
+                errors.push(error_with_code(
+                    CompositionErrorCode::KeyFieldsMissingExternal,
+                    format!(
+                        "{}A @key directive specifies the `{}` field which has no matching @external field.",
+                        log_service_and_type(service_name, parent_type, None),
+                        selected.name,
+                    ),
+                    &nodes,
+                ));
+            }
+
+            if let Some(field) = field {
+                self.check_key_selections(
+                    service_name,
+                    named_type(&field.field_type),
+                    &selected.selection_set.items,
+                    key_position,
+                    errors,
+                );
+            }
+        }
+    }
+}
+
+/// The root query type can't have the fields which federation adds to it,
+/// like [`reservedFieldUsed`].
+///
+/// [`reservedFieldUsed`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preComposition/reservedFieldUsed.ts
+fn reserved_field_used(subgraph: &Subgraph) -> Vec<CompositionError> {
+    let mut root_query_name = "Query";
+    for definition in &subgraph.document.definitions {
+        if let Definition::SchemaDefinition(schema) = definition {
+            if let Some(query) = &schema.query {
+                root_query_name = query;
+            }
+        }
+    }
+
+    let mut errors = Vec::new();
+    for definition in &subgraph.document.definitions {
+        let object = match definition {
+            Definition::TypeDefinition(TypeDefinition::Object(object))
+                if object.name == root_query_name =>
+            {
+                object
+            }
+            _ => continue,
+        };
+        for field in &object.fields {
+            if RESERVED_ROOT_FIELDS.contains(&field.name.as_str()) {
+                errors.push(error_with_code(
+                    CompositionErrorCode::ReservedFieldUsed,
+                    format!(
+                        "{}{} is a field reserved for federation and can't be used at the Query root.",
+                        log_service_and_type(&subgraph.name, root_query_name, Some(&field.name)),
+                        field.name,
+                    ),
+                    &[(&subgraph.name, field.position)],
+                ));
+            }
+        }
+    }
+    errors
+}
+
+/// A subgraph can define an enum or a scalar only once, like
+/// [`duplicateEnumOrScalar`].
+///
+/// [`duplicateEnumOrScalar`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preComposition/duplicateEnumOrScalar.ts
+fn duplicate_enum_or_scalar(subgraph: &Subgraph) -> Vec<CompositionError> {
+    let mut enums = HashSet::new();
+    let mut scalars = HashSet::new();
+
+    let mut errors = Vec::new();
+    for definition in &subgraph.document.definitions {
+        let (seen, kind, code, name, position) = match definition {
+            Definition::TypeDefinition(TypeDefinition::Enum(enum_type)) => (
+                &mut enums,
+                "enum",
+                CompositionErrorCode::DuplicateEnumDefinition,
+                &enum_type.name,
+                enum_type.position,
+            ),
+            Definition::TypeDefinition(TypeDefinition::Scalar(scalar)) => (
+                &mut scalars,
+                "scalar",
+                CompositionErrorCode::DuplicateScalarDefinition,
+                &scalar.name,
+                scalar.position,
+            ),
+            _ => continue,
+        };
+        if !seen.insert(name) {
+            errors.push(error_with_code(
+                code,
+                format!(
+                    "{}The {kind}, `{name}` was defined multiple times in this service. Remove one of the definitions for `{name}`",
+                    log_service_and_type(&subgraph.name, name, None),
+                    kind = kind,
+                    name = name,
+                ),
+                &[(&subgraph.name, position)],
+            ));
+        }
+    }
+    errors
+}
+
+/// The definitions and extensions of an enum within a subgraph can't repeat
+/// each other's values, like [`duplicateEnumValue`].
+///
+/// [`duplicateEnumValue`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preComposition/duplicateEnumValue.ts
+fn duplicate_enum_value(subgraph: &Subgraph) -> Vec<CompositionError> {
+    let mut enums: HashMap<&str, Vec<&str>> = HashMap::new();
+
+    let mut errors = Vec::new();
+    for definition in &subgraph.document.definitions {
+        let (name, values, position) = match definition {
+            Definition::TypeDefinition(TypeDefinition::Enum(enum_type)) => {
+                (&enum_type.name, &enum_type.values, Some(enum_type.position))
+            }
+            // Duplicates within extensions are located at the value rather
+            // than at the extension.
+            Definition::TypeExtension(TypeExtension::Enum(enum_type)) => {
+                (&enum_type.name, &enum_type.values, None)
+            }
+            _ => continue,
+        };
+
+        let seen = enums.entry(name).or_default();
+        if seen.is_empty() {
+            *seen = values.iter().map(|value| value.name.as_str()).collect();
+            continue;
+        }
+        for value in values {
+            if !seen.contains(&value.name.as_str()) {
+                seen.push(&value.name);
+                continue;
+            }
+            errors.push(error_with_code(
+                CompositionErrorCode::DuplicateEnumValue,
+                format!(
+                    "{}The enum, `{}` has multiple definitions of the `{}` value.",
+                    log_service_and_type(&subgraph.name, name, Some(&value.name)),
+                    name,
+                    value.name,
+                ),
+                &[(&subgraph.name, position.unwrap_or(value.position))],
+            ));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        duplicate_enum_or_scalar, duplicate_enum_value, external_used_on_base,
+        requires_used_on_base, reserved_field_used, validate,
+    };
+    use crate::native::{normalize_subgraphs, parse_subgraphs, Subgraph};
+    use crate::{CompositionError, ServiceDefinition};
+
+    /// A subgraph as it was given, which the tests of `federation-js` run
+    /// each rule on.
+    fn subgraph(type_defs: &str) -> Subgraph {
+        parse_subgraphs(&[ServiceDefinition::new("serviceA", "undefined", type_defs)])
+            .unwrap()
+            .remove(0)
+    }
+
+    fn codes_and_messages(errors: &[CompositionError]) -> Vec<(&str, &str)> {
+        errors
+            .iter()
+            .map(|error| (error.code(), error.message.as_deref().unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn it_reports_external_fields_of_type_definitions() {
+        let errors = external_used_on_base(&subgraph(
+            r#"type Product @key(fields: "sku") { sku: String! upc: String! @external id: ID! }"#,
+        ));
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "EXTERNAL_USED_ON_BASE",
+                "[serviceA] Product.upc -> Found extraneous @external directive. @external cannot be used on base types."
+            )]
+        );
+        assert_eq!(errors[0].locations[0].subgraph.as_deref(), Some("serviceA"));
+
+        assert!(external_used_on_base(&subgraph(
+            r#"type Product @key(fields: "sku") { sku: String! upc: String! }"#
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn it_reports_required_fields_of_type_definitions() {
+        let errors = requires_used_on_base(&subgraph(
+            r#"type Product @key(fields: "sku") { sku: String! upc: String! @requires(fields: "sku") id: ID! }"#,
+        ));
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "REQUIRES_USED_ON_BASE",
+                "[serviceA] Product.upc -> Found extraneous @requires directive. @requires cannot be used on base types."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_reserved_fields_of_the_query_type() {
+        let errors = reserved_field_used(&subgraph(
+            "type Query { product: Product _service: String! _entities: String! } type Product { sku: String }",
+        ));
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![
+                ("RESERVED_FIELD_USED", "[serviceA] Query._service -> _service is a field reserved for federation and can't be used at the Query root."),
+                ("RESERVED_FIELD_USED", "[serviceA] Query._entities -> _entities is a field reserved for federation and can't be used at the Query root."),
+            ]
+        );
+
+        // Whatever the query type is named.
+        let errors = reserved_field_used(&subgraph(
+            "schema { query: RootQuery } type RootQuery { product: Product _service: String _entities: String } type Product { sku: String }",
+        ));
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![
+                ("RESERVED_FIELD_USED", "[serviceA] RootQuery._service -> _service is a field reserved for federation and can't be used at the Query root."),
+                ("RESERVED_FIELD_USED", "[serviceA] RootQuery._entities -> _entities is a field reserved for federation and can't be used at the Query root."),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_enums_and_scalars_defined_twice() {
+        let errors = duplicate_enum_or_scalar(&subgraph(
+            r#"type Product @key(fields: "color { id value }") { sku: String! upc: String! color: Color! } type Color { id: ID! value: String! } enum ProductType { BOOK FURNITURE } enum ProductType { DIGITAL }"#,
+        ));
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "DUPLICATE_ENUM_DEFINITION",
+                "[serviceA] ProductType -> The enum, `ProductType` was defined multiple times in this service. Remove one of the definitions for `ProductType`"
+            )]
+        );
+
+        let errors = duplicate_enum_or_scalar(&subgraph(
+            r#"scalar Date type Product @key(fields: "color { id value }") { sku: String! upc: String! deliveryDate: Date } scalar Date"#,
+        ));
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "DUPLICATE_SCALAR_DEFINITION",
+                "[serviceA] Date -> The scalar, `Date` was defined multiple times in this service. Remove one of the definitions for `Date`"
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_enum_values_defined_twice() {
+        let errors = duplicate_enum_value(&subgraph(
+            r#"type Product @key(fields: "color { id value }") { sku: String! upc: String! color: Color! } type Color { id: ID! value: String! } enum ProductType { BOOK FURNITURE } extend enum ProductType { DIGITAL BOOK }"#,
+        ));
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "DUPLICATE_ENUM_VALUE",
+                "[serviceA] ProductType.BOOK -> The enum, `ProductType` has multiple definitions of the `BOOK` value."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_keys_of_extensions_which_select_missing_fields() {
+        let subgraphs = parse_subgraphs(&[ServiceDefinition::new(
+            "reviews",
+            "undefined",
+            r#"extend type User @key(fields: "id") { reviews: [String] }"#,
+        )])
+        .unwrap();
+        let errors = validate(&normalize_subgraphs(&subgraphs)[0]);
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].code(), "KEY_FIELDS_MISSING_EXTERNAL");
+        assert_eq!(
+            errors[0].message.as_deref(),
+            Some("[reviews] User -> A @key directive specifies a field which is not found in this service. Add a field to this type with @external.")
+        );
+        assert_eq!(errors[0].locations[0].line, 1);
+    }
+}
@@ -0,0 +1,114 @@
+//! A port of the [rules] which `composeAndValidate` applies to every subgraph
+//! before normalizing it.
+//!
+//! [rules]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preNormalization
+
+use super::{error_with_code, log_service_and_type};
+use crate::native::normalize::DEFAULT_ROOT_OPERATION_NAMES;
+use crate::native::Subgraph;
+use crate::{CompositionError, CompositionErrorCode};
+use graphql_parser::schema::{Definition, TypeDefinition, TypeExtension};
+
+pub(super) fn validate(subgraph: &Subgraph) -> Vec<CompositionError> {
+    root_field_used(subgraph)
+}
+
+/// When a subgraph gives a root operation type a name other than its default
+/// one (e.g., `query: RootQuery`), no other type may have the default name,
+/// like [`rootFieldUsed`].
+///
+/// [`rootFieldUsed`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/preNormalization/rootFieldUsed.ts
+fn root_field_used(subgraph: &Subgraph) -> Vec<CompositionError> {
+    let mut has_schema_definition = false;
+    let mut disallowed_type_names = Vec::new();
+    for definition in &subgraph.document.definitions {
+        let schema = match definition {
+            Definition::SchemaDefinition(schema) => schema,
+            _ => continue,
+        };
+        let root_operation_types = [
+            ("Query", &schema.query),
+            ("Mutation", &schema.mutation),
+            ("Subscription", &schema.subscription),
+        ];
+        for (default_name, name) in &root_operation_types {
+            if let Some(name) = name {
+                has_schema_definition = true;
+                if !DEFAULT_ROOT_OPERATION_NAMES.contains(&name.as_str()) {
+                    disallowed_type_names.push(*default_name);
+                }
+            }
+        }
+    }
+    if !has_schema_definition {
+        return Vec::new();
+    }
+
+    let mut errors = Vec::new();
+    for definition in &subgraph.document.definitions {
+        let (name, position) = match definition {
+            Definition::TypeDefinition(TypeDefinition::Object(object)) => {
+                (&object.name, object.position)
+            }
+            Definition::TypeExtension(TypeExtension::Object(object)) => {
+                (&object.name, object.position)
+            }
+            _ => continue,
+        };
+        let code = match name.as_str() {
+            "Query" => CompositionErrorCode::RootQueryUsed,
+            "Mutation" => CompositionErrorCode::RootMutationUsed,
+            "Subscription" => CompositionErrorCode::RootSubscriptionUsed,
+            _ => continue,
+        };
+        if !disallowed_type_names.contains(&name.as_str()) {
+            continue;
+        }
+
+        errors.push(error_with_code(
+            code,
+            format!(
+                "{}Found invalid use of default root operation name `{name}`. `{name}` is disallowed when `Schema.{operation}` is set to a type other than `{name}`.",
+                log_service_and_type(&subgraph.name, name, None),
+                name = name,
+                operation = name.to_lowercase(),
+            ),
+            &[(&subgraph.name, position)],
+        ));
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+    use crate::native::{parse_subgraphs, Subgraph};
+    use crate::ServiceDefinition;
+
+    fn subgraph(type_defs: &str) -> Subgraph {
+        parse_subgraphs(&[ServiceDefinition::new("users", "undefined", type_defs)])
+            .unwrap()
+            .remove(0)
+    }
+
+    #[test]
+    fn it_disallows_default_root_operation_names_once_renamed() {
+        let errors = validate(&subgraph(
+            "schema { query: RootQuery } type RootQuery { me: User } type Query { users: [User] } type User { id: ID }",
+        ));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code(), "ROOT_QUERY_USED");
+        assert_eq!(
+            errors[0].message.as_deref(),
+            Some("[users] Query -> Found invalid use of default root operation name `Query`. `Query` is disallowed when `Schema.query` is set to a type other than `Query`.")
+        );
+        assert_eq!(errors[0].coordinate.as_deref(), Some("Query"));
+        assert_eq!(errors[0].subgraphs, vec!["users".to_string()]);
+        assert_eq!(errors[0].locations[0].line, 1);
+
+        assert!(validate(&subgraph(
+            "schema { query: Query } type Query { me: String }"
+        ))
+        .is_empty());
+    }
+}
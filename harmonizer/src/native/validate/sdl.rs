@@ -0,0 +1,875 @@
+//! A port of the [rules] which `composeServices` applies (in place of some of
+//! the ones of `graphql-js`) to the type definitions of every subgraph, and
+//! then to their type extensions.
+//!
+//! `composeServices` validates each of them as a single document, in which
+//! the definitions (and extensions) of a type follow each other, so these
+//! visit them type by type.  Types are visited in order of their names,
+//! rather than in the order subgraphs first mention them, so that errors are
+//! reported in the same order no matter how composition hashes them.
+//!
+//! [rules]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/sdl
+
+use super::{
+    definition_position, error, error_with_code, extension_position, find_directives,
+    log_service_and_type, Node,
+};
+use crate::native::compose::{definition_kind, diff_type_nodes, SPECIFIED_SCALARS};
+use crate::{CompositionError, CompositionErrorCode};
+use graphql_parser::schema::{TypeDefinition, TypeExtension};
+use graphql_parser::Pos;
+use std::collections::HashMap;
+
+type TypeDefinition_ = TypeDefinition<'static, String>;
+type TypeExtension_ = TypeExtension<'static, String>;
+
+/// A definition of a type, along with the subgraph it came from (which is
+/// empty for the `Query` and `Mutation` types composition makes up, which
+/// are the only definition of their type and so never in error).
+type Located<'d> = (&'d str, &'d TypeDefinition_);
+
+/// Validate the definitions of every type, by name, as they were given by
+/// the subgraphs in order.
+pub(crate) fn validate_type_definitions(
+    type_definitions: &HashMap<String, Vec<(Option<String>, TypeDefinition_)>>,
+) -> Vec<CompositionError> {
+    let mut names: Vec<&String> = type_definitions.keys().collect();
+    names.sort();
+
+    let groups: Vec<(&str, Vec<Located<'_>>)> = names
+        .into_iter()
+        .map(|name| {
+            let mut definitions: Vec<Located<'_>> = type_definitions[name]
+                .iter()
+                .map(|(service_name, definition)| {
+                    (service_name.as_deref().unwrap_or_default(), definition)
+                })
+                .collect();
+            // Composition moves the first definition of a type which
+            // implements interfaces behind the others, as it merges them.
+            if definitions
+                .iter()
+                .any(|(_, definition)| !implemented_interfaces(definition).is_empty())
+            {
+                definitions.rotate_left(1);
+            }
+            (name.as_str(), definitions)
+        })
+        .collect();
+
+    // `MatchingEnums` looks at the document as a whole as soon as it starts,
+    // so its errors come before those of any other rule.
+    let mut errors: Vec<CompositionError> = groups
+        .iter()
+        .filter_map(|(name, definitions)| matching_enums(name, definitions))
+        .collect();
+
+    for (name, definitions) in &groups {
+        let mut fields = UniqueFieldDefinitionNames::default();
+        let mut first_definition: Option<Located<'_>> = None;
+        let mut first_union: Option<Located<'_>> = None;
+        for &node in definitions {
+            fields.check_definition(name, node, &mut errors);
+            unique_type_names_with_fields(name, node, &mut first_definition, &mut errors);
+            unique_union_types(name, node, &mut first_union, &mut errors);
+        }
+    }
+    errors
+}
+
+/// Validate the extensions of every type, by name, against the `types`
+/// which their definitions were merged into (and the subgraphs those came
+/// from).
+pub(crate) fn validate_type_extensions(
+    type_extensions: &HashMap<String, Vec<(String, TypeExtension_)>>,
+    types: &HashMap<String, TypeDefinition_>,
+    definers: &HashMap<String, Option<String>>,
+) -> Vec<CompositionError> {
+    let mut names: Vec<&String> = type_extensions.keys().collect();
+    names.sort();
+
+    let mut errors = Vec::new();
+    for name in names {
+        let definition = types.get(name).map(|definition| {
+            let service_name = match definers.get(name) {
+                Some(Some(service_name)) => service_name.as_str(),
+                _ => "",
+            };
+            (service_name, definition)
+        });
+
+        let mut fields = UniqueFieldDefinitionNames::default();
+        for (service_name, extension) in &type_extensions[name] {
+            fields.check_extension(name, service_name, extension, definition, &mut errors);
+            possible_type_extensions(name, service_name, extension, definition, &mut errors);
+        }
+    }
+    errors
+}
+
+/// Every service which defines a type as an enum must define it with the
+/// same values, and every other service must define it as an enum too, like
+/// [`MatchingEnums`].
+///
+/// [`MatchingEnums`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/sdl/matchingEnums.ts
+fn matching_enums(name: &str, definitions: &[Located<'_>]) -> Option<CompositionError> {
+    let nodes: Vec<Node<'_>> = definitions
+        .iter()
+        .map(|(service_name, definition)| (*service_name, definition_position(definition)))
+        .collect();
+    let is_enum = |(_, definition): &&Located<'_>| matches!(definition, TypeDefinition::Enum(_));
+
+    if definitions.iter().all(|definition| is_enum(&definition)) {
+        // Services grouped by their (sorted) values, in the order the groups
+        // are first seen in.
+        let mut groups: Vec<(String, Vec<&str>)> = Vec::new();
+        for (service_name, definition) in definitions {
+            let enum_type = match definition {
+                TypeDefinition::Enum(enum_type) if !service_name.is_empty() => enum_type,
+                _ => continue,
+            };
+            let mut values: Vec<&str> = enum_type
+                .values
+                .iter()
+                .map(|value| value.name.as_str())
+                .collect();
+            values.sort_unstable();
+            let values = values.join(",");
+            match groups.iter_mut().find(|(seen, _)| *seen == values) {
+                Some((_, service_names)) => service_names.push(service_name),
+                None => groups.push((values, vec![service_name])),
+            }
+        }
+        if groups.len() < 2 {
+            return None;
+        }
+
+        let groups: Vec<String> = groups
+            .iter()
+            .map(|(_, service_names)| format!("[{}]", service_names.join(", ")))
+            .collect();
+        Some(error_with_code(
+            CompositionErrorCode::EnumMismatch,
+            format!(
+                "The `{}` enum does not have identical values in all services. Groups of services with identical values are: {}",
+                name,
+                groups.join(", "),
+            ),
+            &nodes,
+        ))
+    } else if definitions.iter().any(|definition| is_enum(&definition)) {
+        let (with_enum, without_enum): (Vec<&Located<'_>>, Vec<&Located<'_>>) =
+            definitions.iter().partition(is_enum);
+        fn service_names<'d>(definitions: Vec<&Located<'d>>) -> Vec<&'d str> {
+            definitions
+                .into_iter()
+                .map(|(service_name, _)| *service_name)
+                .filter(|service_name| !service_name.is_empty())
+                .collect()
+        }
+        let with_enum = service_names(with_enum);
+        let without_enum = service_names(without_enum);
+        Some(error_with_code(
+            CompositionErrorCode::EnumMismatchType,
+            format!(
+                "{}{} is an enum in [{}], but not in [{}]",
+                log_service_and_type(with_enum.first().copied().unwrap_or_default(), name, None),
+                name,
+                with_enum.join(", "),
+                without_enum.join(", "),
+            ),
+            &nodes,
+        ))
+    } else {
+        None
+    }
+}
+
+/// The fields of a type can only be defined once, unless several services
+/// define it as a value type, like [`UniqueFieldDefinitionNames`].
+///
+/// [`UniqueFieldDefinitionNames`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/sdl/uniqueFieldDefinitionNames.ts
+#[derive(Default)]
+struct UniqueFieldDefinitionNames<'d> {
+    known_field_names: HashMap<&'d str, Node<'d>>,
+    possible_value_type: Option<&'d TypeDefinition_>,
+}
+
+impl<'d> UniqueFieldDefinitionNames<'d> {
+    fn check_definition(
+        &mut self,
+        type_name: &str,
+        (service_name, node): Located<'d>,
+        errors: &mut Vec<CompositionError>,
+    ) {
+        let fields = match definition_fields(node) {
+            Some(fields) => fields,
+            None => return,
+        };
+
+        match self.possible_value_type {
+            Some(possible_value_type) => {
+                // Definitions which only differ in the types of their fields
+                // are near value types, which `UniqueTypeNamesWithFields`
+                // reports on.
+                let diff = diff_type_nodes(node, possible_value_type);
+                if diff.fields.iter().all(|(_, types)| types.len() == 2) {
+                    return;
+                }
+                if !diff.input_values.is_empty()
+                    && diff.input_values.iter().all(|(_, types)| types.len() == 2)
+                {
+                    return;
+                }
+            }
+            None => self.possible_value_type = Some(node),
+        }
+
+        for (field_name, position) in fields {
+            self.check_field_name(type_name, field_name, (service_name, position), errors);
+        }
+    }
+
+    fn check_extension(
+        &mut self,
+        type_name: &str,
+        service_name: &'d str,
+        extension: &'d TypeExtension_,
+        definition: Option<Located<'d>>,
+        errors: &mut Vec<CompositionError>,
+    ) {
+        let fields = match extension_fields(extension) {
+            Some(fields) => fields,
+            None => return,
+        };
+
+        for (field_name, position) in fields {
+            let existing = definition.and_then(|(definer, definition)| {
+                let (_, position) = definition_fields(definition)?
+                    .into_iter()
+                    .find(|(name, _)| *name == field_name)?;
+                Some((definer, position))
+            });
+            match existing {
+                Some(existing) => errors.push(error(
+                    format!(
+                        "{}Field \"{}.{}\" already exists in the schema. It cannot also be defined in this type extension. If this is meant to be an external field, add the `@external` directive.",
+                        log_service_and_type(service_name, type_name, Some(field_name)),
+                        type_name,
+                        field_name,
+                    ),
+                    &[existing],
+                )),
+                None => {
+                    self.check_field_name(type_name, field_name, (service_name, position), errors)
+                }
+            }
+        }
+    }
+
+    fn check_field_name(
+        &mut self,
+        type_name: &str,
+        field_name: &'d str,
+        node: Node<'d>,
+        errors: &mut Vec<CompositionError>,
+    ) {
+        match self.known_field_names.get(field_name) {
+            Some(&known) => errors.push(error(
+                format!(
+                    "Field \"{}.{}\" can only be defined once.",
+                    type_name, field_name
+                ),
+                &[known, node],
+            )),
+            None => {
+                self.known_field_names.insert(field_name, node);
+            }
+        }
+    }
+}
+
+/// A type can only be defined once, unless several services define it as
+/// a value type, in which case their definitions must be of the same kind,
+/// with the same fields of the same types, like
+/// [`UniqueTypeNamesWithFields`].
+///
+/// [`UniqueTypeNamesWithFields`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/sdl/uniqueTypeNamesWithFields.ts
+fn unique_type_names_with_fields<'d>(
+    type_name: &str,
+    (service_name, node): Located<'d>,
+    first_definition: &mut Option<Located<'d>>,
+    errors: &mut Vec<CompositionError>,
+) {
+    let (duplicate_service_name, duplicate) = match *first_definition {
+        Some(first_definition) => first_definition,
+        None => {
+            *first_definition = Some((service_name, node));
+            return;
+        }
+    };
+    let nodes = [
+        (service_name, definition_position(node)),
+        (duplicate_service_name, definition_position(duplicate)),
+    ];
+
+    let diff = diff_type_nodes(node, duplicate);
+    if let Some((kind, duplicate_kind)) = diff.kinds {
+        errors.push(error_with_code(
+            CompositionErrorCode::ValueTypeKindMismatch,
+            format!(
+                "{}Found kind mismatch on expected value type belonging to services `{}` and `{}`. `{type_name}` is defined as both a `{}` and a `{}`. In order to define `{type_name}` in multiple places, the kinds must be identical.",
+                log_service_and_type(duplicate_service_name, type_name, None),
+                duplicate_service_name,
+                service_name,
+                kind,
+                duplicate_kind,
+                type_name = type_name,
+            ),
+            &nodes,
+        ));
+        return;
+    }
+
+    let same_field_shape = diff.fields.iter().all(|(_, types)| types.len() == 2);
+    let same_input_values_shape = diff.input_values.iter().all(|(_, types)| types.len() == 2);
+    if !(same_field_shape && same_input_values_shape) {
+        errors.push(error(
+            format!("There can be only one type named \"{}\".", type_name),
+            &[
+                (duplicate_service_name, definition_position(duplicate)),
+                (service_name, definition_position(node)),
+            ],
+        ));
+        return;
+    }
+
+    for (field_name, types) in &diff.fields {
+        let field_nodes: Vec<Node<'_>> = match (
+            definition_field_position(node, field_name),
+            definition_field_position(duplicate, field_name),
+        ) {
+            (Some(field), Some(duplicate_field)) => vec![
+                (service_name, field),
+                (duplicate_service_name, duplicate_field),
+            ],
+            _ => Vec::new(),
+        };
+        errors.push(error_with_code(
+            CompositionErrorCode::ValueTypeFieldTypeMismatch,
+            format!(
+                "{}A field was defined differently in different services. `{}` and `{}` define `{}.{}` as a {} and {} respectively. In order to define `{}` in multiple places, the fields and their types must be identical.",
+                log_service_and_type(duplicate_service_name, type_name, Some(field_name)),
+                duplicate_service_name,
+                service_name,
+                type_name,
+                field_name,
+                types[1],
+                types[0],
+                type_name,
+            ),
+            &field_nodes,
+        ));
+    }
+    for (input_name, types) in &diff.input_values {
+        errors.push(error_with_code(
+            CompositionErrorCode::ValueTypeInputValueMismatch,
+            format!(
+                "{}A field's input type (`{}`) was defined differently in different services. `{}` and `{}` define `{}` as a {} and {} respectively. In order to define `{}` in multiple places, the input values and their types must be identical.",
+                log_service_and_type(duplicate_service_name, type_name, None),
+                input_name,
+                duplicate_service_name,
+                service_name,
+                input_name,
+                types[1],
+                types[0],
+                type_name,
+            ),
+            &nodes,
+        ));
+    }
+
+    let entity_service_name = if is_entity(duplicate) {
+        duplicate_service_name
+    } else if is_entity(node) {
+        service_name
+    } else {
+        return;
+    };
+    errors.push(error_with_code(
+        CompositionErrorCode::ValueTypeNoEntity,
+        format!(
+            "{}Value types cannot be entities (using the `@key` directive). Please ensure that the `{type_name}` type is extended properly or remove the `@key` directive if this is not an entity.",
+            log_service_and_type(entity_service_name, type_name, None),
+            type_name = type_name,
+        ),
+        &nodes,
+    ));
+}
+
+/// Every service which defines a union must define it with the same members,
+/// like [`UniqueUnionTypes`].
+///
+/// [`UniqueUnionTypes`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/sdl/matchingUnions.ts
+fn unique_union_types<'d>(
+    type_name: &str,
+    (service_name, node): Located<'d>,
+    first_union: &mut Option<Located<'d>>,
+    errors: &mut Vec<CompositionError>,
+) {
+    let union = match node {
+        TypeDefinition::Union(union) => union,
+        _ => return,
+    };
+    let (duplicate_service_name, duplicate) = match *first_union {
+        Some((duplicate_service_name, TypeDefinition::Union(duplicate))) => {
+            (duplicate_service_name, duplicate)
+        }
+        _ => {
+            *first_union = Some((service_name, node));
+            return;
+        }
+    };
+
+    let mismatched: Vec<&str> = union
+        .types
+        .iter()
+        .filter(|member| !duplicate.types.contains(member))
+        .chain(
+            duplicate
+                .types
+                .iter()
+                .filter(|member| !union.types.contains(member)),
+        )
+        .map(String::as_str)
+        .collect();
+    if mismatched.is_empty() {
+        return;
+    }
+
+    let (plural, verb) = if mismatched.len() > 1 {
+        ("s", "are")
+    } else {
+        ("", "is")
+    };
+    errors.push(error_with_code(
+        CompositionErrorCode::ValueTypeUnionTypesMismatch,
+        format!(
+            "{}The union `{}` is defined in services `{}` and `{}`, however their types do not match. Union types with the same name must also consist of identical types. The type{} {} {} mismatched.",
+            log_service_and_type(duplicate_service_name, type_name, None),
+            type_name,
+            duplicate_service_name,
+            service_name,
+            plural,
+            mismatched.join(", "),
+            verb,
+        ),
+        &[
+            (service_name, union.position),
+            (duplicate_service_name, duplicate.position),
+        ],
+    ));
+}
+
+/// Object and interface types can only be extended by extensions of the
+/// same kind, and only if some service defines them, like
+/// [`PossibleTypeExtensions`].
+///
+/// [`PossibleTypeExtensions`]: https://github.com/apollographql/federation/blob/d7ca0bc2/federation-js/src/composition/validate/sdl/possibleTypeExtensions.ts
+fn possible_type_extensions(
+    type_name: &str,
+    service_name: &str,
+    extension: &TypeExtension_,
+    definition: Option<Located<'_>>,
+    errors: &mut Vec<CompositionError>,
+) {
+    let kind = match extension {
+        TypeExtension::Object(_) => "ObjectTypeExtension",
+        TypeExtension::Interface(_) => "InterfaceTypeExtension",
+        _ => return,
+    };
+    let nodes = [(service_name, extension_position(extension))];
+
+    let base_kind = match definition {
+        Some((_, definition)) => definition_kind(definition),
+        None if SPECIFIED_SCALARS.contains(&type_name) => "ScalarTypeDefinition",
+        None => {
+            errors.push(error_with_code(
+                CompositionErrorCode::ExtensionWithNoBase,
+                format!(
+                    "{}`{type_name}` is an extension type, but `{type_name}` is not defined in any service",
+                    log_service_and_type(service_name, type_name, None),
+                    type_name = type_name,
+                ),
+                &nodes,
+            ));
+            return;
+        }
+    };
+    let expected_kind = base_kind.replace("Definition", "Extension");
+    if expected_kind != kind {
+        errors.push(error_with_code(
+            CompositionErrorCode::ExtensionOfWrongKind,
+            format!(
+                "{}`{}` was originally defined as a {} and can only be extended by a {}. {} defines {} as a {}",
+                log_service_and_type(service_name, type_name, None),
+                type_name,
+                base_kind,
+                expected_kind,
+                service_name,
+                type_name,
+                kind,
+            ),
+            &nodes,
+        ));
+    }
+}
+
+/// The names and positions of the fields (or input fields) of a definition,
+/// if it is of a kind which has them.
+fn definition_fields(definition: &TypeDefinition_) -> Option<Vec<(&str, Pos)>> {
+    match definition {
+        TypeDefinition::Object(object) => Some(
+            object
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.position))
+                .collect(),
+        ),
+        TypeDefinition::Interface(interface) => Some(
+            interface
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.position))
+                .collect(),
+        ),
+        TypeDefinition::InputObject(input) => Some(
+            input
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.position))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn extension_fields(extension: &TypeExtension_) -> Option<Vec<(&str, Pos)>> {
+    match extension {
+        TypeExtension::Object(object) => Some(
+            object
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.position))
+                .collect(),
+        ),
+        TypeExtension::Interface(interface) => Some(
+            interface
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.position))
+                .collect(),
+        ),
+        TypeExtension::InputObject(input) => Some(
+            input
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.position))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn definition_field_position(definition: &TypeDefinition_, field_name: &str) -> Option<Pos> {
+    definition_fields(definition)?
+        .into_iter()
+        .find(|(name, _)| *name == field_name)
+        .map(|(_, position)| position)
+}
+
+fn implemented_interfaces(definition: &TypeDefinition_) -> &[String] {
+    match definition {
+        TypeDefinition::Object(object) => &object.implements_interfaces,
+        TypeDefinition::Interface(interface) => &interface.implements_interfaces,
+        _ => &[],
+    }
+}
+
+/// Whether there's a `@key` anywhere within a definition, like
+/// `isTypeNodeAnEntity`.
+fn is_entity(definition: &TypeDefinition_) -> bool {
+    let has_key = |directives| find_directives(directives, "key").next().is_some();
+    match definition {
+        TypeDefinition::Scalar(scalar) => has_key(&scalar.directives),
+        TypeDefinition::Object(object) => {
+            has_key(&object.directives)
+                || object.fields.iter().any(|field| {
+                    has_key(&field.directives)
+                        || field
+                            .arguments
+                            .iter()
+                            .any(|argument| has_key(&argument.directives))
+                })
+        }
+        TypeDefinition::Interface(interface) => {
+            has_key(&interface.directives)
+                || interface.fields.iter().any(|field| {
+                    has_key(&field.directives)
+                        || field
+                            .arguments
+                            .iter()
+                            .any(|argument| has_key(&argument.directives))
+                })
+        }
+        TypeDefinition::Union(union) => has_key(&union.directives),
+        TypeDefinition::Enum(enum_type) => {
+            has_key(&enum_type.directives)
+                || enum_type
+                    .values
+                    .iter()
+                    .any(|value| has_key(&value.directives))
+        }
+        TypeDefinition::InputObject(input) => {
+            has_key(&input.directives)
+                || input.fields.iter().any(|field| has_key(&field.directives))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::native::compose::compose_services;
+    use crate::native::{compose, normalize_subgraphs, parse_subgraphs};
+    use crate::{CompositionError, HarmonizerError, ServiceDefinition};
+
+    /// The errors of composing the subgraphs, which these rules make up the
+    /// most of (short of those of the composed schema).
+    fn composition_errors(subgraphs: &[(&str, &str)]) -> Vec<CompositionError> {
+        let service_list: Vec<ServiceDefinition> = subgraphs
+            .iter()
+            .map(|(name, type_defs)| ServiceDefinition::new(*name, "undefined", *type_defs))
+            .collect();
+        let subgraphs = parse_subgraphs(&service_list).unwrap();
+        let (_, errors) = compose_services(&normalize_subgraphs(&subgraphs));
+        errors
+    }
+
+    fn codes_and_messages(errors: &[CompositionError]) -> Vec<(&str, &str)> {
+        errors
+            .iter()
+            .map(|error| (error.code(), error.message.as_deref().unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn it_reports_enums_whose_values_differ_between_subgraphs() {
+        let errors = match compose(&[
+            ServiceDefinition::new(
+                "paint",
+                "undefined",
+                "type Query { paint: Color } enum Color { RED GREEN }",
+            ),
+            ServiceDefinition::new(
+                "ink",
+                "undefined",
+                "extend type Query { ink: Color } enum Color { RED }",
+            ),
+        ]) {
+            Err(HarmonizerError::Composition(errors)) => errors,
+            result => panic!("expected composition errors, got {:?}", result),
+        };
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].code(), "ENUM_MISMATCH");
+        assert_eq!(
+            errors[0].message.as_deref(),
+            Some("The `Color` enum does not have identical values in all services. Groups of services with identical values are: [paint], [ink]")
+        );
+        assert_eq!(errors[0].locations.len(), 2);
+    }
+
+    #[test]
+    fn it_reports_enums_which_are_other_types_in_some_subgraphs() {
+        let errors = composition_errors(&[
+            ("serviceA", "enum ProductType { BOOK FURNITURE }"),
+            ("serviceB", "type ProductType { id: String }"),
+            ("serviceC", "enum ProductType { FURNITURE BOOK DIGITAL }"),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors)[0],
+            (
+                "ENUM_MISMATCH_TYPE",
+                "[serviceA] ProductType -> ProductType is an enum in [serviceA, serviceC], but not in [serviceB]"
+            )
+        );
+        assert_eq!(errors[0].locations.len(), 3);
+    }
+
+    #[test]
+    fn it_reports_fields_defined_more_than_once() {
+        let errors = composition_errors(&[
+            ("serviceA", "type Product { sku: ID! }"),
+            ("serviceB", "extend type Product { sku: Int! }"),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "UNKNOWN",
+                "[serviceB] Product.sku -> Field \"Product.sku\" already exists in the schema. It cannot also be defined in this type extension. If this is meant to be an external field, add the `@external` directive."
+            )]
+        );
+        assert_eq!(errors[0].locations[0].subgraph.as_deref(), Some("serviceA"));
+
+        let errors = composition_errors(&[
+            ("serviceA", "type Product { sku: ID! }"),
+            ("serviceB", "type Product { sku: ID! color: String }"),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![
+                ("UNKNOWN", "Field \"Product.sku\" can only be defined once."),
+                ("UNKNOWN", "There can be only one type named \"Product\"."),
+            ]
+        );
+        assert_eq!(errors[0].locations.len(), 2);
+    }
+
+    #[test]
+    fn it_reports_types_defined_differently_by_several_subgraphs() {
+        let errors = composition_errors(&[
+            ("serviceA", "type Product { sku: ID! }"),
+            ("serviceB", "type Product { color: String! }"),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![("UNKNOWN", "There can be only one type named \"Product\".")]
+        );
+
+        let errors = composition_errors(&[
+            (
+                "serviceA",
+                "type Product { sku: ID! color: String quantity: Int }",
+            ),
+            (
+                "serviceB",
+                "type Product { sku: String! color: String quantity: Int! }",
+            ),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![
+                ("VALUE_TYPE_FIELD_TYPE_MISMATCH", "[serviceA] Product.sku -> A field was defined differently in different services. `serviceA` and `serviceB` define `Product.sku` as a ID! and String! respectively. In order to define `Product` in multiple places, the fields and their types must be identical."),
+                ("VALUE_TYPE_FIELD_TYPE_MISMATCH", "[serviceA] Product.quantity -> A field was defined differently in different services. `serviceA` and `serviceB` define `Product.quantity` as a Int and Int! respectively. In order to define `Product` in multiple places, the fields and their types must be identical."),
+            ]
+        );
+
+        let errors = composition_errors(&[
+            ("serviceA", "type Person { age(relative: Boolean!): Int }"),
+            ("serviceB", "type Person { age(relative: Boolean): Int }"),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "VALUE_TYPE_INPUT_VALUE_MISMATCH",
+                "[serviceA] Person -> A field's input type (`relative`) was defined differently in different services. `serviceA` and `serviceB` define `relative` as a Boolean! and Boolean respectively. In order to define `Person` in multiple places, the input values and their types must be identical."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_value_types_of_different_kinds() {
+        let errors = composition_errors(&[
+            ("serviceA", "input Product { sku: ID }"),
+            ("serviceB", "type Product { sku: ID }"),
+        ]);
+        // Definitions of different kinds aren't value types, so their fields
+        // are reported as duplicates too.
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![
+                ("UNKNOWN", "Field \"Product.sku\" can only be defined once."),
+                (
+                    "VALUE_TYPE_KIND_MISMATCH",
+                    "[serviceA] Product -> Found kind mismatch on expected value type belonging to services `serviceA` and `serviceB`. `Product` is defined as both a `ObjectTypeDefinition` and a `InputObjectTypeDefinition`. In order to define `Product` in multiple places, the kinds must be identical."
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_value_types_which_are_entities() {
+        let errors = composition_errors(&[
+            (
+                "serviceA",
+                r#"type Product @key(fields: "sku") { sku: ID }"#,
+            ),
+            ("serviceB", "type Product { sku: ID }"),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "VALUE_TYPE_NO_ENTITY",
+                "[serviceA] Product -> Value types cannot be entities (using the `@key` directive). Please ensure that the `Product` type is extended properly or remove the `@key` directive if this is not an entity."
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_unions_whose_members_differ_between_subgraphs() {
+        let errors = composition_errors(&[
+            (
+                "serviceA",
+                r#"union ProductOrError = Product | Error type Error { code: Int! message: String! } type Product @key(fields: "sku") { sku: ID! }"#,
+            ),
+            (
+                "serviceB",
+                r#"union ProductOrError = Product type Error { code: Int! message: String! } extend type Product @key(fields: "sku") { sku: ID! @external colors: [String] }"#,
+            ),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "VALUE_TYPE_UNION_TYPES_MISMATCH",
+                "[serviceA] ProductOrError -> The union `ProductOrError` is defined in services `serviceA` and `serviceB`, however their types do not match. Union types with the same name must also consist of identical types. The type Error is mismatched."
+            )]
+        );
+        assert_eq!(errors[0].locations.len(), 2);
+
+        assert!(composition_errors(&[
+            (
+                "serviceA",
+                "union Result = A | B type A { a: Int } type B { b: Int }"
+            ),
+            (
+                "serviceB",
+                "union Result = B | A type A { a: Int } type B { b: Int }"
+            ),
+        ])
+        .is_empty());
+    }
+
+    #[test]
+    fn it_reports_extensions_without_a_definition_of_the_same_kind() {
+        let errors = composition_errors(&[("serviceA", "extend type Product { id: ID! }")]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "EXTENSION_WITH_NO_BASE",
+                "[serviceA] Product -> `Product` is an extension type, but `Product` is not defined in any service"
+            )]
+        );
+
+        let errors = composition_errors(&[
+            ("serviceA", "extend type Product { sku: ID }"),
+            ("serviceB", "input Product { id: ID! }"),
+        ]);
+        assert_eq!(
+            codes_and_messages(&errors),
+            vec![(
+                "EXTENSION_OF_WRONG_KIND",
+                "[serviceA] Product -> `Product` was originally defined as a InputObjectTypeDefinition and can only be extended by a InputObjectTypeExtension. serviceA defines Product as a ObjectTypeExtension"
+            )]
+        );
+    }
+}
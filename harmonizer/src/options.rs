@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+/// Limits on the resources which composition (or any other call into the
+/// JavaScript runtime of a [`Harmonizer`]) may use, so that a pathological
+/// set of subgraphs can't take down the process which is composing them.
+///
+/// By default, there are no limits other than V8's own.
+///
+/// [`Harmonizer`]: crate::Harmonizer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComposeOptions {
+    /// How long a single call may run for before its execution is terminated
+    /// and it fails with [`HarmonizerError::Timeout`].
+    ///
+    /// [`HarmonizerError::Timeout`]: crate::HarmonizerError::Timeout
+    pub timeout: Option<Duration>,
+    /// The maximum size, in bytes, of the V8 heap.  A call which would grow
+    /// the heap beyond it is terminated and fails with
+    /// [`HarmonizerError::HeapLimitExceeded`].
+    ///
+    /// The heap also holds the composition library itself, so this has to
+    /// leave room for it.  A runtime which can't even evaluate the library
+    /// within the limit fails to be created with the same error.
+    ///
+    /// [`HarmonizerError::HeapLimitExceeded`]: crate::HarmonizerError::HeapLimitExceeded
+    pub max_heap_size: Option<usize>,
+}
@@ -0,0 +1,110 @@
+use crate::{CompositionError, CompositionErrorLocation};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+/// The result of a successful composition.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositionOutput {
+    /// The supergraph SDL, which is what the gateway will be configured with.
+    pub supergraph_sdl: String,
+    /// Anything about the subgraphs which didn't prevent composition but
+    /// which their owners will likely still want to know about.
+    #[serde(default)]
+    pub hints: Vec<CompositionHint>,
+}
+
+/// Something noteworthy about the subgraphs which were composed, which did not
+/// prevent them from being composed into a supergraph.
+///
+/// Hints are attributed to the subgraphs, locations and schema coordinates
+/// they are about in the same way that [`CompositionError`]s are.
+///
+/// [`CompositionError`]: crate::CompositionError
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompositionHint {
+    /// What kind of hint this is.
+    pub code: CompositionHintCode,
+    /// A human-readable description of the hint.
+    pub message: String,
+    /// The places within the subgraphs' SDL that this hint refers to.
+    #[serde(default)]
+    pub locations: Vec<CompositionErrorLocation>,
+    /// The names of the subgraphs this hint is about, if they could be
+    /// determined.
+    #[serde(default)]
+    pub subgraphs: Vec<String>,
+    /// The schema coordinate this hint is about (e.g., `User`, `User.name` or
+    /// `@auth`), if it could be determined.
+    #[serde(default)]
+    pub coordinate: Option<String>,
+}
+
+impl Display for CompositionHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+/// The kinds of [`CompositionHint`]s which can be reported by composition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CompositionHintCode {
+    /// A type, field or enum value is defined by more than one subgraph with
+    /// different descriptions, of which only one makes it into the supergraph.
+    InconsistentDescription,
+    /// A type can't be reached from any of the supergraph's root operation
+    /// types.
+    UnusedType,
+    /// A custom type system directive is used within a subgraph, but only
+    /// executable directives are included in the supergraph.
+    DirectiveDropped,
+}
+
+impl CompositionHintCode {
+    /// The code as it is reported by composition.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompositionHintCode::InconsistentDescription => "INCONSISTENT_DESCRIPTION",
+            CompositionHintCode::UnusedType => "UNUSED_TYPE",
+            CompositionHintCode::DirectiveDropped => "DIRECTIVE_DROPPED",
+        }
+    }
+}
+
+impl Display for CompositionHintCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The result of composing in best-effort mode, with
+/// [`Harmonizer::compose_best_effort`] or [`harmonize_best_effort`], which
+/// reports whatever could be composed even when composition fails.
+///
+/// [`Harmonizer::compose_best_effort`]: crate::Harmonizer::compose_best_effort
+/// [`harmonize_best_effort`]: crate::harmonize_best_effort
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialComposition {
+    /// The supergraph SDL printed from the schema which composition managed to
+    /// put together, even if that is incomplete.  This is only `None` when
+    /// nothing could be composed at all (e.g., because of a syntax error).
+    #[serde(default)]
+    pub supergraph_sdl: Option<String>,
+    /// The errors which prevented a complete supergraph from being produced.
+    #[serde(default)]
+    pub errors: Vec<CompositionError>,
+    /// Anything else about the subgraphs that their owners will likely want to
+    /// know about.
+    #[serde(default)]
+    pub hints: Vec<CompositionHint>,
+}
+
+impl PartialComposition {
+    /// Whether composition succeeded, in which case the
+    /// [`PartialComposition::supergraph_sdl`] is the complete supergraph.
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty() && self.supergraph_sdl.is_some()
+    }
+}
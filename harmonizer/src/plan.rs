@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+mod operation;
+mod print;
+
+/// A query plan, as built by the bundled [`@apollo/query-planner`] for an
+/// operation against a supergraph.  This mirrors the [`QueryPlan`] type of
+/// `query-planner-js`.
+///
+/// Its [`Display`] implementation prints it exactly like the
+/// [`prettyFormatQueryPlan`] function of `query-planner-js` does.
+///
+/// [`@apollo/query-planner`]: https://npm.im/@apollo/query-planner
+/// [`QueryPlan`]: https://github.com/apollographql/federation/blob/d7ca0bc2/query-planner-js/src/QueryPlan.ts#L10-L13
+/// [`prettyFormatQueryPlan`]: https://github.com/apollographql/federation/blob/d7ca0bc2/query-planner-js/src/prettyFormatQueryPlan.ts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueryPlan {
+    /// The root of the plan, which is `None` when there's nothing to fetch
+    /// (e.g., for an operation which only selects `__typename`).
+    #[serde(default)]
+    pub node: Option<PlanNode>,
+}
+
+/// A node within a [`QueryPlan`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind")]
+pub enum PlanNode {
+    /// See [`SequenceNode`].
+    Sequence(SequenceNode),
+    /// See [`ParallelNode`].
+    Parallel(ParallelNode),
+    /// See [`FetchNode`].
+    Fetch(FetchNode),
+    /// See [`FlattenNode`].
+    Flatten(FlattenNode),
+}
+
+/// Nodes which must be executed one after the other, since each of them
+/// depends on the results of the ones before it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SequenceNode {
+    /// The nodes to execute, in order.
+    pub nodes: Vec<PlanNode>,
+}
+
+/// Nodes which are independent of each other and can be executed in parallel.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParallelNode {
+    /// The nodes to execute.
+    pub nodes: Vec<PlanNode>,
+}
+
+/// A fetch of an operation from a single subgraph.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchNode {
+    /// The name of the subgraph to fetch from.
+    pub service_name: String,
+    /// The names of the variables of the original operation which are used by
+    /// this fetch's `operation`.
+    #[serde(default)]
+    pub variable_usages: Vec<String>,
+    /// The selections which the entities fetched with `_entities` require as
+    /// their representations, if this is an entity fetch.
+    #[serde(default)]
+    pub requires: Option<Vec<QueryPlanSelectionNode>>,
+    /// The operation to send to the subgraph.
+    pub operation: String,
+}
+
+/// A node whose results are merged into the response at a `path`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FlattenNode {
+    /// Where in the response the results of the `node` belong, in which `@`
+    /// stands for every element of a list.
+    pub path: Vec<ResponsePathElement>,
+    /// The node whose results are merged.
+    pub node: Box<PlanNode>,
+}
+
+/// An element of a [`FlattenNode::path`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum ResponsePathElement {
+    /// A field's response name, or `@` for every element of a list.
+    Key(String),
+    /// An index into a list.
+    Index(usize),
+}
+
+impl Display for ResponsePathElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponsePathElement::Key(key) => f.write_str(key),
+            ResponsePathElement::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+/// A selection within the [`FetchNode::requires`] of an entity fetch.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind")]
+pub enum QueryPlanSelectionNode {
+    /// A field, with its own selections if it isn't a leaf.
+    #[serde(rename_all = "camelCase")]
+    Field {
+        /// The alias of the field, if it has one.
+        #[serde(default)]
+        alias: Option<String>,
+        /// The name of the field.
+        name: String,
+        /// The selections of the field, if it isn't a leaf.
+        #[serde(default)]
+        selections: Option<Vec<QueryPlanSelectionNode>>,
+    },
+    /// An inline fragment.
+    #[serde(rename_all = "camelCase")]
+    InlineFragment {
+        /// The name of the type this fragment applies to, if any.
+        #[serde(default)]
+        type_condition: Option<String>,
+        /// The selections of the fragment.
+        selections: Vec<QueryPlanSelectionNode>,
+    },
+}
+
+/// An error which prevented an operation from being planned, e.g., because it
+/// isn't valid against the supergraph.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlanningError {
+    /// A human-readable description of the error.
+    pub message: String,
+    /// The places within the operation that this error refers to.
+    #[serde(default)]
+    pub locations: Vec<PlanningErrorLocation>,
+}
+
+impl Display for PlanningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A position within an operation document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlanningErrorLocation {
+    /// The line within the document, starting at 1.
+    pub line: usize,
+    /// The column within the line, starting at 1.
+    pub column: usize,
+}
+
+impl Display for PlanningErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
@@ -0,0 +1,685 @@
+//! Just enough of a GraphQL parser and printer to print the operations of a
+//! [`FetchNode`] the way that `graphql-js`'s `print` does when
+//! `prettyFormatQueryPlan` prints them.
+//!
+//! The operations are printed by the query planner itself, so we can expect
+//! them to be valid executable documents.  Unlike a general purpose parser,
+//! this keeps everything that `print` needs to reproduce them byte for byte,
+//! like the exact text of numbers, the order of the fields of input objects
+//! and whether strings were block strings.
+//!
+//! [`FetchNode`]: super::FetchNode
+
+/// `graphql-js` breaks the arguments of a field onto separate lines when
+/// printing them on one line would exceed this length.
+const MAX_LINE_LENGTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Selection {
+    Field {
+        alias: Option<String>,
+        name: String,
+        arguments: Vec<(String, Value)>,
+        directives: Vec<Directive>,
+        selection_set: Vec<Selection>,
+    },
+    FragmentSpread {
+        name: String,
+        directives: Vec<Directive>,
+    },
+    InlineFragment {
+        type_condition: Option<String>,
+        directives: Vec<Directive>,
+        selection_set: Vec<Selection>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Directive {
+    name: String,
+    arguments: Vec<(String, Value)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Value {
+    Variable(String),
+    Int(String),
+    Float(String),
+    String { value: String, block: bool },
+    Boolean(bool),
+    Null,
+    Enum(String),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Definition {
+    Operation {
+        operation: String,
+        selection_set: Vec<Selection>,
+    },
+    Fragment {
+        name: String,
+        type_condition: String,
+        directives: Vec<Directive>,
+        selection_set: Vec<Selection>,
+    },
+}
+
+/// Print the operation of a fetch like `prettyFormatQueryPlan` does, which
+/// leaves out the operation definition itself and, for entity fetches, the
+/// `_entities` field, so only the selections which are fetched remain.
+///
+/// Returns `None` if the operation can't be parsed.
+pub(super) fn print_fetch_operation(operation: &str) -> Option<String> {
+    let definitions = Parser::new(operation).parse_document()?;
+    let printed: Vec<String> = definitions
+        .iter()
+        .map(|definition| match definition {
+            Definition::Operation {
+                operation,
+                selection_set,
+            } => match selection_set.first() {
+                Some(Selection::Field {
+                    name,
+                    selection_set: entities,
+                    ..
+                }) if operation == "query" && name == "_entities" => print_selection_set(entities),
+                _ => print_selection_set(selection_set),
+            },
+            Definition::Fragment {
+                name,
+                type_condition,
+                directives,
+                selection_set,
+            } => format!(
+                "fragment {} on {} {}{}",
+                name,
+                type_condition,
+                wrap("", &join(directives.iter().map(print_directive), " "), " "),
+                print_selection_set(selection_set)
+            ),
+        })
+        .collect();
+    Some(join(printed, "\n\n"))
+}
+
+pub(super) fn print_selection_set(selections: &[Selection]) -> String {
+    block(selections.iter().map(print_selection))
+}
+
+fn print_selection(selection: &Selection) -> String {
+    match selection {
+        Selection::Field {
+            alias,
+            name,
+            arguments,
+            directives,
+            selection_set,
+        } => {
+            let prefix = format!("{}{}", wrap("", alias.as_deref().unwrap_or(""), ": "), name);
+            let mut arguments_line = format!(
+                "{}{}",
+                prefix,
+                wrap("(", &join(arguments.iter().map(print_argument), ", "), ")")
+            );
+            if arguments_line.encode_utf16().count() > MAX_LINE_LENGTH {
+                arguments_line = format!(
+                    "{}{}",
+                    prefix,
+                    wrap(
+                        "(\n",
+                        &indent(&join(arguments.iter().map(print_argument), "\n")),
+                        "\n)"
+                    )
+                );
+            }
+            join(
+                vec![
+                    arguments_line,
+                    join(directives.iter().map(print_directive), " "),
+                    print_selection_set(selection_set),
+                ],
+                " ",
+            )
+        }
+        Selection::FragmentSpread { name, directives } => format!(
+            "...{}{}",
+            name,
+            wrap(" ", &join(directives.iter().map(print_directive), " "), "")
+        ),
+        Selection::InlineFragment {
+            type_condition,
+            directives,
+            selection_set,
+        } => join(
+            vec![
+                "...".to_string(),
+                wrap("on ", type_condition.as_deref().unwrap_or(""), ""),
+                join(directives.iter().map(print_directive), " "),
+                print_selection_set(selection_set),
+            ],
+            " ",
+        ),
+    }
+}
+
+fn print_directive(directive: &Directive) -> String {
+    format!(
+        "@{}{}",
+        directive.name,
+        wrap(
+            "(",
+            &join(directive.arguments.iter().map(print_argument), ", "),
+            ")"
+        )
+    )
+}
+
+fn print_argument((name, value): &(String, Value)) -> String {
+    format!("{}: {}", name, print_value(value))
+}
+
+fn print_value(value: &Value) -> String {
+    match value {
+        Value::Variable(name) => format!("${}", name),
+        Value::Int(raw) | Value::Float(raw) | Value::Enum(raw) => raw.clone(),
+        Value::String { value, block: true } => print_block_string(value, "  "),
+        Value::String {
+            value,
+            block: false,
+        } => serde_json::to_string(value).expect("strings can always be serialized"),
+        Value::Boolean(value) => value.to_string(),
+        Value::Null => "null".to_string(),
+        Value::List(values) => format!("[{}]", join(values.iter().map(print_value), ", ")),
+        Value::Object(fields) => format!("{{{}}}", join(fields.iter().map(print_argument), ", ")),
+    }
+}
+
+/// `printBlockString` from `graphql-js`.
+fn print_block_string(value: &str, indentation: &str) -> String {
+    let is_single_line = !value.contains('\n');
+    let has_leading_space = value.starts_with(' ') || value.starts_with('\t');
+    let has_trailing_quote = value.ends_with('"');
+    let has_trailing_slash = value.ends_with('\\');
+    let print_as_multiple_lines = !is_single_line || has_trailing_quote || has_trailing_slash;
+
+    let mut result = String::new();
+    if print_as_multiple_lines && !(is_single_line && has_leading_space) {
+        result.push('\n');
+        result.push_str(indentation);
+    }
+    if indentation.is_empty() {
+        result.push_str(value);
+    } else {
+        result.push_str(&value.replace('\n', &format!("\n{}", indentation)));
+    }
+    if print_as_multiple_lines {
+        result.push('\n');
+    }
+
+    format!("\"\"\"{}\"\"\"", result.replace("\"\"\"", "\\\"\"\""))
+}
+
+/// `join` from `graphql-js`'s printer, which skips empty strings.
+fn join<I: IntoIterator<Item = String>>(parts: I, separator: &str) -> String {
+    parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// `wrap` from `graphql-js`'s printer, which wraps only non-empty strings.
+fn wrap(start: &str, string: &str, end: &str) -> String {
+    if string.is_empty() {
+        String::new()
+    } else {
+        format!("{}{}{}", start, string, end)
+    }
+}
+
+fn indent(string: &str) -> String {
+    wrap("  ", &string.replace('\n', "\n  "), "")
+}
+
+fn block<I: IntoIterator<Item = String>>(parts: I) -> String {
+    wrap("{\n", &indent(&join(parts, "\n")), "\n}")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Punctuator(char),
+    Spread,
+    Name(String),
+    Int(String),
+    Float(String),
+    String { value: String, block: bool },
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+    peeked: Option<Option<Token>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Parser<'a> {
+        Parser {
+            source,
+            position: 0,
+            peeked: None,
+        }
+    }
+
+    fn parse_document(&mut self) -> Option<Vec<Definition>> {
+        let mut definitions = Vec::new();
+        while self.peek().is_some() {
+            definitions.push(self.parse_definition()?);
+        }
+        Some(definitions)
+    }
+
+    fn parse_definition(&mut self) -> Option<Definition> {
+        match self.peek()? {
+            Token::Punctuator('{') => Some(Definition::Operation {
+                operation: "query".to_string(),
+                selection_set: self.parse_selection_set()?,
+            }),
+            Token::Name(name) if name == "fragment" => {
+                self.next();
+                let name = self.expect_name()?;
+                if self.expect_name()? != "on" {
+                    return None;
+                }
+                Some(Definition::Fragment {
+                    name,
+                    type_condition: self.expect_name()?,
+                    directives: self.parse_directives()?,
+                    selection_set: self.parse_selection_set()?,
+                })
+            }
+            Token::Name(_) => {
+                let operation = self.expect_name()?;
+                if let Some(Token::Name(_)) = self.peek() {
+                    self.next();
+                }
+                if self.peek() == Some(&Token::Punctuator('(')) {
+                    self.skip_variable_definitions()?;
+                }
+                self.parse_directives()?;
+                Some(Definition::Operation {
+                    operation,
+                    selection_set: self.parse_selection_set()?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The variable definitions of an operation are never printed, so all
+    /// that matters is getting past them.
+    fn skip_variable_definitions(&mut self) -> Option<()> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Punctuator('(') | Token::Punctuator('[') | Token::Punctuator('{') => {
+                    depth += 1
+                }
+                Token::Punctuator(')') | Token::Punctuator(']') | Token::Punctuator('}') => {
+                    depth -= 1
+                }
+                _ => {}
+            }
+            if depth == 0 {
+                return Some(());
+            }
+        }
+    }
+
+    fn parse_selection_set(&mut self) -> Option<Vec<Selection>> {
+        self.expect(Token::Punctuator('{'))?;
+        let mut selections = Vec::new();
+        while !self.skip(Token::Punctuator('}')) {
+            selections.push(self.parse_selection()?);
+        }
+        Some(selections)
+    }
+
+    fn parse_optional_selection_set(&mut self) -> Option<Vec<Selection>> {
+        if self.peek() == Some(&Token::Punctuator('{')) {
+            self.parse_selection_set()
+        } else {
+            Some(Vec::new())
+        }
+    }
+
+    fn parse_selection(&mut self) -> Option<Selection> {
+        if self.skip(Token::Spread) {
+            return match self.peek() {
+                Some(Token::Name(name)) if name != "on" => Some(Selection::FragmentSpread {
+                    name: self.expect_name()?,
+                    directives: self.parse_directives()?,
+                }),
+                _ => {
+                    let type_condition = match self.peek() {
+                        Some(Token::Name(_)) => {
+                            self.next();
+                            Some(self.expect_name()?)
+                        }
+                        _ => None,
+                    };
+                    Some(Selection::InlineFragment {
+                        type_condition,
+                        directives: self.parse_directives()?,
+                        selection_set: self.parse_selection_set()?,
+                    })
+                }
+            };
+        }
+
+        let name_or_alias = self.expect_name()?;
+        let (alias, name) = if self.skip(Token::Punctuator(':')) {
+            (Some(name_or_alias), self.expect_name()?)
+        } else {
+            (None, name_or_alias)
+        };
+        Some(Selection::Field {
+            alias,
+            name,
+            arguments: self.parse_arguments()?,
+            directives: self.parse_directives()?,
+            selection_set: self.parse_optional_selection_set()?,
+        })
+    }
+
+    fn parse_arguments(&mut self) -> Option<Vec<(String, Value)>> {
+        let mut arguments = Vec::new();
+        if self.skip(Token::Punctuator('(')) {
+            while !self.skip(Token::Punctuator(')')) {
+                let name = self.expect_name()?;
+                self.expect(Token::Punctuator(':'))?;
+                arguments.push((name, self.parse_value()?));
+            }
+        }
+        Some(arguments)
+    }
+
+    fn parse_directives(&mut self) -> Option<Vec<Directive>> {
+        let mut directives = Vec::new();
+        while self.skip(Token::Punctuator('@')) {
+            directives.push(Directive {
+                name: self.expect_name()?,
+                arguments: self.parse_arguments()?,
+            });
+        }
+        Some(directives)
+    }
+
+    fn parse_value(&mut self) -> Option<Value> {
+        Some(match self.next()? {
+            Token::Punctuator('$') => Value::Variable(self.expect_name()?),
+            Token::Int(raw) => Value::Int(raw),
+            Token::Float(raw) => Value::Float(raw),
+            Token::String { value, block } => Value::String { value, block },
+            Token::Name(name) => match name.as_str() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                "null" => Value::Null,
+                _ => Value::Enum(name),
+            },
+            Token::Punctuator('[') => {
+                let mut values = Vec::new();
+                while !self.skip(Token::Punctuator(']')) {
+                    values.push(self.parse_value()?);
+                }
+                Value::List(values)
+            }
+            Token::Punctuator('{') => {
+                let mut fields = Vec::new();
+                while !self.skip(Token::Punctuator('}')) {
+                    let name = self.expect_name()?;
+                    self.expect(Token::Punctuator(':'))?;
+                    fields.push((name, self.parse_value()?));
+                }
+                Value::Object(fields)
+            }
+            _ => return None,
+        })
+    }
+
+    fn expect_name(&mut self) -> Option<String> {
+        match self.next()? {
+            Token::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Option<()> {
+        if self.next()? == token {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Consume the next token if it is the given one.
+    fn skip(&mut self, token: Token) -> bool {
+        if self.peek() == Some(&token) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lex());
+        }
+        self.peeked.as_ref().and_then(|token| token.as_ref())
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        match self.peeked.take() {
+            Some(token) => token,
+            None => self.lex(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn lex(&mut self) -> Option<Token> {
+        // Skip everything which is ignored: whitespace, line terminators,
+        // commas, comments and the byte order mark.
+        loop {
+            let rest = self.rest();
+            let c = rest.chars().next()?;
+            match c {
+                ' ' | '\t' | '\n' | '\r' | ',' | '\u{feff}' => self.position += c.len_utf8(),
+                '#' => self.position += rest.find(['\n', '\r']).unwrap_or(rest.len()),
+                _ => break,
+            }
+        }
+
+        let rest = self.rest();
+        let c = rest.chars().next()?;
+        match c {
+            '!' | '$' | '&' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '|' | '}' => {
+                self.position += 1;
+                Some(Token::Punctuator(c))
+            }
+            '.' if rest.starts_with("...") => {
+                self.position += 3;
+                Some(Token::Spread)
+            }
+            '_' | 'a'..='z' | 'A'..='Z' => {
+                let length = rest
+                    .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+                    .unwrap_or(rest.len());
+                self.position += length;
+                Some(Token::Name(rest[..length].to_string()))
+            }
+            '-' | '0'..='9' => {
+                let length = rest
+                    .char_indices()
+                    .skip(1)
+                    .find(|(_, c)| {
+                        !(c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+                    })
+                    .map(|(index, _)| index)
+                    .unwrap_or(rest.len());
+                let raw = rest[..length].to_string();
+                self.position += length;
+                if raw.contains(['.', 'e', 'E']) {
+                    Some(Token::Float(raw))
+                } else {
+                    Some(Token::Int(raw))
+                }
+            }
+            '"' if rest.starts_with("\"\"\"") => self.lex_block_string(),
+            '"' => self.lex_string(),
+            _ => None,
+        }
+    }
+
+    fn lex_string(&mut self) -> Option<Token> {
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices().skip(1);
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += index + 1;
+                    return Some(Token::String {
+                        value,
+                        block: false,
+                    });
+                }
+                '\\' => match chars.next()?.1 {
+                    '"' => value.push('"'),
+                    '\\' => value.push('\\'),
+                    '/' => value.push('/'),
+                    'b' => value.push('\u{8}'),
+                    'f' => value.push('\u{c}'),
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    'u' => {
+                        let hex: String = (0..4)
+                            .map(|_| chars.next().map(|(_, c)| c))
+                            .collect::<Option<_>>()?;
+                        value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    _ => return None,
+                },
+                '\n' | '\r' => return None,
+                c => value.push(c),
+            }
+        }
+        None
+    }
+
+    fn lex_block_string(&mut self) -> Option<Token> {
+        let body = &self.rest()[3..];
+        let mut raw = String::new();
+        let mut index = 0;
+        loop {
+            let rest = &body[index..];
+            if rest.starts_with("\"\"\"") {
+                self.position += 3 + index + 3;
+                return Some(Token::String {
+                    value: dedent_block_string_value(&raw),
+                    block: true,
+                });
+            } else if rest.starts_with("\\\"\"\"") {
+                raw.push_str("\"\"\"");
+                index += 4;
+            } else {
+                let c = rest.chars().next()?;
+                raw.push(c);
+                index += c.len_utf8();
+            }
+        }
+    }
+}
+
+/// `dedentBlockStringValue` from `graphql-js`, which turns the raw contents
+/// of a block string into its value.
+fn dedent_block_string_value(raw: &str) -> String {
+    let raw = raw.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines: Vec<&str> = raw.split('\n').collect();
+
+    let leading_whitespace = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let is_blank = |line: &str| leading_whitespace(line) == line.len();
+
+    let common_indent = lines
+        .iter()
+        .skip(1)
+        .filter(|line| !is_blank(line))
+        .map(|line| leading_whitespace(line))
+        .min();
+    if let Some(common_indent) = common_indent.filter(|indent| *indent > 0) {
+        for line in lines.iter_mut().skip(1) {
+            *line = line.get(common_indent..).unwrap_or("");
+        }
+    }
+
+    while matches!(lines.first(), Some(line) if is_blank(line)) {
+        lines.remove(0);
+    }
+    while matches!(lines.last(), Some(line) if is_blank(line)) {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::print_fetch_operation;
+
+    #[test]
+    fn it_prints_operations_like_graphql_js() {
+        assert_eq!(
+            print_fetch_operation(
+                r#"query($representations:[_Any!]!$locale:String){_entities(representations:$representations){...on Book{title(locale:$locale format:{case:UPPER,length:1.50})@include(if:true) aliased:year}}}"#
+            )
+            .unwrap(),
+            r#"{
+  ... on Book {
+    title(locale: $locale, format: {case: UPPER, length: 1.50}) @include(if: true)
+    aliased: year
+  }
+}"#
+        );
+
+        assert_eq!(
+            print_fetch_operation(concat!(
+                r#"{product(upc:"1\"é",description:""""#,
+                "\n  a\n  b\n",
+                r#"""",filter:{a:"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",b:"bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"}){...F}}"#,
+                "fragment F on Product{name}",
+            ))
+            .unwrap(),
+            r#"{
+  product(
+    upc: "1\"é"
+    description: """
+      a
+      b
+    """
+    filter: {a: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", b: "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"}
+  ) {
+    ...F
+  }
+}
+
+fragment F on Product {
+  name
+}"#
+        );
+    }
+}
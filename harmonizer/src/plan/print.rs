@@ -0,0 +1,280 @@
+//! Printing of [`QueryPlan`]s in the format of `prettyFormatQueryPlan` from
+//! `query-planner-js`, which is `pretty-format` with the `queryPlanSerializer`
+//! and `astSerializer` plugins and its default configuration.
+
+use super::operation::{print_fetch_operation, print_selection_set, Selection};
+use super::{FetchNode, PlanNode, QueryPlan, QueryPlanSelectionNode};
+use std::fmt::{self, Display, Formatter};
+
+/// The `indent` of `pretty-format`'s default configuration.
+const INDENT: &str = "  ";
+
+impl Display for QueryPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("QueryPlan {")?;
+        print_nodes(f, self.node.iter(), "")?;
+        f.write_str("}")
+    }
+}
+
+fn print_nodes<'a, I>(f: &mut Formatter<'_>, nodes: I, indentation: &str) -> fmt::Result
+where
+    I: ExactSizeIterator<Item = &'a PlanNode>,
+{
+    if nodes.len() == 0 {
+        return Ok(());
+    }
+
+    let indentation_next = format!("{}{}", indentation, INDENT);
+    f.write_str("\n")?;
+    let count = nodes.len();
+    for (index, node) in nodes.enumerate() {
+        f.write_str(&indentation_next)?;
+        print_node(f, node, &indentation_next)?;
+        // Unlike the last node, every other node is followed by a newline.
+        f.write_str(",")?;
+        if index < count - 1 {
+            f.write_str("\n")?;
+        }
+    }
+    write!(f, "\n{}", indentation)
+}
+
+fn print_node(f: &mut Formatter<'_>, node: &PlanNode, indentation: &str) -> fmt::Result {
+    let nodes: Vec<&PlanNode> = match node {
+        PlanNode::Fetch(fetch) => return print_fetch(f, fetch, indentation),
+        PlanNode::Flatten(flatten) => {
+            f.write_str("Flatten(path: \"")?;
+            for (index, element) in flatten.path.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(".")?;
+                }
+                write!(f, "{}", element)?;
+            }
+            f.write_str("\")")?;
+            vec![&flatten.node]
+        }
+        PlanNode::Sequence(sequence) => {
+            f.write_str("Sequence")?;
+            sequence.nodes.iter().collect()
+        }
+        PlanNode::Parallel(parallel) => {
+            f.write_str("Parallel")?;
+            parallel.nodes.iter().collect()
+        }
+    };
+
+    if !nodes.is_empty() {
+        f.write_str(" {")?;
+        print_nodes(f, nodes.into_iter(), indentation)?;
+        f.write_str("}")?;
+    }
+    Ok(())
+}
+
+fn print_fetch(f: &mut Formatter<'_>, fetch: &FetchNode, indentation: &str) -> fmt::Result {
+    let indentation_next = format!("{}{}", indentation, INDENT);
+
+    writeln!(f, "Fetch(service: \"{}\") {{", fetch.service_name)?;
+    if let Some(requires) = &fetch.requires {
+        let requires: Vec<Selection> = requires.iter().map(Selection::from).collect();
+        f.write_str(&print_ast(
+            &print_selection_set(&requires),
+            &indentation_next,
+        ))?;
+        f.write_str(" =>\n")?;
+    }
+    // The planner's operations are always valid, but should one not be, we
+    // would rather print it as it is than not at all.
+    let operation =
+        print_fetch_operation(&fetch.operation).unwrap_or_else(|| fetch.operation.clone());
+    f.write_str(&print_ast(&operation, &indentation_next))?;
+    write!(f, "\n{}}}", indentation)
+}
+
+/// Re-indent a document printed by `graphql-js` the way that `astSerializer`
+/// does, keeping documents which fit on a single line as they are.
+fn print_ast(printed: &str, indentation: &str) -> String {
+    let lines: Vec<&str> = printed.trim().split('\n').collect();
+    if lines.len() == 1 {
+        return lines[0].to_string();
+    }
+
+    lines
+        .iter()
+        .map(|line| {
+            // `graphql-js` always indents with 2 spaces.
+            let indentation_length = (line.len() - line.trim_start_matches(' ').len()) / 2 * 2;
+            format!(
+                "{}{}{}",
+                indentation,
+                INDENT.repeat(indentation_length / 2),
+                &line[indentation_length..]
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl From<&QueryPlanSelectionNode> for Selection {
+    /// Like `remapInlineFragmentNodes` in `astSerializer`, which leaves out
+    /// the aliases of fields.
+    fn from(selection: &QueryPlanSelectionNode) -> Self {
+        match selection {
+            QueryPlanSelectionNode::Field {
+                name, selections, ..
+            } => Selection::Field {
+                alias: None,
+                name: name.clone(),
+                arguments: Vec::new(),
+                directives: Vec::new(),
+                selection_set: selections.iter().flatten().map(Selection::from).collect(),
+            },
+            QueryPlanSelectionNode::InlineFragment {
+                type_condition,
+                selections,
+            } => Selection::InlineFragment {
+                type_condition: type_condition.clone(),
+                directives: Vec::new(),
+                selection_set: selections.iter().map(Selection::from).collect(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::QueryPlan;
+
+    /// From `should use two independent fetches when requesting root fields
+    /// from two services` in `gateway-js`'s `buildQueryPlan.test.ts`.
+    #[test]
+    fn it_prints_like_pretty_format_query_plan() {
+        let requires = |fields: &[&str]| {
+            serde_json::json!([{
+                "kind": "InlineFragment",
+                "typeCondition": "Book",
+                "selections": fields
+                    .iter()
+                    .map(|name| serde_json::json!({ "kind": "Field", "name": name }))
+                    .collect::<Vec<_>>(),
+            }])
+        };
+        let query_plan: QueryPlan = serde_json::from_value(serde_json::json!({
+            "kind": "QueryPlan",
+            "node": {
+                "kind": "Parallel",
+                "nodes": [
+                    {
+                        "kind": "Fetch",
+                        "serviceName": "accounts",
+                        "variableUsages": [],
+                        "operation": "{me{name}}",
+                    },
+                    {
+                        "kind": "Sequence",
+                        "nodes": [
+                            {
+                                "kind": "Fetch",
+                                "serviceName": "product",
+                                "variableUsages": [],
+                                "operation": "{topProducts{__typename ...on Book{__typename isbn}...on Furniture{name}}}",
+                            },
+                            {
+                                "kind": "Flatten",
+                                "path": ["topProducts", "@"],
+                                "node": {
+                                    "kind": "Fetch",
+                                    "serviceName": "books",
+                                    "requires": requires(&["__typename", "isbn"]),
+                                    "variableUsages": [],
+                                    "operation": "query($representations:[_Any!]!){_entities(representations:$representations){...on Book{__typename isbn title year}}}",
+                                },
+                            },
+                            {
+                                "kind": "Flatten",
+                                "path": ["topProducts", "@"],
+                                "node": {
+                                    "kind": "Fetch",
+                                    "serviceName": "product",
+                                    "requires": requires(&["__typename", "isbn", "title", "year"]),
+                                    "variableUsages": [],
+                                    "operation": "query($representations:[_Any!]!){_entities(representations:$representations){...on Book{name}}}",
+                                },
+                            },
+                        ],
+                    },
+                ],
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            query_plan.to_string(),
+            r#"QueryPlan {
+  Parallel {
+    Fetch(service: "accounts") {
+      {
+        me {
+          name
+        }
+      }
+    },
+    Sequence {
+      Fetch(service: "product") {
+        {
+          topProducts {
+            __typename
+            ... on Book {
+              __typename
+              isbn
+            }
+            ... on Furniture {
+              name
+            }
+          }
+        }
+      },
+      Flatten(path: "topProducts.@") {
+        Fetch(service: "books") {
+          {
+            ... on Book {
+              __typename
+              isbn
+            }
+          } =>
+          {
+            ... on Book {
+              __typename
+              isbn
+              title
+              year
+            }
+          }
+        },
+      },
+      Flatten(path: "topProducts.@") {
+        Fetch(service: "product") {
+          {
+            ... on Book {
+              __typename
+              isbn
+              title
+              year
+            }
+          } =>
+          {
+            ... on Book {
+              name
+            }
+          }
+        },
+      },
+    },
+  },
+}"#
+        );
+
+        assert_eq!(QueryPlan { node: None }.to_string(), "QueryPlan {}");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::workers::Workers;
+use crate::{ComposeOptions, CompositionOutput, HarmonizerError, ServiceList};
+use std::sync::mpsc::channel;
+
+/// A pool of warm composition runtimes, each on a dedicated thread, for
+/// composing many [`ServiceList`]s in parallel.
+///
+/// A [`Harmonizer`] composes one [`ServiceList`] at a time, on the thread it
+/// is called on.  A `HarmonizerPool` instead distributes the service lists it
+/// is given across its threads, of which there should be no more than there
+/// are CPU cores to run them on, since composition is CPU-bound.
+///
+/// ```no_run
+/// use harmonizer::{HarmonizerPool, ServiceDefinition};
+///
+/// let pool = HarmonizerPool::new(4)?;
+/// let variants = (0..100)
+///     .map(|variant| {
+///         vec![ServiceDefinition::new(
+///             "users",
+///             "http://users",
+///             format!("type Query {{ variant{}: String }}", variant),
+///         )]
+///     })
+///     .collect();
+/// for composed in pool.compose_many(variants) {
+///     println!("{}", composed?.supergraph_sdl);
+/// }
+/// # Ok::<(), harmonizer::HarmonizerError>(())
+/// ```
+///
+/// [`Harmonizer`]: crate::Harmonizer
+pub struct HarmonizerPool {
+    workers: Workers,
+}
+
+impl HarmonizerPool {
+    /// Start a pool of `size` runtimes (at least one), returning once each of
+    /// them has evaluated the composition library.
+    pub fn new(size: usize) -> Result<HarmonizerPool, HarmonizerError> {
+        HarmonizerPool::with_options(size, ComposeOptions::default())
+    }
+
+    /// Start a pool like [`HarmonizerPool::new`] does, whose compositions are
+    /// each limited by the given [`ComposeOptions`].
+    pub fn with_options(
+        size: usize,
+        options: ComposeOptions,
+    ) -> Result<HarmonizerPool, HarmonizerError> {
+        Ok(HarmonizerPool {
+            workers: Workers::start(size, options)?,
+        })
+    }
+
+    /// Compose each of the [`ServiceList`]s, in parallel, returning once all
+    /// of them have been composed.  The results are in the same order as the
+    /// service lists, and each of them is what [`Harmonizer::compose`] would
+    /// have returned for its service list.
+    ///
+    /// [`Harmonizer::compose`]: crate::Harmonizer::compose
+    pub fn compose_many(
+        &self,
+        service_lists: Vec<ServiceList>,
+    ) -> Vec<Result<CompositionOutput, HarmonizerError>> {
+        let mut results: Vec<Option<Result<CompositionOutput, HarmonizerError>>> =
+            service_lists.iter().map(|_| None).collect();
+
+        let (result_tx, result_rx) = channel();
+        for (index, service_list) in service_lists.into_iter().enumerate() {
+            let result_tx = result_tx.clone();
+            let submitted = self.workers.submit(Box::new(move |harmonizer| {
+                let result = harmonizer.and_then(|harmonizer| harmonizer.compose(service_list));
+                let usable = !matches!(&result, Err(err) if err.is_internal());
+                let _ = result_tx.send((index, result));
+                usable
+            }));
+            if submitted.is_err() {
+                results[index] = Some(Err(HarmonizerError::WorkerStopped));
+            }
+        }
+        // The results stop arriving once every job has sent its result (or
+        // has been dropped by a worker which stopped without running it).
+        drop(result_tx);
+        for (index, result) in result_rx {
+            results[index] = Some(result);
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or(Err(HarmonizerError::WorkerStopped)))
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for HarmonizerPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HarmonizerPool").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{harmonize, HarmonizerError, HarmonizerPool, ServiceDefinition};
+
+    #[test]
+    fn it_composes_many_service_lists_in_order() {
+        let pool = HarmonizerPool::new(3).unwrap();
+        let service_list = |name: &str| {
+            vec![ServiceDefinition::new(
+                name,
+                "undefined",
+                format!("type Query {{ {}: String }}", name),
+            )]
+        };
+
+        let names = ["users", "movies", "books", "reviews", "accounts"];
+        let mut service_lists: Vec<_> = names.iter().map(|name| service_list(name)).collect();
+        service_lists.insert(
+            2,
+            vec![ServiceDefinition::new("broken", "undefined", "type {")],
+        );
+
+        let results = pool.compose_many(service_lists);
+        assert_eq!(results.len(), names.len() + 1);
+        assert!(matches!(results[2], Err(HarmonizerError::Composition(_))));
+
+        let composed = results.into_iter().flatten();
+        for (name, output) in names.iter().zip(composed) {
+            assert_eq!(output, harmonize(service_list(name)).unwrap());
+        }
+
+        assert!(pool.compose_many(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn it_keeps_the_order_of_service_lists_which_finish_out_of_order() {
+        let pool = HarmonizerPool::new(2).unwrap();
+        // The larger service lists take longer to compose, so the smaller ones
+        // submitted after them finish first on the other worker.
+        let service_list = |size: usize| -> Vec<ServiceDefinition> {
+            (0..size)
+                .map(|index| {
+                    ServiceDefinition::new(
+                        format!("subgraph{}", index),
+                        "undefined",
+                        format!(
+                            "{} Query {{ field{}x{}: String }}",
+                            if index == 0 { "type" } else { "extend type" },
+                            index,
+                            size
+                        ),
+                    )
+                })
+                .collect()
+        };
+
+        let sizes = [30, 1, 20, 1, 1, 10, 2];
+        let results = pool.compose_many(sizes.iter().map(|size| service_list(*size)).collect());
+        assert_eq!(results.len(), sizes.len());
+        for (size, result) in sizes.iter().zip(results) {
+            assert_eq!(result.unwrap(), harmonize(service_list(*size)).unwrap());
+        }
+    }
+}
@@ -0,0 +1,875 @@
+use crate::syntax::{check_service_list, CheckedServices};
+use crate::{
+    console, ComposeOptions, CompositionError, CompositionOutput, ConsoleLevel, HarmonizerError,
+    PartialComposition, PlanningError, QueryPlan, ServiceDefinition, ServiceList, SupergraphError,
+};
+use anyhow::anyhow;
+#[cfg(feature = "snapshot")]
+use deno_core::Snapshot;
+use deno_core::{op_sync, v8, JsRuntime, RuntimeOptions};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{fmt, thread};
+
+/// A `Harmonizer` owns a JavaScript runtime which has already evaluated the
+/// bundled composition library, so that it can compose any number of
+/// [`ServiceList`]s without paying for V8 startup and the evaluation of
+/// `composition.js` on every call.
+///
+/// Each call to [`Harmonizer::compose`] is isolated from the ones before it:
+/// the service list is removed from the runtime's global scope once
+/// composition has finished and any stray results left over from a previous
+/// call are discarded.
+///
+/// Anything the JavaScript prints, e.g., with `console.warn`, is logged through
+/// the [`log`] facade with the `harmonizer::js` target, unless the
+/// `Harmonizer` was created with a console of its own using
+/// [`Harmonizer::with_console`].  Nothing is ever written to stdout.
+///
+/// The time and memory each call may use can be limited with
+/// [`ComposeOptions`], see [`Harmonizer::with_options`].
+///
+/// ```no_run
+/// use harmonizer::{Harmonizer, ServiceDefinition};
+///
+/// let mut harmonizer = Harmonizer::new()?;
+/// for _ in 0..3 {
+///     let output = harmonizer.compose(vec![ServiceDefinition::new(
+///         "users",
+///         "http://users",
+///         "type Query { me: String }",
+///     )])?;
+///     println!("{}", output.supergraph_sdl);
+/// }
+/// # Ok::<(), harmonizer::HarmonizerError>(())
+/// ```
+///
+/// [`log`]: https://crates.io/crates/log
+pub struct Harmonizer {
+    runtime: JsRuntime,
+    results: Receiver<serde_json::Value>,
+    options: ComposeOptions,
+    isolate: v8::IsolateHandle,
+    execution: Arc<Mutex<Execution>>,
+    checked_services: CheckedServices,
+}
+
+/// The state of the call which a [`Harmonizer`] is executing, which is shared
+/// with everything that may terminate it.
+#[derive(Debug, Default)]
+struct Execution {
+    /// Whether a call is being executed.  Outside of calls there is nothing
+    /// to terminate, and terminating anyway would terminate the next call.
+    running: bool,
+    /// Why the execution of the current call was terminated, if it was.
+    termination: Option<Termination>,
+}
+
+impl Execution {
+    /// Finish the current call, returning why it was terminated, if it was.
+    fn finish(&mut self) -> Option<Termination> {
+        self.running = false;
+        self.termination.take()
+    }
+}
+
+/// The reasons for which a call's execution can be terminated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Termination {
+    Timeout,
+    HeapLimitExceeded,
+    Cancelled,
+}
+
+impl Harmonizer {
+    /// Create a new [`Harmonizer`], initializing the JavaScript runtime and
+    /// evaluating the composition library within it.
+    pub fn new() -> Result<Harmonizer, HarmonizerError> {
+        Harmonizer::with_console_and_options(console::log, ComposeOptions::default())
+    }
+
+    /// Create a new [`Harmonizer`] like [`Harmonizer::new`] does, which
+    /// rather than logging anything the JavaScript prints passes it to the
+    /// `console` callback along with the [`ConsoleLevel`] it was printed with.
+    pub fn with_console<F>(console: F) -> Result<Harmonizer, HarmonizerError>
+    where
+        F: Fn(ConsoleLevel, &str) + 'static,
+    {
+        Harmonizer::with_console_and_options(console, ComposeOptions::default())
+    }
+
+    /// Create a new [`Harmonizer`] like [`Harmonizer::new`] does, whose calls
+    /// are limited by the given [`ComposeOptions`].
+    pub fn with_options(options: ComposeOptions) -> Result<Harmonizer, HarmonizerError> {
+        Harmonizer::with_console_and_options(console::log, options)
+    }
+
+    /// Create a new [`Harmonizer`] with both a `console` callback, like
+    /// [`Harmonizer::with_console`], and [`ComposeOptions`], like
+    /// [`Harmonizer::with_options`].
+    pub fn with_console_and_options<F>(
+        console: F,
+        options: ComposeOptions,
+    ) -> Result<Harmonizer, HarmonizerError>
+    where
+        F: Fn(ConsoleLevel, &str) + 'static,
+    {
+        let mut runtime = new_runtime(&options);
+        let isolate = runtime.v8_isolate().thread_safe_handle();
+        let execution = Arc::new(Mutex::new(Execution::default()));
+        if let Some(max_heap_size) = options.max_heap_size {
+            watch_heap_limit(&mut runtime, max_heap_size, &execution);
+        }
+
+        // We'll use this channel to get the results
+        let (tx, rx) = channel();
+
+        // The first thing we do is define an op for the `console` which
+        // `js/init.js` installs, because by default the JavaScript console
+        // functions are just stubs (they don't do anything).
+        runtime.register_op(
+            "op_console",
+            // The op_fn callback takes a state object OpState,
+            // a structured arg of type `T` and an optional ZeroCopyBuf,
+            // a mutable reference to a JavaScript ArrayBuffer
+            op_sync(
+                move |_state, (level, message): (ConsoleLevel, String), _zero_copy| {
+                    console(level, &message);
+
+                    Ok(()) // No meaningful result
+                },
+            ),
+        );
+
+        runtime.register_op(
+            "op_composition_result",
+            op_sync(move |_state, value, _zero_copy| {
+                tx.send(value)
+                    .map_err(|_| anyhow!("composition result channel is closed"))?;
+
+                Ok(serde_json::json!(null))
+
+                // Don't return anything to JS
+            }),
+        );
+
+        // Now that all of our ops are registered, we initialize the ops cache.
+        // This maps op names to their id's.  This must happen after the
+        // runtime has been created (rather than in `js/init.js`) since a
+        // cache that was captured in a startup snapshot won't know about them,
+        // and before the composition library is evaluated, so that anything
+        // it prints while being evaluated reaches the console.
+        execute(&mut runtime, "<ops>", "Deno.core.ops();")?;
+
+        // Evaluate the composition library within the runtime, unless it has
+        // been booted from a snapshot which already has.
+        execution.lock().unwrap().running = true;
+        let initialized = initialize_runtime(&mut runtime);
+        let termination = execution.lock().unwrap().finish();
+        if let Err(err) = initialized {
+            return Err(match termination {
+                Some(Termination::HeapLimitExceeded) => {
+                    HarmonizerError::HeapLimitExceeded(options.max_heap_size.unwrap_or_default())
+                }
+                _ => err,
+            });
+        }
+
+        Ok(Harmonizer {
+            runtime,
+            results: rx,
+            options,
+            isolate,
+            execution,
+            checked_services: CheckedServices::default(),
+        })
+    }
+
+    /// Get a [`CancellationHandle`] for the calls of this [`Harmonizer`], with
+    /// which they can be cancelled from another thread.
+    pub fn cancellation_handle(&self) -> CancellationHandle {
+        CancellationHandle {
+            isolate: self.isolate.clone(),
+            execution: Arc::clone(&self.execution),
+        }
+    }
+
+    /// Compose the given [`ServiceList`] into a supergraph, reusing the
+    /// JavaScript runtime owned by this [`Harmonizer`].
+    ///
+    /// The SDL of every subgraph is parsed within Rust first, and if any of
+    /// them can't be, every syntax error of every one of them is reported
+    /// (with the subgraph, line and column it is at) without composing
+    /// anything.
+    pub fn compose(
+        &mut self,
+        service_list: ServiceList,
+    ) -> Result<CompositionOutput, HarmonizerError> {
+        self.compose_with(service_list, false, false)
+            .and_then(successful_composition)
+    }
+
+    /// Compose the given [`ServiceList`], reporting whatever composition
+    /// managed to put together even when the subgraphs can't be composed.
+    ///
+    /// Unlike [`Harmonizer::compose`], composition errors are returned within
+    /// the [`PartialComposition`] (along with the supergraph SDL printed from
+    /// the incomplete schema) rather than as a [`HarmonizerError`], which is
+    /// reserved for failures within the harmonizer itself.
+    pub fn compose_best_effort(
+        &mut self,
+        service_list: ServiceList,
+    ) -> Result<PartialComposition, HarmonizerError> {
+        self.compose_with(service_list, true, false)
+    }
+
+    /// Compose the given [`ServiceList`] like [`Harmonizer::compose`] does,
+    /// but only parse, validate and normalize the subgraphs which have
+    /// changed since the previous call to `recompose` (or
+    /// [`Harmonizer::recompose_best_effort`]) on this [`Harmonizer`].
+    ///
+    /// Subgraphs are compared by their name, URL and SDL, and the result of
+    /// everything composition does with each of them on its own (including
+    /// the check for syntax errors made before entering the runtime) is kept
+    /// until the next call.  Putting the subgraphs together
+    /// into a supergraph is still done from scratch, so the result is always
+    /// the same as that of [`Harmonizer::compose`].  This cuts the time spent
+    /// recomposing a graph of many subgraphs, of which only one typically
+    /// changes at a time (e.g., while developing one of them).
+    ///
+    /// ```no_run
+    /// use harmonizer::{Harmonizer, ServiceDefinition};
+    ///
+    /// let mut harmonizer = Harmonizer::new()?;
+    /// let mut service_list = vec![
+    ///     ServiceDefinition::new("users", "http://users", "type Query { me: String }"),
+    ///     ServiceDefinition::new("movies", "http://movies", "type Query { movies: [String] }"),
+    /// ];
+    /// harmonizer.recompose(service_list.clone())?;
+    ///
+    /// // Only `movies` is parsed and validated again.
+    /// service_list[1].type_defs = "type Query { movies: [String!] }".to_string();
+    /// let output = harmonizer.recompose(service_list)?;
+    /// println!("{}", output.supergraph_sdl);
+    /// # Ok::<(), harmonizer::HarmonizerError>(())
+    /// ```
+    pub fn recompose(
+        &mut self,
+        service_list: ServiceList,
+    ) -> Result<CompositionOutput, HarmonizerError> {
+        self.compose_with(service_list, false, true)
+            .and_then(successful_composition)
+    }
+
+    /// Compose the given [`ServiceList`] like
+    /// [`Harmonizer::compose_best_effort`] does, but incrementally, like
+    /// [`Harmonizer::recompose`] does.
+    pub fn recompose_best_effort(
+        &mut self,
+        service_list: ServiceList,
+    ) -> Result<PartialComposition, HarmonizerError> {
+        self.compose_with(service_list, true, true)
+    }
+
+    /// Validate a single [`ServiceDefinition`] on its own, without composing
+    /// it with any other subgraphs, using the same rules that composition
+    /// applies to each subgraph before composing them.
+    ///
+    /// Every problem found is returned as a [`CompositionError`], which makes
+    /// an empty list a valid subgraph.  Extending types which aren't defined
+    /// by the subgraph itself is not a problem, since they are presumably
+    /// owned by another subgraph.
+    pub fn validate_subgraph(
+        &mut self,
+        service: ServiceDefinition,
+    ) -> Result<Vec<CompositionError>, HarmonizerError> {
+        if let Err(errors) = check_service_list(std::slice::from_ref(&service)) {
+            return Ok(errors);
+        }
+        let diagnostics: Diagnostics = self.run(
+            "do_validate.js",
+            include_str!("../js/do_validate.js"),
+            &[("serviceList", service_list_json(&[service])?)],
+        )?;
+        Ok(diagnostics.errors)
+    }
+
+    /// Normalize the SDL of a single [`ServiceDefinition`] in the same way
+    /// that composition does before composing it, e.g., by turning types
+    /// marked with `@extends` into type extensions and removing the
+    /// definitions of federation's own types and directives.
+    ///
+    /// SDL which can't be parsed results in a
+    /// [`HarmonizerError::Composition`].
+    pub fn normalize(&mut self, service: ServiceDefinition) -> Result<String, HarmonizerError> {
+        check_service_list(std::slice::from_ref(&service)).map_err(HarmonizerError::Composition)?;
+        let normalized: Normalized = self.run(
+            "do_normalize.js",
+            include_str!("../js/do_normalize.js"),
+            &[("serviceList", service_list_json(&[service])?)],
+        )?;
+        if !normalized.errors.is_empty() {
+            return Err(HarmonizerError::Composition(normalized.errors));
+        }
+        normalized.type_defs.ok_or(HarmonizerError::NoResult)
+    }
+
+    fn compose_with(
+        &mut self,
+        service_list: ServiceList,
+        best_effort: bool,
+        incremental: bool,
+    ) -> Result<PartialComposition, HarmonizerError> {
+        // Subgraphs which can't be parsed leave nothing to compose, so they're
+        // reported without entering the runtime at all.
+        let checked = if incremental {
+            self.checked_services.check(&service_list)
+        } else {
+            check_service_list(&service_list)
+        };
+        if let Err(errors) = checked {
+            return Ok(PartialComposition {
+                supergraph_sdl: None,
+                errors,
+                hints: Vec::new(),
+            });
+        }
+        self.run(
+            "do_compose.js",
+            include_str!("../js/do_compose.js"),
+            &[
+                ("serviceList", service_list_json(&service_list)?),
+                ("bestEffort", best_effort.to_string()),
+                ("incremental", incremental.to_string()),
+            ],
+        )
+    }
+
+    /// Build a [`QueryPlan`] for an operation against a supergraph, using the
+    /// bundled `@apollo/query-planner`.
+    ///
+    /// The `operation` is a GraphQL document which is validated against the
+    /// supergraph before it is planned.  If it contains more than one
+    /// operation, `operation_name` selects the one to plan.
+    ///
+    /// Problems with the operation are reported as
+    /// [`HarmonizerError::Planning`], while a `supergraph_sdl` which isn't a
+    /// valid supergraph is reported as [`HarmonizerError::Supergraph`], with
+    /// locations within the supergraph SDL.
+    pub fn plan(
+        &mut self,
+        supergraph_sdl: &str,
+        operation: &str,
+        operation_name: Option<&str>,
+    ) -> Result<QueryPlan, HarmonizerError> {
+        let planned: Planned = self.run(
+            "do_plan.js",
+            include_str!("../js/do_plan.js"),
+            &[
+                (
+                    "supergraphSdl",
+                    serde_json::json!(supergraph_sdl).to_string(),
+                ),
+                ("operation", serde_json::json!(operation).to_string()),
+                (
+                    "operationName",
+                    serde_json::json!(operation_name).to_string(),
+                ),
+            ],
+        )?;
+        if !planned.supergraph_errors.is_empty() {
+            return Err(HarmonizerError::Supergraph(planned.supergraph_errors));
+        }
+        if !planned.errors.is_empty() {
+            return Err(HarmonizerError::Planning(planned.errors));
+        }
+        planned.query_plan.ok_or(HarmonizerError::NoResult)
+    }
+
+    /// Evaluate one of the `do_*.js` scripts with the given globals, each of
+    /// which is a name and the JSON to assign to it, and deserialize the
+    /// result that the script reports.
+    fn run<T: DeserializeOwned>(
+        &mut self,
+        script: &'static str,
+        source: &'static str,
+        globals: &[(&str, String)],
+    ) -> Result<T, HarmonizerError> {
+        self.execution.lock().unwrap().running = true;
+        let watchdog = self.options.timeout.map(|timeout| {
+            Watchdog::start(timeout, self.isolate.clone(), Arc::clone(&self.execution))
+        });
+        let result = self.run_script(script, source, globals);
+        if let Some(watchdog) = watchdog {
+            watchdog.stop();
+        }
+
+        // A call which finished just as it was being terminated keeps its
+        // result, but either way the runtime has to be able to execute the
+        // scripts of the next call.
+        let termination = self.execution.lock().unwrap().finish();
+        let result = match termination {
+            Some(termination) => {
+                self.runtime.v8_isolate().cancel_terminate_execution();
+                if let (Termination::HeapLimitExceeded, Some(max_heap_size)) =
+                    (termination, self.options.max_heap_size)
+                {
+                    // Undo the headroom which was granted for terminating.
+                    self.runtime.remove_near_heap_limit_callback(max_heap_size);
+                    watch_heap_limit(&mut self.runtime, max_heap_size, &self.execution);
+                }
+                result.map_err(|_| match termination {
+                    Termination::Timeout => {
+                        HarmonizerError::Timeout(self.options.timeout.unwrap_or_default())
+                    }
+                    Termination::HeapLimitExceeded => HarmonizerError::HeapLimitExceeded(
+                        self.options.max_heap_size.unwrap_or_default(),
+                    ),
+                    Termination::Cancelled => HarmonizerError::Cancelled,
+                })
+            }
+            None => result,
+        };
+
+        // A script which misbehaves may report more than once for a single
+        // call.  Only the first report is meaningful and none of them may
+        // leak into the next call.
+        while self.results.try_recv().is_ok() {}
+
+        let reset_globals: String = globals
+            .iter()
+            .map(|(name, _)| format!("{} = undefined;", name))
+            .collect();
+        execute(&mut self.runtime, "<reset_globals>", &reset_globals)?;
+
+        serde_json::from_value(result?).map_err(HarmonizerError::DeserializeResult)
+    }
+
+    fn run_script(
+        &mut self,
+        script: &'static str,
+        source: &'static str,
+        globals: &[(&str, String)],
+    ) -> Result<serde_json::Value, HarmonizerError> {
+        // We literally just turn the inputs into JSON objects that we assign
+        // to globals within the runtime.
+        let set_globals: String = globals
+            .iter()
+            .map(|(name, json)| format!("{} = {};", name, json))
+            .collect();
+
+        execute(&mut self.runtime, "<set_globals>", &set_globals)?;
+        execute(&mut self.runtime, script, source)?;
+
+        // Ops are synchronous, so by the time the script has finished
+        // evaluating, its result has either been reported or never will be.
+        self.results
+            .try_recv()
+            .map_err(|_| HarmonizerError::NoResult)
+    }
+}
+
+/// A handle with which the call that a [`Harmonizer`] is executing can be
+/// cancelled, e.g., from another thread once its result is no longer needed.
+///
+/// Cancelling terminates the JavaScript execution of the call, which then
+/// returns [`HarmonizerError::Cancelled`].  The [`Harmonizer`] remains usable
+/// for the calls after it.  Only the call which is being executed when
+/// [`CancellationHandle::cancel`] is called is cancelled; when there is none,
+/// cancelling does nothing.
+///
+/// ```no_run
+/// use harmonizer::{Harmonizer, HarmonizerError, ServiceDefinition};
+///
+/// let mut harmonizer = Harmonizer::new()?;
+/// let handle = harmonizer.cancellation_handle();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+///     handle.cancel();
+/// });
+/// match harmonizer.compose(vec![ServiceDefinition::new(
+///     "users",
+///     "http://users",
+///     "type Query { me: String }",
+/// )]) {
+///     Err(HarmonizerError::Cancelled) => println!("cancelled"),
+///     composed => println!("{:?}", composed),
+/// }
+/// # Ok::<(), harmonizer::HarmonizerError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CancellationHandle {
+    isolate: v8::IsolateHandle,
+    execution: Arc<Mutex<Execution>>,
+}
+
+impl CancellationHandle {
+    /// Cancel the call which is being executed, if there is one.
+    pub fn cancel(&self) {
+        terminate(&self.isolate, &self.execution, Termination::Cancelled);
+    }
+}
+
+/// Terminates the execution of a call which is still running once its
+/// [`ComposeOptions::timeout`] has elapsed.
+struct Watchdog {
+    stop: Sender<()>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl Watchdog {
+    fn start(
+        timeout: Duration,
+        isolate: v8::IsolateHandle,
+        execution: Arc<Mutex<Execution>>,
+    ) -> Watchdog {
+        let (stop, stopped) = channel();
+        let thread = thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(timeout) {
+                terminate(&isolate, &execution, Termination::Timeout);
+            }
+        });
+        Watchdog { stop, thread }
+    }
+
+    /// Stop watching, returning once the watchdog can no longer terminate
+    /// anything.
+    fn stop(self) {
+        // The watchdog only stops waiting early once it receives this, so
+        // failing to send means it has timed out already.
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+    }
+}
+
+/// Terminate the execution of the current call within an isolate, unless
+/// there is none or it has been terminated for another reason already.
+fn terminate(isolate: &v8::IsolateHandle, execution: &Mutex<Execution>, reason: Termination) {
+    let mut execution = execution.lock().unwrap();
+    if execution.running && execution.termination.is_none() {
+        execution.termination = Some(reason);
+        isolate.terminate_execution();
+    }
+}
+
+/// Terminate the execution within a runtime once its heap approaches
+/// `max_heap_size`, which V8 would otherwise handle by crashing the process.
+fn watch_heap_limit(
+    runtime: &mut JsRuntime,
+    max_heap_size: usize,
+    execution: &Arc<Mutex<Execution>>,
+) {
+    let isolate = runtime.v8_isolate().thread_safe_handle();
+    let execution = Arc::clone(execution);
+    runtime.add_near_heap_limit_callback(move |current_limit, _initial_limit| {
+        terminate(&isolate, &execution, Termination::HeapLimitExceeded);
+        // Terminating takes a little more memory, which V8 has to be given
+        // for it not to crash regardless.
+        current_limit.max(max_heap_size) * 2
+    });
+}
+
+/// Serialize a service list into the JSON which the scripts expect to find in
+/// their `serviceList` global.
+fn service_list_json(service_list: &[ServiceDefinition]) -> Result<String, HarmonizerError> {
+    serde_json::to_string(service_list).map_err(HarmonizerError::SerializeServiceList)
+}
+
+/// The [`CompositionOutput`] of a composition which reported no errors, or
+/// those errors.
+fn successful_composition(
+    composition: PartialComposition,
+) -> Result<CompositionOutput, HarmonizerError> {
+    if !composition.errors.is_empty() {
+        return Err(HarmonizerError::Composition(composition.errors));
+    }
+
+    Ok(CompositionOutput {
+        supergraph_sdl: composition
+            .supergraph_sdl
+            .ok_or(HarmonizerError::NoResult)?,
+        hints: composition.hints,
+    })
+}
+
+/// What `do_validate.js` reports for a subgraph.
+#[derive(Deserialize)]
+struct Diagnostics {
+    errors: Vec<CompositionError>,
+}
+
+/// What `do_plan.js` reports for an operation.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Planned {
+    #[serde(default)]
+    query_plan: Option<QueryPlan>,
+    errors: Vec<PlanningError>,
+    /// Why the supergraph SDL isn't a valid supergraph, in which case there's
+    /// nothing to plan against.
+    #[serde(default)]
+    supergraph_errors: Vec<SupergraphError>,
+}
+
+/// What `do_normalize.js` reports for a subgraph.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Normalized {
+    #[serde(default)]
+    type_defs: Option<String>,
+    errors: Vec<CompositionError>,
+}
+
+/// Evaluate a script within the runtime, attributing any failure to it.
+fn execute(
+    runtime: &mut JsRuntime,
+    script: &'static str,
+    source: &str,
+) -> Result<(), HarmonizerError> {
+    runtime
+        .execute(script, source)
+        .map_err(|source| HarmonizerError::JavaScript { script, source })
+}
+
+/// The V8 startup snapshot created by `build.rs`, which contains the state of
+/// a runtime after it has evaluated `js/init.js`, `dist/composition.js` and
+/// `js/diagnostics.js`.
+#[cfg(feature = "snapshot")]
+static COMPOSITION_SNAPSHOT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/composition.snap"));
+
+/// Create a runtime with the heap limit of the [`ComposeOptions`].  With the
+/// `snapshot` feature, it is booted from [`COMPOSITION_SNAPSHOT`], which
+/// saves us from parsing and evaluating the composition library at all.
+fn new_runtime(options: &ComposeOptions) -> JsRuntime {
+    JsRuntime::new(RuntimeOptions {
+        #[cfg(feature = "snapshot")]
+        startup_snapshot: Some(Snapshot::Static(COMPOSITION_SNAPSHOT)),
+        create_params: options
+            .max_heap_size
+            .map(|max_heap_size| v8::Isolate::create_params().heap_limits(0, max_heap_size)),
+        ..Default::default()
+    })
+}
+
+/// Nothing to do, since the snapshot already contains the evaluated library.
+#[cfg(feature = "snapshot")]
+fn initialize_runtime(_runtime: &mut JsRuntime) -> Result<(), HarmonizerError> {
+    Ok(())
+}
+
+/// Evaluate the composition library within a runtime.
+#[cfg(not(feature = "snapshot"))]
+fn initialize_runtime(runtime: &mut JsRuntime) -> Result<(), HarmonizerError> {
+    // The runtime automatically contains a Deno.core object with several
+    // functions for interacting with it.
+    execute(runtime, "<init>", include_str!("../js/init.js"))?;
+
+    // Load the composition library.
+    execute(
+        runtime,
+        "composition.js",
+        include_str!("../dist/composition.js"),
+    )?;
+
+    // Define the functions shared by the `do_*.js` scripts.
+    execute(
+        runtime,
+        "diagnostics.js",
+        include_str!("../js/diagnostics.js"),
+    )?;
+
+    Ok(())
+}
+
+impl fmt::Debug for Harmonizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Harmonizer").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::execute;
+    use crate::{ComposeOptions, ConsoleLevel, Harmonizer, HarmonizerError, ServiceDefinition};
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    #[test]
+    fn it_routes_console_output_to_the_console_callback() {
+        let printed = Rc::new(RefCell::new(Vec::new()));
+        let mut harmonizer = Harmonizer::with_console({
+            let printed = Rc::clone(&printed);
+            move |level, message: &str| printed.borrow_mut().push((level, message.to_string()))
+        })
+        .unwrap();
+
+        execute(
+            &mut harmonizer.runtime,
+            "<test>",
+            "console.warn('deprecated:', { field: 'User.name' }, 1); print('done');",
+        )
+        .unwrap();
+
+        assert_eq!(
+            *printed.borrow(),
+            vec![
+                (
+                    ConsoleLevel::Warn,
+                    r#"deprecated: {"field":"User.name"} 1"#.to_string()
+                ),
+                (ConsoleLevel::Log, "done".to_string()),
+            ]
+        );
+    }
+
+    fn assert_still_composes(harmonizer: &mut Harmonizer) {
+        harmonizer
+            .compose(vec![ServiceDefinition::new(
+                "users",
+                "undefined",
+                "type Query { users: [String!] }",
+            )])
+            .unwrap();
+    }
+
+    #[test]
+    fn it_terminates_calls_which_run_for_too_long() {
+        let timeout = Duration::from_millis(200);
+        let mut harmonizer = Harmonizer::with_options(ComposeOptions {
+            timeout: Some(timeout),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let error = harmonizer
+            .run::<serde_json::Value>("<test>", "for (;;) {}", &[])
+            .unwrap_err();
+        assert!(matches!(error, HarmonizerError::Timeout(t) if t == timeout));
+        assert!(!error.is_internal());
+
+        assert_still_composes(&mut harmonizer);
+    }
+
+    #[test]
+    fn it_terminates_calls_which_use_too_much_memory() {
+        let max_heap_size = 256 * 1024 * 1024;
+        let mut harmonizer = Harmonizer::with_options(ComposeOptions {
+            max_heap_size: Some(max_heap_size),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Within a function, the arrays are garbage once the call has been
+        // terminated, rather than a global which would keep the heap full.
+        let error = harmonizer
+            .run::<serde_json::Value>(
+                "<test>",
+                "(() => { const arrays = []; for (;;) { arrays.push(new Array(1e6).fill(0)); } })()",
+                &[],
+            )
+            .unwrap_err();
+        assert!(matches!(error, HarmonizerError::HeapLimitExceeded(size) if size == max_heap_size));
+
+        assert_still_composes(&mut harmonizer);
+    }
+
+    #[test]
+    fn it_cancels_calls_from_another_thread() {
+        let mut harmonizer = Harmonizer::new().unwrap();
+
+        // Cancelling while nothing is being executed must not affect the
+        // calls after it.
+        harmonizer.cancellation_handle().cancel();
+        assert_still_composes(&mut harmonizer);
+
+        let handle = harmonizer.cancellation_handle();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            handle.cancel();
+        });
+        let error = harmonizer
+            .run::<serde_json::Value>("<test>", "for (;;) {}", &[])
+            .unwrap_err();
+        canceller.join().unwrap();
+        assert!(matches!(error, HarmonizerError::Cancelled));
+
+        assert_still_composes(&mut harmonizer);
+    }
+
+    #[test]
+    fn it_recomposes_only_the_subgraphs_which_changed() {
+        let mut harmonizer = Harmonizer::new().unwrap();
+        let mut service_list = vec![
+            ServiceDefinition::new(
+                "users",
+                "undefined",
+                r#"type User @key(fields: "id") { id: ID! name: String } type Query { me: User }"#,
+            ),
+            ServiceDefinition::new(
+                "reviews",
+                "undefined",
+                r#"extend type User @key(fields: "id") { id: ID! @external reviews: [String] }"#,
+            ),
+        ];
+
+        // Which of the subgraphs known to the runtime are the same ones that
+        // were known when they were last marked.
+        let reused = |harmonizer: &mut Harmonizer| {
+            harmonizer
+                .run::<Vec<bool>>(
+                    "<test>",
+                    "done(incrementalServices.map((service) => !!service.marked));",
+                    &[],
+                )
+                .unwrap()
+        };
+        let mark = |harmonizer: &mut Harmonizer| {
+            harmonizer
+                .run::<serde_json::Value>(
+                    "<test>",
+                    "incrementalServices.forEach((service) => { service.marked = true; }); done(null);",
+                    &[],
+                )
+                .unwrap();
+        };
+
+        assert_eq!(
+            harmonizer.recompose(service_list.clone()).unwrap(),
+            harmonizer.compose(service_list.clone()).unwrap()
+        );
+        mark(&mut harmonizer);
+
+        service_list[1].type_defs = service_list[1].type_defs.replace("[String]", "[Strin]");
+        let errors = match harmonizer.recompose(service_list.clone()) {
+            Err(HarmonizerError::Composition(errors)) => errors,
+            result => panic!("expected composition errors, got {:?}", result),
+        };
+        assert_eq!(reused(&mut harmonizer), vec![true, false]);
+        assert!(matches!(
+            harmonizer.compose(service_list.clone()),
+            Err(HarmonizerError::Composition(expected)) if expected == errors
+        ));
+        assert_eq!(
+            harmonizer
+                .recompose_best_effort(service_list.clone())
+                .unwrap(),
+            harmonizer
+                .compose_best_effort(service_list.clone())
+                .unwrap()
+        );
+        mark(&mut harmonizer);
+
+        service_list[1].type_defs = service_list[1].type_defs.replace("[Strin]", "[String]");
+        service_list.push(ServiceDefinition::new(
+            "movies",
+            "undefined",
+            "type Query { movies: [String] }",
+        ));
+        assert_eq!(
+            harmonizer.recompose(service_list.clone()).unwrap(),
+            harmonizer.compose(service_list).unwrap()
+        );
+        assert_eq!(reused(&mut harmonizer), vec![true, false, false]);
+    }
+}
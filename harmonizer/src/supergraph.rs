@@ -0,0 +1,1141 @@
+use crate::syntax::split_syntax_error;
+use crate::{HarmonizerError, QueryPlanSelectionNode};
+use graphql_parser::query::{self, OperationDefinition, Selection, TypeCondition};
+use graphql_parser::schema::{
+    Definition, Directive, Document, Field, InputValue, SchemaDefinition, Type, TypeDefinition,
+    TypeExtension, Value,
+};
+use graphql_parser::Pos;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// The scalars which every schema has, without having to define them.
+const BUILT_IN_SCALARS: &[&str] = &["Int", "Float", "String", "Boolean", "ID"];
+
+/// The core schema features, and their versions, which the query planner
+/// supports.
+const SUPPORTED_FEATURES: &[&str] = &[
+    "https://specs.apollo.dev/core/v0.1",
+    "https://specs.apollo.dev/join/v0.1",
+];
+
+/// A supergraph SDL which is known to be a core schema that uses the [join
+/// spec] in the way that the query planner expects.
+///
+/// This performs the same checks as [`buildComposedSchema`] in
+/// `query-planner-js` does before a gateway will accept a supergraph, but
+/// natively, so that a supergraph can be checked before it is deployed without
+/// starting a JavaScript runtime.
+///
+/// Beyond the SDL itself, a supergraph describes which subgraph is
+/// responsible for what, which is available from its [`graphs`] and the
+/// [`SupergraphType`]s of its object and interface [`types`].
+///
+/// [join spec]: https://specs.apollo.dev/join/v0.1
+/// [`buildComposedSchema`]: https://github.com/apollographql/federation/blob/d7ca0bc2/query-planner-js/src/composedSchema/buildComposedSchema.ts
+/// [`graphs`]: Supergraph::graphs
+/// [`types`]: Supergraph::types
+#[derive(Debug, Clone, PartialEq)]
+pub struct Supergraph {
+    sdl: String,
+    graphs: Vec<Graph>,
+    types: Vec<SupergraphType>,
+}
+
+impl Supergraph {
+    /// Parse and validate a supergraph SDL, such as the
+    /// [`CompositionOutput::supergraph_sdl`] of a composition.
+    ///
+    /// Every problem which can be found is reported as a [`SupergraphError`]
+    /// within [`HarmonizerError::Supergraph`], except for syntax errors, of
+    /// which only the first one is.
+    ///
+    /// Of the rules which `buildASTSchema` applies to the SDL before
+    /// `buildComposedSchema` gets to it, only the uniqueness of type and
+    /// directive names and that every referenced type is defined are
+    /// checked.  References to undefined types are located at the field,
+    /// argument or definition which makes them, and aren't followed by the
+    /// suggestions which `graphql-js` makes.
+    ///
+    /// [`CompositionOutput::supergraph_sdl`]: crate::CompositionOutput::supergraph_sdl
+    pub fn parse(sdl: &str) -> Result<Supergraph, HarmonizerError> {
+        let document = graphql_parser::parse_schema::<String>(sdl).map_err(|err| {
+            HarmonizerError::Supergraph(vec![SupergraphError::syntax(&err.to_string())])
+        })?;
+
+        let (graphs, types) = Validator::default()
+            .validate(&document)
+            .map_err(HarmonizerError::Supergraph)?;
+
+        Ok(Supergraph {
+            sdl: sdl.to_string(),
+            graphs,
+            types,
+        })
+    }
+
+    /// The SDL this supergraph was parsed from.
+    pub fn sdl(&self) -> &str {
+        &self.sdl
+    }
+
+    /// The subgraphs this supergraph was composed from, in the order of the
+    /// `join__Graph` enum.
+    pub fn graphs(&self) -> &[Graph] {
+        &self.graphs
+    }
+
+    /// The subgraph with a name, if there is one.
+    pub fn graph(&self, name: &str) -> Option<&Graph> {
+        self.graphs.iter().find(|graph| graph.name == name)
+    }
+
+    /// The object and interface types of this supergraph, in the order
+    /// they're defined in.
+    pub fn types(&self) -> &[SupergraphType] {
+        &self.types
+    }
+
+    /// The object type with a name, if there is one.
+    pub fn object_type(&self, name: &str) -> Option<&SupergraphType> {
+        self.type_of_kind(name, SupergraphTypeKind::Object)
+    }
+
+    /// The interface type with a name, if there is one.
+    pub fn interface_type(&self, name: &str) -> Option<&SupergraphType> {
+        self.type_of_kind(name, SupergraphTypeKind::Interface)
+    }
+
+    /// The field of an object or interface type, if there is one.  Its
+    /// [`SupergraphField::graph`] is which subgraph resolves it.
+    pub fn field(&self, type_name: &str, field_name: &str) -> Option<&SupergraphField> {
+        self.types
+            .iter()
+            .find(|supergraph_type| supergraph_type.name == type_name)?
+            .field(field_name)
+    }
+
+    fn type_of_kind(&self, name: &str, kind: SupergraphTypeKind) -> Option<&SupergraphType> {
+        self.types
+            .iter()
+            .find(|supergraph_type| supergraph_type.name == name && supergraph_type.kind == kind)
+    }
+}
+
+impl FromStr for Supergraph {
+    type Err = HarmonizerError;
+
+    fn from_str(sdl: &str) -> Result<Self, Self::Err> {
+        Supergraph::parse(sdl)
+    }
+}
+
+impl Display for Supergraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.sdl)
+    }
+}
+
+/// A subgraph of a [`Supergraph`], as described by a value of its
+/// `join__Graph` enum.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Graph {
+    /// The name of the subgraph, as it was given to composition.
+    pub name: String,
+    /// The routing URL of the subgraph.
+    pub url: String,
+}
+
+/// An object or interface type of a [`Supergraph`], which are the types the
+/// join spec directives may be applied to.
+///
+/// A type which is owned by a subgraph is an entity.  Every other type is a
+/// value type, which each subgraph which uses it defines and resolves for
+/// itself.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SupergraphType {
+    /// The name of the type.
+    pub name: String,
+    /// Whether this is an object or an interface type.
+    pub kind: SupergraphTypeKind,
+    /// The name of the subgraph which owns this type, if it's an entity.
+    pub owner: Option<String>,
+    /// The `@key`s by which each subgraph can fetch this type, if it's an
+    /// entity.
+    pub keys: Vec<EntityKey>,
+    /// The fields of the type, in the order they're defined in.
+    pub fields: Vec<SupergraphField>,
+}
+
+impl SupergraphType {
+    /// Whether this type is an entity, rather than a value type.
+    pub fn is_entity(&self) -> bool {
+        self.owner.is_some()
+    }
+
+    /// The field with a name, if there is one.
+    pub fn field(&self, name: &str) -> Option<&SupergraphField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// The `@key`s of this type within a subgraph.
+    pub fn keys_for<'a>(&'a self, graph: &'a str) -> impl Iterator<Item = &'a EntityKey> + 'a {
+        self.keys.iter().filter(move |key| key.graph == graph)
+    }
+}
+
+/// What kind of type a [`SupergraphType`] is.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SupergraphTypeKind {
+    /// An object type.
+    Object,
+    /// An interface type.
+    Interface,
+}
+
+/// One of the `@key`s of an entity within a subgraph.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EntityKey {
+    /// The name of the subgraph.
+    pub graph: String,
+    /// The fields which make up the key.
+    pub fields: Vec<QueryPlanSelectionNode>,
+}
+
+/// A field of a [`SupergraphType`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SupergraphField {
+    /// The name of the field.
+    pub name: String,
+    /// The name of the subgraph which resolves this field, which unless the
+    /// field says otherwise is the owner of its type.  This is `None` for the
+    /// fields of value types, which every subgraph resolves for itself.
+    pub graph: Option<String>,
+    /// The fields of its type which this field `@requires` to be fetched
+    /// before it can be resolved, if any.
+    pub requires: Option<Vec<QueryPlanSelectionNode>>,
+    /// The fields of the field's own type which the subgraph resolving it
+    /// `@provides` along with it, if any.
+    pub provides: Option<Vec<QueryPlanSelectionNode>>,
+}
+
+/// A problem which makes a supergraph SDL unusable by the query planner.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SupergraphError {
+    /// A human-readable description of the problem, which is the same as the
+    /// one `buildComposedSchema` reports where it reports the problem, too.
+    pub message: String,
+    /// The places within the supergraph SDL that this error refers to.
+    #[serde(default)]
+    pub locations: Vec<SupergraphErrorLocation>,
+}
+
+impl SupergraphError {
+    fn new(message: String, position: Option<Pos>) -> Self {
+        SupergraphError {
+            message,
+            locations: position
+                .map(SupergraphErrorLocation::from)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Turn the error message of a `graphql_parser::schema::ParseError` into a
+    /// [`SupergraphError`], recovering the position it mentions.
+    fn syntax(message: &str) -> Self {
+        let (position, description) = split_syntax_error(message);
+        SupergraphError::new(format!("Syntax Error: {}", description), position)
+    }
+}
+
+impl Display for SupergraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A position within a supergraph SDL.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SupergraphErrorLocation {
+    /// The line within the SDL, starting at 1.
+    pub line: usize,
+    /// The column within the line, starting at 1.
+    pub column: usize,
+}
+
+impl From<Pos> for SupergraphErrorLocation {
+    fn from(position: Pos) -> Self {
+        SupergraphErrorLocation {
+            line: position.line,
+            column: position.column,
+        }
+    }
+}
+
+impl Display for SupergraphErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The types of the arguments of the core and join directives.
+#[derive(Debug, Clone, Copy)]
+enum ArgumentType {
+    String,
+    Graph,
+    FieldSet,
+}
+
+impl ArgumentType {
+    fn name(self) -> &'static str {
+        match self {
+            ArgumentType::String => "String",
+            ArgumentType::Graph => "join__Graph",
+            ArgumentType::FieldSet => "join__FieldSet",
+        }
+    }
+}
+
+/// An argument of one of the core or join directives: its name, its type and
+/// whether that type is non-null.
+type ArgumentDefinition = (&'static str, ArgumentType, bool);
+
+const CORE_ARGUMENTS: &[ArgumentDefinition] = &[("feature", ArgumentType::String, true)];
+const JOIN_GRAPH_ARGUMENTS: &[ArgumentDefinition] = &[
+    ("name", ArgumentType::String, true),
+    ("url", ArgumentType::String, true),
+];
+const JOIN_OWNER_ARGUMENTS: &[ArgumentDefinition] = &[("graph", ArgumentType::Graph, true)];
+const JOIN_TYPE_ARGUMENTS: &[ArgumentDefinition] = &[
+    ("graph", ArgumentType::Graph, true),
+    ("key", ArgumentType::FieldSet, false),
+];
+const JOIN_FIELD_ARGUMENTS: &[ArgumentDefinition] = &[
+    ("graph", ArgumentType::Graph, false),
+    ("requires", ArgumentType::FieldSet, false),
+    ("provides", ArgumentType::FieldSet, false),
+];
+
+/// The valid value of an argument of one of the core or join directives.
+enum ArgumentValue {
+    String(String),
+    /// The name of the subgraph that a `join__Graph` value stands for.
+    Graph(String),
+    FieldSet(Vec<QueryPlanSelectionNode>),
+}
+
+/// The equivalent of `buildComposedSchema`, which rather than throwing on the
+/// first problem it finds collects all of them.
+#[derive(Default)]
+struct Validator {
+    errors: Vec<SupergraphError>,
+    /// The values of the `join__Graph` enum, if it could be found.
+    graph_values: Option<HashSet<String>>,
+    /// The subgraphs, by the `join__Graph` values which stand for them.
+    graphs: Vec<(String, Graph)>,
+}
+
+impl Validator {
+    fn validate(
+        mut self,
+        document: &Document<'_, String>,
+    ) -> Result<(Vec<Graph>, Vec<SupergraphType>), Vec<SupergraphError>> {
+        let mut schema_definition: Option<&SchemaDefinition<'_, String>> = None;
+        let mut types = HashMap::new();
+        let mut directives = HashSet::new();
+
+        for definition in &document.definitions {
+            match definition {
+                Definition::SchemaDefinition(schema) => {
+                    if schema_definition.is_some() {
+                        self.error(
+                            "Must provide only one schema definition.".to_string(),
+                            Some(schema.position),
+                        );
+                    } else {
+                        schema_definition = Some(schema);
+                    }
+                }
+                Definition::TypeDefinition(definition) => {
+                    let (name, position) = type_name_and_position(definition);
+                    if types.insert(name, definition).is_some() {
+                        self.error(
+                            format!("There can be only one type named \"{}\".", name),
+                            Some(position),
+                        );
+                    }
+                }
+                Definition::DirectiveDefinition(definition) => {
+                    if !directives.insert(definition.name.as_str()) {
+                        self.error(
+                            format!(
+                                "There can be only one directive named \"@{}\".",
+                                definition.name
+                            ),
+                            Some(definition.position),
+                        );
+                    }
+                }
+                Definition::TypeExtension(_) => {}
+            }
+        }
+
+        // `buildComposedSchema` builds the schema with `buildASTSchema`, which
+        // rejects references to types which aren't defined.
+        for definition in &document.definitions {
+            for (name, position) in type_references(definition) {
+                if !types.contains_key(name) && !BUILT_IN_SCALARS.contains(&name) {
+                    self.error(format!("Unknown type \"{}\".", name), Some(position));
+                }
+            }
+        }
+
+        let schema_definition = match schema_definition {
+            Some(schema_definition) if directives.contains("core") => schema_definition,
+            Some(schema_definition) => {
+                self.error(
+                    "Expected core schema, but can't find @core directive".to_string(),
+                    Some(schema_definition.position),
+                );
+                return Err(self.errors);
+            }
+            None => {
+                self.error(
+                    "Expected core schema, but can't find a schema definition".to_string(),
+                    None,
+                );
+                return Err(self.errors);
+            }
+        };
+
+        for directive in named(&schema_definition.directives, "core") {
+            let arguments = self.arguments(directive, CORE_ARGUMENTS);
+            if let Some(ArgumentValue::String(feature)) = arguments.get("feature") {
+                if !SUPPORTED_FEATURES.contains(&feature.as_str()) {
+                    self.error(
+                        format!(
+                            "Unsupported core schema feature and/or version: {}",
+                            feature
+                        ),
+                        Some(directive.position),
+                    );
+                }
+            }
+        }
+
+        for name in &["owner", "type", "field", "graph"] {
+            if !directives.contains(format!("join__{}", name).as_str()) {
+                self.error(
+                    format!("Composed schema should define @join__{} directive", name),
+                    None,
+                );
+            }
+        }
+
+        match types.get("join__Graph") {
+            Some(TypeDefinition::Enum(graph_enum)) => {
+                for value in &graph_enum.values {
+                    match self.single(&value.directives, "join__graph") {
+                        Some(directive) => {
+                            let mut arguments = self.arguments(directive, JOIN_GRAPH_ARGUMENTS);
+                            if let (
+                                Some(ArgumentValue::String(name)),
+                                Some(ArgumentValue::String(url)),
+                            ) = (arguments.remove("name"), arguments.remove("url"))
+                            {
+                                self.graphs.push((value.name.clone(), Graph { name, url }));
+                            }
+                        }
+                        None => self.error(
+                            format!(
+                                "join__Graph value {} in composed schema should have a @join__graph directive",
+                                value.name
+                            ),
+                            Some(value.position),
+                        ),
+                    }
+                }
+                self.graph_values = Some(
+                    graph_enum
+                        .values
+                        .iter()
+                        .map(|value| value.name.clone())
+                        .collect(),
+                );
+            }
+            Some(definition) => {
+                let (_, position) = type_name_and_position(definition);
+                self.error("join__Graph should be an enum".to_string(), Some(position));
+            }
+            None => self.error("join__Graph should be an enum".to_string(), None),
+        }
+
+        let mut supergraph_types = Vec::new();
+        for definition in &document.definitions {
+            // The join spec directives are only allowed on object and
+            // interface types.  `buildComposedSchema` only reads them from
+            // object types, but there's no reason to ignore entities which
+            // are interfaces.
+            let (kind, name, directives, type_fields) = match definition {
+                Definition::TypeDefinition(TypeDefinition::Object(object)) => (
+                    SupergraphTypeKind::Object,
+                    &object.name,
+                    &object.directives,
+                    &object.fields,
+                ),
+                Definition::TypeDefinition(TypeDefinition::Interface(interface)) => (
+                    SupergraphTypeKind::Interface,
+                    &interface.name,
+                    &interface.directives,
+                    &interface.fields,
+                ),
+                _ => continue,
+            };
+
+            let owner_directive = self.single(directives, "join__owner");
+            let owner = owner_directive.and_then(|directive| {
+                match self
+                    .arguments(directive, JOIN_OWNER_ARGUMENTS)
+                    .remove("graph")
+                {
+                    Some(ArgumentValue::Graph(graph)) => Some(graph),
+                    _ => None,
+                }
+            });
+
+            let mut keys = Vec::new();
+            for directive in named(directives, "join__type") {
+                if owner_directive.is_none() {
+                    self.error(
+                        format!(
+                            "GraphQL type \"{}\" cannot have a @join__type directive without an @join__owner directive",
+                            name
+                        ),
+                        Some(directive.position),
+                    );
+                }
+                let mut arguments = self.arguments(directive, JOIN_TYPE_ARGUMENTS);
+                if let (Some(ArgumentValue::Graph(graph)), Some(ArgumentValue::FieldSet(fields))) =
+                    (arguments.remove("graph"), arguments.remove("key"))
+                {
+                    keys.push(EntityKey { graph, fields });
+                }
+            }
+
+            let mut fields = Vec::new();
+            for field in type_fields {
+                let mut arguments = match self.single(&field.directives, "join__field") {
+                    Some(directive) => self.arguments(directive, JOIN_FIELD_ARGUMENTS),
+                    None => HashMap::new(),
+                };
+                let graph = match arguments.remove("graph") {
+                    Some(ArgumentValue::Graph(graph)) => Some(graph),
+                    _ => owner.clone(),
+                };
+                let mut field_set = |name| match arguments.remove(name) {
+                    Some(ArgumentValue::FieldSet(fields)) => Some(fields),
+                    _ => None,
+                };
+                fields.push(SupergraphField {
+                    name: field.name.clone(),
+                    graph,
+                    requires: field_set("requires"),
+                    provides: field_set("provides"),
+                });
+            }
+
+            supergraph_types.push(SupergraphType {
+                name: name.clone(),
+                kind,
+                owner,
+                keys,
+                fields,
+            });
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        let graphs = self.graphs.into_iter().map(|(_, graph)| graph).collect();
+        Ok((graphs, supergraph_types))
+    }
+
+    fn error(&mut self, message: String, position: Option<Pos>) {
+        self.errors.push(SupergraphError::new(message, position));
+    }
+
+    /// The first of the directives with a `name` which is not repeatable,
+    /// reporting any other ones.
+    fn single<'d, 'a>(
+        &mut self,
+        directives: &'d [Directive<'a, String>],
+        name: &'static str,
+    ) -> Option<&'d Directive<'a, String>> {
+        let mut directives = named(directives, name);
+        let first = directives.next();
+        for directive in directives {
+            self.error(
+                format!(
+                    "The directive \"@{}\" can only be used once at this location.",
+                    name
+                ),
+                Some(directive.position),
+            );
+        }
+        first
+    }
+
+    /// Check the arguments of a directive against their definitions the same
+    /// way that `getArgumentValues` from `graphql-js` does, and return the ones
+    /// which are valid.
+    fn arguments(
+        &mut self,
+        directive: &Directive<'_, String>,
+        definitions: &[ArgumentDefinition],
+    ) -> HashMap<&'static str, ArgumentValue> {
+        let mut arguments = HashMap::new();
+
+        for &(name, argument_type, non_null) in definitions {
+            let value = directive
+                .arguments
+                .iter()
+                .find(|(argument, _)| argument == name)
+                .map(|(_, value)| value);
+
+            let type_name = format!(
+                "{}{}",
+                argument_type.name(),
+                if non_null { "!" } else { "" }
+            );
+            let value = match value {
+                None if non_null => {
+                    self.error(
+                        format!(
+                            "Argument \"{}\" of required type \"{}\" was not provided.",
+                            name, type_name
+                        ),
+                        Some(directive.position),
+                    );
+                    continue;
+                }
+                Some(Value::Null) if non_null => {
+                    self.error(
+                        format!(
+                            "Argument \"{}\" of non-null type \"{}\" must not be null.",
+                            name, type_name
+                        ),
+                        Some(directive.position),
+                    );
+                    continue;
+                }
+                None | Some(Value::Null) => continue,
+                Some(value) => value,
+            };
+
+            let valid = match (argument_type, value) {
+                (ArgumentType::String, Value::String(value)) => {
+                    Some(ArgumentValue::String(value.clone()))
+                }
+                (ArgumentType::Graph, Value::Enum(value)) => {
+                    let valid = match &self.graph_values {
+                        Some(values) => values.contains(value),
+                        None => true,
+                    };
+                    // A value without a `@join__graph` has already been
+                    // reported, so it can't be resolved but is still valid.
+                    let graph = self
+                        .graphs
+                        .iter()
+                        .find(|(graph_value, _)| graph_value == value)
+                        .map(|(_, graph)| graph.name.clone());
+                    match graph {
+                        Some(graph) => Some(ArgumentValue::Graph(graph)),
+                        None if valid => continue,
+                        None => None,
+                    }
+                }
+                (ArgumentType::FieldSet, Value::String(field_set)) => {
+                    match parse_field_set(field_set) {
+                        Ok(fields) => Some(ArgumentValue::FieldSet(fields)),
+                        Err(message) => {
+                            self.error(message, Some(directive.position));
+                            continue;
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            if let Some(valid) = valid {
+                arguments.insert(name, valid);
+            } else {
+                self.error(
+                    format!("Argument \"{}\" has invalid value {}.", name, value),
+                    Some(directive.position),
+                );
+            }
+        }
+
+        arguments
+    }
+}
+
+/// The directives with a `name`, in order.
+fn named<'d, 'a>(
+    directives: &'d [Directive<'a, String>],
+    name: &'static str,
+) -> impl Iterator<Item = &'d Directive<'a, String>> {
+    directives
+        .iter()
+        .filter(move |directive| directive.name == name)
+}
+
+fn type_name_and_position<'d>(definition: &'d TypeDefinition<'_, String>) -> (&'d str, Pos) {
+    match definition {
+        TypeDefinition::Scalar(scalar) => (&scalar.name, scalar.position),
+        TypeDefinition::Object(object) => (&object.name, object.position),
+        TypeDefinition::Interface(interface) => (&interface.name, interface.position),
+        TypeDefinition::Union(union) => (&union.name, union.position),
+        TypeDefinition::Enum(enum_type) => (&enum_type.name, enum_type.position),
+        TypeDefinition::InputObject(input) => (&input.name, input.position),
+    }
+}
+
+/// The names of the types which a definition refers to, each with the
+/// position of the field, argument or definition which refers to it, since
+/// `graphql-parser` doesn't keep the positions of the references themselves.
+fn type_references<'d>(definition: &'d Definition<'_, String>) -> Vec<(&'d str, Pos)> {
+    let mut references = Vec::new();
+    match definition {
+        Definition::SchemaDefinition(schema) => {
+            for name in [&schema.query, &schema.mutation, &schema.subscription]
+                .iter()
+                .copied()
+                .flatten()
+            {
+                references.push((name.as_str(), schema.position));
+            }
+        }
+        Definition::TypeDefinition(TypeDefinition::Object(object)) => {
+            name_references(
+                &object.implements_interfaces,
+                object.position,
+                &mut references,
+            );
+            field_references(&object.fields, &mut references);
+        }
+        Definition::TypeDefinition(TypeDefinition::Interface(interface)) => {
+            name_references(
+                &interface.implements_interfaces,
+                interface.position,
+                &mut references,
+            );
+            field_references(&interface.fields, &mut references);
+        }
+        Definition::TypeDefinition(TypeDefinition::Union(union)) => {
+            name_references(&union.types, union.position, &mut references);
+        }
+        Definition::TypeDefinition(TypeDefinition::InputObject(input)) => {
+            input_value_references(&input.fields, &mut references);
+        }
+        Definition::TypeDefinition(TypeDefinition::Scalar(_))
+        | Definition::TypeDefinition(TypeDefinition::Enum(_)) => {}
+        Definition::TypeExtension(TypeExtension::Object(object)) => {
+            name_references(
+                &object.implements_interfaces,
+                object.position,
+                &mut references,
+            );
+            field_references(&object.fields, &mut references);
+        }
+        Definition::TypeExtension(TypeExtension::Interface(interface)) => {
+            name_references(
+                &interface.implements_interfaces,
+                interface.position,
+                &mut references,
+            );
+            field_references(&interface.fields, &mut references);
+        }
+        Definition::TypeExtension(TypeExtension::Union(union)) => {
+            name_references(&union.types, union.position, &mut references);
+        }
+        Definition::TypeExtension(TypeExtension::InputObject(input)) => {
+            input_value_references(&input.fields, &mut references);
+        }
+        Definition::TypeExtension(TypeExtension::Scalar(_))
+        | Definition::TypeExtension(TypeExtension::Enum(_)) => {}
+        Definition::DirectiveDefinition(directive) => {
+            input_value_references(&directive.arguments, &mut references);
+        }
+    }
+    references
+}
+
+fn name_references<'d>(names: &'d [String], position: Pos, references: &mut Vec<(&'d str, Pos)>) {
+    references.extend(names.iter().map(|name| (name.as_str(), position)));
+}
+
+fn field_references<'d>(fields: &'d [Field<'_, String>], references: &mut Vec<(&'d str, Pos)>) {
+    for field in fields {
+        references.push((named_type(&field.field_type), field.position));
+        input_value_references(&field.arguments, references);
+    }
+}
+
+fn input_value_references<'d>(
+    values: &'d [InputValue<'_, String>],
+    references: &mut Vec<(&'d str, Pos)>,
+) {
+    for value in values {
+        references.push((named_type(&value.value_type), value.position));
+    }
+}
+
+/// The name of the type which a (possibly list or non-null) type wraps.
+fn named_type<'t>(type_: &'t Type<'_, String>) -> &'t str {
+    match type_ {
+        Type::NamedType(name) => name,
+        Type::ListType(inner) | Type::NonNullType(inner) => named_type(inner),
+    }
+}
+
+/// Parse a `join__FieldSet` the same way that `parseFieldSet` from
+/// `query-planner-js` does: it has to be a non-empty selection set without the
+/// braces, and without any fragment spreads.
+fn parse_field_set(source: &str) -> Result<Vec<QueryPlanSelectionNode>, String> {
+    let selection_set = format!("{{{}}}", source);
+    let document = graphql_parser::parse_query::<String>(&selection_set).map_err(|err| {
+        let (_, description) = split_syntax_error(&err.to_string());
+        format!("Syntax Error in field set \"{}\": {}", source, description)
+    })?;
+
+    let selection_set = match document.definitions.as_slice() {
+        [query::Definition::Operation(OperationDefinition::SelectionSet(selection_set))] => {
+            selection_set
+        }
+        _ => return Err(format!("Invalid field set: \"{}\"", source)),
+    };
+
+    fn convert(selections: &[Selection<'_, String>]) -> Option<Vec<QueryPlanSelectionNode>> {
+        selections
+            .iter()
+            .map(|selection| match selection {
+                Selection::FragmentSpread(_) => None,
+                Selection::Field(field) => Some(QueryPlanSelectionNode::Field {
+                    alias: field.alias.clone(),
+                    name: field.name.clone(),
+                    selections: if field.selection_set.items.is_empty() {
+                        None
+                    } else {
+                        Some(convert(&field.selection_set.items)?)
+                    },
+                }),
+                Selection::InlineFragment(fragment) => {
+                    Some(QueryPlanSelectionNode::InlineFragment {
+                        type_condition: fragment
+                            .type_condition
+                            .as_ref()
+                            .map(|TypeCondition::On(name)| name.clone()),
+                        selections: convert(&fragment.selection_set.items)?,
+                    })
+                }
+            })
+            .collect()
+    }
+
+    convert(&selection_set.items).ok_or_else(|| {
+        format!(
+            "Field sets may not contain fragment spreads, but found: \"{}\"",
+            source
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        EntityKey, Graph, HarmonizerError, QueryPlanSelectionNode, Supergraph,
+        SupergraphErrorLocation, SupergraphTypeKind,
+    };
+
+    const SUPERGRAPH: &str = r#"schema
+  @core(feature: "https://specs.apollo.dev/core/v0.1"),
+  @core(feature: "https://specs.apollo.dev/join/v0.1")
+{
+  query: Query
+}
+
+directive @core(feature: String!) repeatable on SCHEMA
+
+directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet) on FIELD_DEFINITION
+
+directive @join__type(graph: join__Graph!, key: join__FieldSet) repeatable on OBJECT | INTERFACE
+
+directive @join__owner(graph: join__Graph!) on OBJECT | INTERFACE
+
+directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+scalar join__FieldSet
+
+enum join__Graph {
+  MOVIES @join__graph(name: "movies" url: "http://movies")
+  USERS @join__graph(name: "users" url: "http://users")
+}
+
+type Movie {
+  title: String
+}
+
+type Query {
+  movies: [Movie!] @join__field(graph: MOVIES)
+  users: [User!] @join__field(graph: USERS)
+}
+
+type User
+  @join__owner(graph: USERS)
+  @join__type(graph: USERS, key: "id")
+  @join__type(graph: MOVIES, key: "id")
+{
+  favorites: [Movie!] @join__field(graph: MOVIES, requires: "name")
+  id: ID! @join__field(graph: USERS)
+  name: String @join__field(graph: USERS)
+}
+"#;
+
+    fn errors(sdl: &str) -> Vec<(String, Vec<SupergraphErrorLocation>)> {
+        match Supergraph::parse(sdl) {
+            Err(HarmonizerError::Supergraph(errors)) => errors
+                .into_iter()
+                .map(|err| (err.message, err.locations))
+                .collect(),
+            result => panic!("expected the supergraph to be invalid: {:?}", result),
+        }
+    }
+
+    fn at(line: usize, column: usize) -> Vec<SupergraphErrorLocation> {
+        vec![SupergraphErrorLocation { line, column }]
+    }
+
+    #[test]
+    fn it_parses_a_supergraph() {
+        let supergraph = Supergraph::parse(SUPERGRAPH).unwrap();
+        assert_eq!(supergraph.sdl(), SUPERGRAPH);
+        assert_eq!(SUPERGRAPH.parse::<Supergraph>().unwrap(), supergraph);
+    }
+
+    #[test]
+    fn it_describes_which_subgraph_resolves_what() {
+        let supergraph = Supergraph::parse(SUPERGRAPH).unwrap();
+
+        assert_eq!(
+            supergraph.graphs(),
+            &[
+                Graph {
+                    name: "movies".to_string(),
+                    url: "http://movies".to_string()
+                },
+                Graph {
+                    name: "users".to_string(),
+                    url: "http://users".to_string()
+                },
+            ]
+        );
+        assert_eq!(supergraph.graph("users").unwrap().url, "http://users");
+
+        let user = supergraph.object_type("User").unwrap();
+        assert!(user.is_entity());
+        assert_eq!(user.owner.as_deref(), Some("users"));
+        let id = || {
+            vec![QueryPlanSelectionNode::Field {
+                alias: None,
+                name: "id".to_string(),
+                selections: None,
+            }]
+        };
+        assert_eq!(
+            user.keys_for("movies").collect::<Vec<_>>(),
+            vec![&EntityKey {
+                graph: "movies".to_string(),
+                fields: id()
+            }]
+        );
+        assert_eq!(user.keys.len(), 2);
+
+        let favorites = supergraph.field("User", "favorites").unwrap();
+        assert_eq!(favorites.graph.as_deref(), Some("movies"));
+        assert_eq!(
+            favorites.requires,
+            Some(vec![QueryPlanSelectionNode::Field {
+                alias: None,
+                name: "name".to_string(),
+                selections: None,
+            }])
+        );
+        assert_eq!(favorites.provides, None);
+        assert_eq!(
+            supergraph.field("User", "name").unwrap().graph.as_deref(),
+            Some("users")
+        );
+        assert_eq!(
+            supergraph
+                .field("Query", "movies")
+                .unwrap()
+                .graph
+                .as_deref(),
+            Some("movies")
+        );
+
+        let movie = supergraph.object_type("Movie").unwrap();
+        assert!(!movie.is_entity());
+        assert_eq!(movie.field("title").unwrap().graph, None);
+        assert!(supergraph.field("Movie", "rating").is_none());
+    }
+
+    #[test]
+    fn it_describes_interface_entities() {
+        let supergraph = Supergraph::parse(&format!(
+            "{}\n{}",
+            SUPERGRAPH,
+            r#"interface Node @join__owner(graph: USERS) @join__type(graph: USERS, key: "id") {
+  id: ID!
+  name: String @join__field(graph: MOVIES)
+}
+"#
+        ))
+        .unwrap();
+
+        let node = supergraph.interface_type("Node").unwrap();
+        assert_eq!(node.kind, SupergraphTypeKind::Interface);
+        assert_eq!(node.owner.as_deref(), Some("users"));
+        assert_eq!(node.keys_for("users").count(), 1);
+        assert_eq!(
+            supergraph.field("Node", "id").unwrap().graph.as_deref(),
+            Some("users")
+        );
+        assert_eq!(
+            supergraph.field("Node", "name").unwrap().graph.as_deref(),
+            Some("movies")
+        );
+        assert!(supergraph.object_type("Node").is_none());
+        assert_eq!(
+            supergraph.object_type("User").unwrap().kind,
+            SupergraphTypeKind::Object
+        );
+
+        assert_eq!(
+            errors(&format!(
+                "{}\n{}",
+                SUPERGRAPH, "interface Node @join__type(graph: USERS) { id: ID! }\n"
+            )),
+            vec![(
+                "GraphQL type \"Node\" cannot have a @join__type directive without an @join__owner directive"
+                    .to_string(),
+                at(44, 16)
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reports_malformed_supergraphs() {
+        assert_eq!(
+            errors("type Query {"),
+            vec![(
+                "Syntax Error: Unexpected end of input, Expected Name".to_string(),
+                at(1, 13)
+            )]
+        );
+
+        assert_eq!(
+            errors(&SUPERGRAPH.replace("title: String", "title: Title")),
+            vec![("Unknown type \"Title\".".to_string(), at(26, 3))]
+        );
+
+        assert_eq!(
+            errors("type Query { a: String }"),
+            vec![(
+                "Expected core schema, but can't find a schema definition".to_string(),
+                vec![]
+            )]
+        );
+
+        assert_eq!(
+            errors(&SUPERGRAPH.replace("join/v0.1", "join/v0.2")),
+            vec![(
+                "Unsupported core schema feature and/or version: https://specs.apollo.dev/join/v0.2"
+                    .to_string(),
+                at(3, 3)
+            )]
+        );
+
+        assert_eq!(
+            errors(&SUPERGRAPH.replace(
+                "directive @join__owner(graph: join__Graph!) on OBJECT | INTERFACE",
+                ""
+            )),
+            vec![(
+                "Composed schema should define @join__owner directive".to_string(),
+                vec![]
+            )]
+        );
+
+        assert_eq!(
+            errors(&SUPERGRAPH.replace(
+                r#"MOVIES @join__graph(name: "movies" url: "http://movies")"#,
+                "MOVIES"
+            )),
+            vec![(
+                "join__Graph value MOVIES in composed schema should have a @join__graph directive"
+                    .to_string(),
+                at(21, 3)
+            )]
+        );
+
+        assert_eq!(
+            errors(&SUPERGRAPH.replace("@join__owner(graph: USERS)", "")),
+            vec![
+                (
+                    "GraphQL type \"User\" cannot have a @join__type directive without an @join__owner directive"
+                        .to_string(),
+                    at(36, 3)
+                ),
+                (
+                    "GraphQL type \"User\" cannot have a @join__type directive without an @join__owner directive"
+                        .to_string(),
+                    at(37, 3)
+                ),
+            ]
+        );
+
+        assert_eq!(
+            errors(
+                &SUPERGRAPH
+                    .replace(
+                        "@join__type(graph: USERS, key: \"id\")",
+                        "@join__type(key: \"id\")"
+                    )
+                    .replace("graph: USERS)\n  name", "graph: BOOKS)\n  name")
+                    .replace("requires: \"name\"", "requires: \"...Name\"")
+            ),
+            vec![
+                (
+                    "Argument \"graph\" of required type \"join__Graph!\" was not provided."
+                        .to_string(),
+                    at(36, 3)
+                ),
+                (
+                    "Field sets may not contain fragment spreads, but found: \"...Name\""
+                        .to_string(),
+                    at(39, 23)
+                ),
+                (
+                    "Argument \"graph\" has invalid value BOOKS.".to_string(),
+                    at(40, 11)
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,447 @@
+//! Parsing of the SDL of subgraphs within Rust, so that the syntax errors of a
+//! service list are reported (all of them, by subgraph, with their line and
+//! column) before anything is composed, and composition only ever sees
+//! well-formed documents.
+//!
+//! Everything else which parses GraphQL with `graphql-parser` (supergraphs and
+//! the field sets of native composition) turns its errors into messages of
+//! its own with [`split_syntax_error`].
+
+use crate::{CompositionError, CompositionErrorLocation, ServiceDefinition};
+use graphql_parser::schema::Document;
+use graphql_parser::Pos;
+#[cfg(any(test, feature = "js"))]
+use std::collections::HashMap;
+
+/// The keywords which every definition of a schema starts with, save for
+/// its description.
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "schema",
+    "scalar",
+    "type",
+    "interface",
+    "union",
+    "enum",
+    "input",
+    "directive",
+    "extend",
+];
+
+/// Parse the SDL of a subgraph, or report every syntax error within it.
+#[cfg(feature = "native")]
+pub(crate) fn parse_type_defs(
+    service: &ServiceDefinition,
+) -> Result<Document<'static, String>, Vec<CompositionError>> {
+    parse(&service.type_defs).map_err(|syntax_errors| {
+        syntax_errors
+            .into_iter()
+            .map(|(position, description)| syntax_error(&service.name, position, &description))
+            .collect()
+    })
+}
+
+/// Check that the SDL of every subgraph can be parsed before handing the
+/// service list to JavaScript composition, or report the syntax errors of all
+/// of the subgraphs which can't.
+///
+/// `graphql-parser` doesn't support everything that `graphql-js` does (i.e.,
+/// schema extensions, descriptions of the schema definition, surrogate pairs
+/// within strings and deeply nested lists), so subgraphs which it rejects for
+/// using any of that are left for `graphql-js` to parse.
+///
+/// Every subgraph is parsed twice as a result, here and again by `graphql-js`
+/// within the runtime.  [`CheckedServices`] spares incremental compositions
+/// the first of those for the subgraphs which haven't changed.
+#[cfg(feature = "js")]
+pub(crate) fn check_service_list(
+    service_list: &[ServiceDefinition],
+) -> Result<(), Vec<CompositionError>> {
+    let errors: Vec<CompositionError> = service_list.iter().flat_map(check_service).collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// The syntax errors of each subgraph of the service list which was last
+/// checked incrementally, by name, along with the URL and SDL they were found
+/// in.  This mirrors what the runtime keeps of the subgraphs which were last
+/// composed incrementally, so that `Harmonizer::recompose` only parses the
+/// subgraphs which have changed, both here and within the runtime.
+#[cfg(any(test, feature = "js"))]
+#[derive(Debug, Default)]
+pub(crate) struct CheckedServices {
+    services: HashMap<String, CheckedService>,
+}
+
+#[cfg(any(test, feature = "js"))]
+#[derive(Debug)]
+struct CheckedService {
+    url: String,
+    type_defs: String,
+    errors: Vec<CompositionError>,
+}
+
+#[cfg(any(test, feature = "js"))]
+impl CheckedServices {
+    /// Like [`check_service_list`], but only parses the subgraphs which have
+    /// changed (by name, URL and SDL) since the previous call, and forgets
+    /// those which are no longer in the service list.
+    pub(crate) fn check(
+        &mut self,
+        service_list: &[ServiceDefinition],
+    ) -> Result<(), Vec<CompositionError>> {
+        let mut previous = std::mem::take(&mut self.services);
+        let mut errors = Vec::new();
+        for service in service_list {
+            let checked = match previous.remove(&service.name) {
+                Some(checked)
+                    if checked.url == service.url && checked.type_defs == service.type_defs =>
+                {
+                    checked
+                }
+                _ => CheckedService {
+                    url: service.url.clone(),
+                    type_defs: service.type_defs.clone(),
+                    errors: check_service(service),
+                },
+            };
+            errors.extend(checked.errors.iter().cloned());
+            self.services.insert(service.name.clone(), checked);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The syntax errors of a single subgraph, if `graphql-parser` rejects it for
+/// anything other than what only `graphql-js` supports.
+#[cfg(any(test, feature = "js"))]
+fn check_service(service: &ServiceDefinition) -> Vec<CompositionError> {
+    let syntax_errors = match parse(&service.type_defs) {
+        Ok(_) => return Vec::new(),
+        Err(syntax_errors) => syntax_errors,
+    };
+    if syntax_errors
+        .iter()
+        .any(|(_, description)| is_unsupported(description))
+    {
+        return Vec::new();
+    }
+    syntax_errors
+        .into_iter()
+        .map(|(position, description)| syntax_error(&service.name, position, &description))
+        .collect()
+}
+
+/// Parse SDL, or report the position and description of every syntax error
+/// within it.
+fn parse(source: &str) -> Result<Document<'static, String>, Vec<(Option<Pos>, String)>> {
+    match graphql_parser::parse_schema::<String>(source) {
+        Ok(document) => Ok(document.into_static()),
+        Err(err) => Err(syntax_errors(source, &err.to_string())),
+    }
+}
+
+/// The position and description of every syntax error within `source`, the
+/// first of which `message` describes.
+///
+/// `graphql-parser` gives up at the first error, so parsing resumes at the
+/// first definition which starts on a line of its own after that error, and
+/// so on until the rest of the SDL parses or there's no definition left to
+/// resume at.
+fn syntax_errors(source: &str, message: &str) -> Vec<(Option<Pos>, String)> {
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let mut errors = Vec::new();
+    let mut message = message.to_string();
+    let mut line_offset = 0;
+    loop {
+        let (position, description) = split_syntax_error(&message);
+        let position = position.map(|position| Pos {
+            line: position.line + line_offset,
+            column: position.column,
+        });
+        errors.push((position, description));
+
+        let resume_at = match position.and_then(|position| {
+            (position.line..lines.len()).find(|&index| starts_definition(lines[index]))
+        }) {
+            Some(resume_at) => resume_at,
+            None => break,
+        };
+        match graphql_parser::parse_schema::<String>(&lines[resume_at..].concat()) {
+            Ok(_) => break,
+            Err(err) => {
+                message = err.to_string();
+                line_offset = resume_at;
+            }
+        }
+    }
+    errors
+}
+
+/// Whether a line starts with the keyword of a definition.
+fn starts_definition(line: &str) -> bool {
+    DEFINITION_KEYWORDS
+        .iter()
+        .any(|keyword| match line.strip_prefix(keyword) {
+            Some(rest) => !rest.starts_with(|c: char| c == '_' || c.is_ascii_alphanumeric()),
+            None => false,
+        })
+}
+
+/// Whether `graphql-parser` rejected SDL for using something which
+/// `graphql-js` supports.  The known gaps are:
+///
+/// - descriptions of the schema definition (`"""...""" schema { ... }`) and
+///   schema extensions (`extend schema ...`), both of which it rejects as
+///   "Unexpected `schema[Name]`", and
+/// - surrogate pairs within strings (`"\uD83D\uDE00"`), the first half of
+///   which it rejects as "... is not a valid unicode code point", and
+/// - list types and values nested 50 or more deep (`[[[...String...]]]`),
+///   which it rejects as "Recursion limit exceeded".
+///
+/// `graphql-parser` doesn't tell its errors apart other than by their
+/// messages, so this has to match their wording, which the tests pin for
+/// each of the gaps.
+#[cfg(any(test, feature = "js"))]
+fn is_unsupported(description: &str) -> bool {
+    description.starts_with("Unexpected `schema[Name]`")
+        || description.contains("is not a valid unicode code point")
+        || description.contains("Recursion limit exceeded")
+}
+
+/// `graphql_parser` only exposes its errors as messages like "schema parse
+/// error: Parse error at 1:6\nUnexpected `{[Punctuator]`\nExpected Name\n", so
+/// the position has to be recovered from the first line of the message.
+pub(crate) fn split_syntax_error(message: &str) -> (Option<Pos>, String) {
+    let mut lines = message.lines();
+    let position = lines
+        .next()
+        .and_then(|line| line.rsplit("at ").next())
+        .and_then(|position| {
+            let mut parts = position.trim().splitn(2, ':');
+            Some(Pos {
+                line: parts.next()?.parse().ok()?,
+                column: parts.next()?.parse().ok()?,
+            })
+        });
+    let description = lines
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    (position, description)
+}
+
+/// A [`CompositionError`] for a syntax error within the SDL of a subgraph,
+/// like the ones JavaScript composition reports.
+fn syntax_error(subgraph: &str, position: Option<Pos>, description: &str) -> CompositionError {
+    CompositionError {
+        message: Some(format!("Syntax Error: {}", description)),
+        extensions: None,
+        locations: position
+            .map(|position| CompositionErrorLocation {
+                subgraph: Some(subgraph.to_string()),
+                line: position.line,
+                column: position.column,
+            })
+            .into_iter()
+            .collect(),
+        subgraphs: vec![subgraph.to_string()],
+        coordinate: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, split_syntax_error, syntax_error};
+    use graphql_parser::Pos;
+
+    #[test]
+    fn it_reports_every_syntax_error() {
+        let errors = parse(
+            "type Query {\n  me: User\n  users: [User!\n}\n\ntype User {\n  id: ID!\n}\n\ntype Name {\n  first: String =\n}\n",
+        )
+        .unwrap_err();
+
+        let positions: Vec<Option<Pos>> = errors.iter().map(|(position, _)| *position).collect();
+        assert_eq!(
+            positions,
+            vec![
+                Some(Pos { line: 4, column: 1 }),
+                Some(Pos {
+                    line: 11,
+                    column: 17
+                })
+            ]
+        );
+        assert!(parse("type Query { me: String }").is_ok());
+    }
+
+    #[test]
+    fn it_locates_syntax_errors_within_their_subgraph() {
+        let error = syntax_error(
+            "users",
+            Some(Pos { line: 4, column: 1 }),
+            "Unexpected `}[Punctuator]`",
+        );
+        assert_eq!(
+            error.message.as_deref(),
+            Some("Syntax Error: Unexpected `}[Punctuator]`")
+        );
+        assert_eq!(error.locations[0].to_string(), "users:4:1");
+        assert_eq!(error.subgraphs, vec!["users".to_string()]);
+    }
+
+    #[test]
+    fn it_splits_the_position_off_syntax_errors() {
+        let err = graphql_parser::parse_schema::<String>("type Query {").unwrap_err();
+        assert_eq!(
+            split_syntax_error(&err.to_string()),
+            (
+                Some(Pos {
+                    line: 1,
+                    column: 13
+                }),
+                "Unexpected end of input, Expected Name".to_string()
+            )
+        );
+    }
+
+    /// SDL which `graphql-js` parses but `graphql-parser` doesn't, one for
+    /// each of the gaps which [`is_unsupported`] knows of.
+    ///
+    /// [`is_unsupported`]: super::is_unsupported
+    const UNSUPPORTED: &[&str] = &[
+        r#""""d""" schema { query: Query } type Query { a: String }"#,
+        r#"extend schema @link(url: "users") type Query { me: String }"#,
+        r#"type Query { a(b: String = "\uD83D\uDE00"): String }"#,
+    ];
+
+    /// SDL with a list type and a list value nested deeper than
+    /// `graphql-parser` parses, which can't be spelled out within
+    /// [`UNSUPPORTED`].
+    fn deeply_nested() -> Vec<String> {
+        let depth = 60;
+        vec![
+            format!(
+                "type Query {{ a: {}String{} }}",
+                "[".repeat(depth),
+                "]".repeat(depth)
+            ),
+            format!(
+                "type Query {{ a(b: [Int] = {}1{}): String }}",
+                "[".repeat(depth),
+                "]".repeat(depth)
+            ),
+        ]
+    }
+
+    #[test]
+    fn it_recognizes_what_only_graphql_js_supports() {
+        use super::is_unsupported;
+
+        for sdl in UNSUPPORTED {
+            let errors = parse(sdl).unwrap_err();
+            assert!(
+                errors
+                    .iter()
+                    .all(|(_, description)| is_unsupported(description)),
+                "{}: {:?}",
+                sdl,
+                errors
+            );
+        }
+
+        let errors = parse("type Query { a: String").unwrap_err();
+        assert!(!is_unsupported(&errors[0].1));
+    }
+
+    #[test]
+    fn it_recognizes_nesting_beyond_the_recursion_limit() {
+        use super::is_unsupported;
+
+        for sdl in deeply_nested() {
+            let errors = parse(&sdl).unwrap_err();
+            assert!(
+                errors
+                    .iter()
+                    .all(|(_, description)| is_unsupported(description)),
+                "{:?}",
+                errors
+            );
+        }
+    }
+
+    #[cfg(feature = "js")]
+    #[test]
+    fn it_leaves_what_it_does_not_support_to_javascript() {
+        use super::check_service_list;
+        use crate::ServiceDefinition;
+
+        let errors = check_service_list(&[
+            ServiceDefinition::new(
+                "users",
+                "undefined",
+                "extend schema @link(url: \"users\") type Query { me: String }",
+            ),
+            ServiceDefinition::new("movies", "undefined", "type Query { movies: [String!] "),
+            ServiceDefinition::new("reviews", "undefined", "type {"),
+        ])
+        .unwrap_err();
+
+        let subgraphs: Vec<&str> = errors
+            .iter()
+            .flat_map(|error| error.subgraphs.iter().map(String::as_str))
+            .collect();
+        assert_eq!(subgraphs, vec!["movies", "reviews"]);
+
+        for sdl in UNSUPPORTED {
+            let service = ServiceDefinition::new("users", "undefined", *sdl);
+            assert!(check_service_list(&[service]).is_ok(), "{}", sdl);
+        }
+    }
+
+    #[test]
+    fn it_only_checks_the_subgraphs_which_have_changed() {
+        use super::CheckedServices;
+        use crate::ServiceDefinition;
+
+        let mut checked = CheckedServices::default();
+        let mut service_list = vec![
+            ServiceDefinition::new("users", "http://users", "type Query { me: String }"),
+            ServiceDefinition::new("movies", "http://movies", "type Query {"),
+        ];
+        assert_eq!(checked.check(&service_list).unwrap_err().len(), 1);
+
+        // What was found is reused for the subgraphs which haven't changed, so
+        // a finding planted on `users` survives until its SDL changes.
+        let planted = syntax_error("users", None, "planted");
+        checked.services.get_mut("users").unwrap().errors = vec![planted.clone()];
+        service_list[1].type_defs = "type Query { movies: [String] }".to_string();
+        assert_eq!(checked.check(&service_list), Err(vec![planted]));
+
+        service_list[0].type_defs = "type Query { me: ID }".to_string();
+        assert_eq!(checked.check(&service_list), Ok(()));
+
+        // Subgraphs which are dropped from the service list are forgotten.
+        checked.check(&service_list[1..]).unwrap();
+        assert_eq!(checked.services.len(), 1);
+    }
+
+    #[cfg(feature = "js")]
+    #[test]
+    fn it_leaves_nesting_beyond_the_recursion_limit_to_javascript() {
+        use super::check_service_list;
+        use crate::ServiceDefinition;
+
+        for sdl in deeply_nested() {
+            let service = ServiceDefinition::new("users", "undefined", sdl);
+            assert!(check_service_list(&[service]).is_ok());
+        }
+    }
+}
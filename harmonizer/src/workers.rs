@@ -0,0 +1,134 @@
+use crate::{ComposeOptions, Harmonizer, HarmonizerError};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A unit of work for a worker, which is given the worker's [`Harmonizer`]
+/// (or the error that creating one failed with) and returns whether that
+/// [`Harmonizer`] can still be used afterwards.
+pub(crate) type Job = Box<dyn FnOnce(Result<&mut Harmonizer, HarmonizerError>) -> bool + Send>;
+
+/// Dedicated threads which each own a [`Harmonizer`], since a `JsRuntime`
+/// can't be moved between threads, and take turns running the [`Job`]s which
+/// are submitted to them.
+pub(crate) struct Workers {
+    jobs: Mutex<Option<Sender<Job>>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl Workers {
+    /// Start `count` workers (at least one) whose runtimes are limited by the
+    /// given [`ComposeOptions`], returning once all of them are ready.
+    pub(crate) fn start(count: usize, options: ComposeOptions) -> Result<Workers, HarmonizerError> {
+        let (jobs, queue) = channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        let (started, starting) = channel();
+
+        let mut workers = Workers {
+            jobs: Mutex::new(Some(jobs)),
+            threads: Vec::new(),
+        };
+        for index in 0..count.max(1) {
+            let queue = Arc::clone(&queue);
+            let options = options.clone();
+            let started = started.clone();
+            let thread = thread::Builder::new()
+                .name(format!("harmonizer-{}", index))
+                .spawn(move || {
+                    let harmonizer = Harmonizer::with_options(options.clone());
+                    let harmonizer = match harmonizer {
+                        Ok(harmonizer) => {
+                            let _ = started.send(Ok(()));
+                            // The workers are only done starting once every
+                            // one of them has let go of its sender.
+                            drop(started);
+                            harmonizer
+                        }
+                        Err(err) => {
+                            let _ = started.send(Err(err));
+                            return;
+                        }
+                    };
+                    work(harmonizer, &options, &queue);
+                })
+                // Dropping the workers stops the ones which were spawned.
+                .map_err(HarmonizerError::SpawnWorker)?;
+            workers.threads.push(thread);
+        }
+        drop(started);
+
+        for started in starting {
+            // Dropping the workers stops the ones which did start.
+            started?;
+        }
+        Ok(workers)
+    }
+
+    /// Queue a job for the next worker which is free, returning it if there
+    /// are no workers left to run it.
+    pub(crate) fn submit(&self, job: Job) -> Result<(), Job> {
+        match &*self.jobs.lock().unwrap() {
+            Some(jobs) => jobs.send(job).map_err(|err| err.0),
+            None => Err(job),
+        }
+    }
+}
+
+/// Run the queued jobs until there are none left and the queue is closed,
+/// replacing the [`Harmonizer`] whenever a job leaves it unusable.
+fn work(harmonizer: Harmonizer, options: &ComposeOptions, queue: &Mutex<Receiver<Job>>) {
+    let mut harmonizer = Some(harmonizer);
+    loop {
+        // The lock is only held while waiting, so that the other workers can
+        // take the next job while this one is busy.
+        let job = match queue.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+
+        let current = match harmonizer.take() {
+            Some(current) => Ok(current),
+            None => Harmonizer::with_options(options.clone()),
+        };
+        match current {
+            Ok(mut current) => {
+                if job(Ok(&mut current)) {
+                    harmonizer = Some(current);
+                }
+            }
+            Err(err) => {
+                job(Err(err));
+            }
+        }
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        // Closing the queue stops every worker once it has finished its
+        // current job.
+        self.jobs.lock().unwrap().take();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Workers;
+    use crate::ComposeOptions;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn it_returns_once_every_worker_has_started() {
+        let (done, finished) = channel();
+        thread::spawn(move || {
+            let workers = Workers::start(2, ComposeOptions::default());
+            let _ = done.send(workers.is_ok());
+        });
+        assert_eq!(finished.recv_timeout(Duration::from_secs(60)), Ok(true));
+    }
+}